@@ -0,0 +1,38 @@
+-- Sessions Table
+-- One row per login (device). The session id doubles as the refresh-token family:
+-- only the digest of the latest refresh token of a session is kept, so presenting
+-- an older token of the same session is detected as reuse.
+CREATE TABLE IF NOT EXISTS sessions (
+    id UUID PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL, -- SHA-256 hex digest of the current refresh token
+    user_agent VARCHAR(512),
+    ip_address VARCHAR(45),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP
+);
+
+-- Index for sessions.user_id
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+
+-- Carry the live token of every refresh-token family over as a session
+INSERT INTO sessions (id, user_id, refresh_token_hash, created_at, last_used_at, expires_at, revoked_at)
+SELECT
+    latest.session_id,
+    latest.user_id,
+    latest.token_hash,
+    (SELECT MIN(first.created_at) FROM refresh_tokens first WHERE first.session_id = latest.session_id),
+    latest.created_at,
+    latest.expires_at,
+    latest.revoked_at
+FROM refresh_tokens latest
+WHERE latest.rotated_at IS NULL
+ON CONFLICT (id) DO NOTHING;
+
+DROP TABLE IF EXISTS refresh_tokens;
+
+-- Tokens now live on sessions only
+ALTER TABLE users DROP COLUMN IF EXISTS access_token;
+ALTER TABLE users DROP COLUMN IF EXISTS refresh_token;
@@ -0,0 +1,8 @@
+-- One-Time Passwords
+-- `users.one_time_password_token` now holds only the SHA-256 hex digest of the
+-- outstanding one-time password, which stops working at `one_time_password_expires_at`
+-- or as soon as it is used.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS one_time_password_expires_at TIMESTAMP;
+
+-- Plain tokens stored before this change can no longer be matched; drop them
+UPDATE users SET one_time_password_token = NULL;
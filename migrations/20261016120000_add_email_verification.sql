@@ -0,0 +1,6 @@
+-- Email Verification
+-- Set once the user proves ownership of `users.email`; NULL while unverified.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMP;
+
+-- Accounts created before verification existed keep being able to log in
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
@@ -0,0 +1,9 @@
+-- Phone Verification
+-- Set once the user proves ownership of `users.phone_number`; NULL while unverified.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verified_at TIMESTAMP;
+
+-- The outstanding verification code, stored as its SHA-256 digest
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verification_code VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verification_expires_at TIMESTAMP;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verification_sent_at TIMESTAMP;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verification_attempts INTEGER NOT NULL DEFAULT 0;
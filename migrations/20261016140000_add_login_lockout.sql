@@ -0,0 +1,6 @@
+-- Login Lockout
+-- Failed logins since the last successful one, and when the latest happened. Each one
+-- delays the next attempt a little longer; too many lock the account until `locked_until`.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS failed_login_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_failed_login_at TIMESTAMP;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMP;
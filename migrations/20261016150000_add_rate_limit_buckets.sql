@@ -0,0 +1,11 @@
+-- Rate Limit Buckets
+-- Token buckets of the rate limiter when `rate_limit.store` is `postgres`, shared by every
+-- instance. A bucket is refilled lazily from `updated_at` whenever it is drawn from.
+CREATE TABLE IF NOT EXISTS rate_limit_buckets (
+    key VARCHAR(128) PRIMARY KEY, -- route, then the client IP or a SHA-256 digest of the account
+    tokens DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Index for sweeping idle buckets
+CREATE INDEX IF NOT EXISTS idx_rate_limit_buckets_updated_at ON rate_limit_buckets(updated_at);
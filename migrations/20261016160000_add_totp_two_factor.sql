@@ -0,0 +1,11 @@
+-- TOTP Two-Factor Authentication
+-- The base32 secret shared with the user's authenticator app, and when it was confirmed;
+-- both NULL while two-factor authentication is off.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_enabled_at TIMESTAMP;
+
+-- A secret handed out by enrollment, waiting for a first code to confirm it
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_pending_secret VARCHAR(64);
+
+-- The time step of the latest accepted code, so no code is accepted twice
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_used_step BIGINT;
@@ -0,0 +1,13 @@
+-- MFA Recovery Codes Table
+-- Single-use codes standing in for the second factor when the authenticator is lost.
+-- Only Argon2id hashes are stored; regenerating the set deletes the previous one.
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMP
+);
+
+-- Index for mfa_recovery_codes.user_id
+CREATE INDEX IF NOT EXISTS idx_mfa_recovery_codes_user_id ON mfa_recovery_codes(user_id);
@@ -0,0 +1,13 @@
+-- Unknown Email Login Failures
+-- Failed logins for emails no account has, counted like `users.failed_login_attempts` so
+-- an unknown email is answered exactly like a registered one. Rows are keyed by a SHA-256
+-- digest of the normalized email, so the emails themselves are not kept.
+CREATE TABLE IF NOT EXISTS unknown_email_login_failures (
+    email_hash VARCHAR(64) PRIMARY KEY,
+    failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failed_login_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP
+);
+
+-- Index for sweeping forgotten failures
+CREATE INDEX IF NOT EXISTS idx_unknown_email_login_failures_last_failed_login_at ON unknown_email_login_failures(last_failed_login_at);
@@ -8,8 +8,6 @@ CREATE TABLE IF NOT EXISTS users (
     password VARCHAR(255) NOT NULL, -- Stores Argon2 hashed password
     full_name VARCHAR(511) NOT NULL, -- Computed from first_name + last_name
     profile_image VARCHAR(512),
-    access_token VARCHAR(1024),
-    refresh_token VARCHAR(1024),
     one_time_password_token VARCHAR(1024),
     status VARCHAR(10) NOT NULL DEFAULT 'offline',
     last_seen VARCHAR(20),
@@ -25,3 +23,18 @@ CREATE TABLE IF NOT EXISTS users (
 -- Index for users.email
 CREATE INDEX IF NOT EXISTS idx_users_email ON users(email);
 
+-- Sessions Table
+CREATE TABLE IF NOT EXISTS sessions (
+    id UUID PRIMARY KEY, -- Also identifies the refresh-token family of the session
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL, -- SHA-256 hex digest of the current refresh token
+    user_agent VARCHAR(512),
+    ip_address VARCHAR(45),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP
+);
+
+-- Index for sessions.user_id
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
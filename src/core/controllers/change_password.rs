@@ -0,0 +1,233 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::hashing_handler::{PasswordHashError, hashing_handler};
+use crate::utils::load_config::SecuritySection;
+use crate::utils::password_policy::{
+    PasswordPolicyViolation, check_password_policy, describe_violations,
+};
+use crate::utils::session_handler::{
+    refresh_logged_out_flag, revoke_other_sessions, revoke_user_sessions,
+};
+use crate::utils::step_up_handler::verify_step_up;
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::response::Response;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    current_password: String,
+    /// A code from the authenticator app or a recovery code, when two-factor
+    /// authentication is on.
+    code: Option<String>,
+    new_password: String,
+    /// Log every other device out once the password has changed.
+    #[serde(default)]
+    revoke_other_sessions: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    revoked_sessions: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangePasswordResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+    /// Every password policy rule the chosen password breaks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_violations: Option<Vec<PasswordPolicyViolation>>,
+}
+
+fn change_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(ChangePasswordResponse {
+            response_message: "Password change failed".to_string(),
+            response: None,
+            error: Some(error),
+            password_violations: None,
+        }),
+    )
+        .into_response()
+}
+
+fn password_rejected(violations: Vec<PasswordPolicyViolation>) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ChangePasswordResponse {
+            response_message: "Password change failed".to_string(),
+            response: None,
+            error: Some(describe_violations(&violations)),
+            password_violations: Some(violations),
+        }),
+    )
+        .into_response()
+}
+
+/// Refuses the request while every password hashing slot is taken.
+fn hashing_saturated(retry_after_in_seconds: u64) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, retry_after_in_seconds.to_string())],
+        Json(ChangePasswordResponse {
+            response_message: "Password change failed".to_string(),
+            response: None,
+            error: Some("Too many password requests, try again shortly".to_string()),
+            password_violations: None,
+        }),
+    )
+        .into_response()
+}
+
+/// Replaces the authenticated user's password, given their current one and, with
+/// two-factor authentication on, a second factor.
+///
+/// With `revoke_other_sessions`, every session except the caller's is revoked. A caller
+/// authenticated without a session is then logged out too.
+pub async fn change_password(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Response {
+    let account = sqlx::query_as::<_, (String, String)>(
+        "SELECT password, full_name FROM users WHERE id = $1",
+    )
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await;
+
+    let (current_hash, full_name) = match account {
+        Ok(account) => account,
+        Err(e) => {
+            error!("PASSWORD CHANGE FAILED: {}", e);
+
+            return change_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    };
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    if let Err(e) = verify_step_up(
+        &state.db,
+        &state.password_hashing,
+        security,
+        user.id,
+        &payload.current_password,
+        payload.code.as_deref(),
+    )
+    .await
+    {
+        error!("PASSWORD CHANGE FAILED: {}", e);
+
+        return e.into_response_with(change_failed);
+    }
+
+    if payload.new_password == payload.current_password {
+        return change_failed(
+            StatusCode::BAD_REQUEST,
+            "New password must differ from the current password".to_string(),
+        );
+    }
+
+    let mut personal_info: Vec<&str> = full_name.split_whitespace().collect();
+    personal_info.push(&user.email);
+
+    if let Err(violations) = check_password_policy(
+        &payload.new_password,
+        security,
+        &personal_info,
+        &state.password_blocklist,
+    ) {
+        error!("PASSWORD CHANGE FAILED: PASSWORD DOES NOT MEET THE POLICY!");
+
+        return password_rejected(violations);
+    }
+
+    let hashed_password =
+        match hashing_handler(&payload.new_password, &state.password_hashing).await {
+            Ok(hash) => hash,
+            Err(PasswordHashError::Saturated {
+                retry_after_in_seconds,
+            }) => {
+                error!("PASSWORD CHANGE FAILED: PASSWORD HASHING IS SATURATED!");
+
+                return hashing_saturated(retry_after_in_seconds);
+            }
+            Err(e) => {
+                error!("PASSWORD HASHING ERROR!");
+
+                return change_failed(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Password hashing error: {}", e),
+                );
+            }
+        };
+
+    let result = async {
+        let mut tx = state.db.begin().await?;
+
+        // Only swap the hash the current password was checked against
+        let updated = sqlx::query(
+            "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2 AND password = $3",
+        )
+        .bind(&hashed_password)
+        .bind(user.id)
+        .bind(&current_hash)
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let revoked_sessions = match (payload.revoke_other_sessions, user.session_id) {
+            (false, _) => 0,
+            (true, Some(session_id)) => {
+                revoke_other_sessions(&mut *tx, user.id, session_id).await?
+            }
+            (true, None) => revoke_user_sessions(&mut *tx, user.id).await?,
+        };
+        refresh_logged_out_flag(&mut *tx, user.id).await?;
+        tx.commit().await?;
+
+        Ok::<Option<u64>, sqlx::Error>(Some(revoked_sessions))
+    };
+
+    match result.await {
+        Ok(Some(revoked_sessions)) => (
+            StatusCode::OK,
+            Json(ChangePasswordResponse {
+                response_message: "Password changed successfully".to_string(),
+                response: Some(ResponseCore { revoked_sessions }),
+                error: None,
+                password_violations: None,
+            }),
+        )
+            .into_response(),
+        Ok(None) => {
+            error!("PASSWORD CHANGE FAILED: PASSWORD CHANGED CONCURRENTLY!");
+
+            change_failed(
+                StatusCode::CONFLICT,
+                "Password was changed by another request".to_string(),
+            )
+        }
+        Err(e) => {
+            error!("PASSWORD CHANGE FAILED: {}", e);
+
+            change_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::load_config::SecuritySection;
+use crate::utils::recovery_codes_handler::{remaining_recovery_codes, replace_recovery_codes};
+use crate::utils::totp_handler::confirm_totp_enrollment;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTotpRequest {
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    /// Shown once; only their hashes are kept.
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfirmTotpResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn confirmation_failed(
+    status: StatusCode,
+    error: String,
+) -> (StatusCode, Json<ConfirmTotpResponse>) {
+    (
+        status,
+        Json(ConfirmTotpResponse {
+            response_message: "Two-factor confirmation failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+}
+
+/// Turns two-factor authentication on with the secret from `/mfa/totp/enroll`, given the
+/// first code the authenticator app shows for it.
+///
+/// A user without recovery codes left gets a new set, returned only here. A re-enrollment
+/// keeps the codes the user has.
+pub async fn confirm_totp(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Json(payload): Json<ConfirmTotpRequest>,
+) -> impl IntoResponse {
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    match confirm_totp_enrollment(&state.db, user.id, &payload.code, security).await {
+        Ok(true) => {
+            info!("TWO-FACTOR AUTHENTICATION ENABLED FOR USER {}", user.id);
+        }
+        Ok(false) => {
+            error!("TOTP CONFIRMATION FAILED: INVALID CODE!");
+
+            return confirmation_failed(
+                StatusCode::BAD_REQUEST,
+                "Authentication code is invalid or no enrollment is pending".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("TOTP CONFIRMATION FAILED: {}", e);
+
+            return confirmation_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    let recovery_codes = match remaining_recovery_codes(&state.db, user.id).await {
+        Ok(0) => replace_recovery_codes(&state.db, &state.password_hashing, user.id)
+            .await
+            .map(Some),
+        Ok(_) => Ok(None),
+        Err(e) => Err(e.into()),
+    };
+
+    match recovery_codes {
+        Ok(recovery_codes) => (
+            StatusCode::OK,
+            Json(ConfirmTotpResponse {
+                response_message: "Two-factor authentication enabled".to_string(),
+                response: recovery_codes.map(|recovery_codes| ResponseCore { recovery_codes }),
+                error: None,
+            }),
+        ),
+        Err(e) => {
+            // Two-factor authentication is on either way; the codes can be regenerated
+            error!("FAILED TO GENERATE RECOVERY CODES: {}", e);
+
+            (
+                StatusCode::OK,
+                Json(ConfirmTotpResponse {
+                    response_message: "Two-factor authentication enabled".to_string(),
+                    response: None,
+                    error: Some(
+                        "Recovery codes could not be generated, regenerate them at /mfa/recovery-codes"
+                            .to_string(),
+                    ),
+                }),
+            )
+        }
+    }
+}
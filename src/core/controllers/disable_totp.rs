@@ -0,0 +1,108 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::load_config::SecuritySection;
+use crate::utils::recovery_codes_handler::delete_recovery_codes;
+use crate::utils::step_up_handler::verify_step_up;
+use crate::utils::totp_handler::{disable_totp as disable_totp_for_user, is_totp_enabled};
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTotpRequest {
+    password: String,
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisableTotpResponse {
+    response_message: String,
+    error: Option<String>,
+}
+
+fn disable_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(DisableTotpResponse {
+            response_message: "Disabling two-factor authentication failed".to_string(),
+            error: Some(error),
+        }),
+    )
+        .into_response()
+}
+
+/// Turns two-factor authentication off and deletes the recovery codes, given the current
+/// password and a code from the authenticator app or a recovery code.
+pub async fn disable_totp(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Json(payload): Json<DisableTotpRequest>,
+) -> Response {
+    match is_totp_enabled(&state.db, user.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return disable_failed(
+                StatusCode::CONFLICT,
+                "Two-factor authentication is not enabled".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("FAILED TO DISABLE TOTP: {}", e);
+
+            return disable_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    if let Err(e) = verify_step_up(
+        &state.db,
+        &state.password_hashing,
+        security,
+        user.id,
+        &payload.password,
+        Some(&payload.code),
+    )
+    .await
+    {
+        error!("FAILED TO DISABLE TOTP: {}", e);
+
+        return e.into_response_with(disable_failed);
+    }
+
+    let result = async {
+        let mut tx = state.db.begin().await?;
+        disable_totp_for_user(&mut *tx, user.id).await?;
+        delete_recovery_codes(&mut *tx, user.id).await?;
+        tx.commit().await
+    };
+
+    match result.await {
+        Ok(()) => {
+            info!("TWO-FACTOR AUTHENTICATION DISABLED FOR USER {}", user.id);
+
+            (
+                StatusCode::OK,
+                Json(DisableTotpResponse {
+                    response_message: "Two-factor authentication disabled".to_string(),
+                    error: None,
+                }),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("FAILED TO DISABLE TOTP: {}", e);
+
+            disable_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
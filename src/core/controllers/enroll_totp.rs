@@ -0,0 +1,102 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::load_config::SecuritySection;
+use crate::utils::step_up_handler::verify_step_up;
+use crate::utils::totp_handler::{
+    generate_totp_secret, start_totp_enrollment, totp_provisioning_uri,
+};
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollTotpRequest {
+    password: String,
+    /// A code from the current authenticator, when re-enrolling.
+    code: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    /// Base32 secret, for entering into an authenticator app by hand.
+    secret: String,
+    /// `otpauth://` URI, usually shown as a QR code.
+    provisioning_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnrollTotpResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn enrollment_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(EnrollTotpResponse {
+            response_message: "Two-factor enrollment failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+        .into_response()
+}
+
+/// Hands the authenticated user a new TOTP secret to set their authenticator app up with.
+///
+/// Takes the current password, and a code from the current authenticator when two-factor
+/// authentication is already on. Nothing changes until the secret is confirmed at
+/// `/mfa/totp/confirm`; until then the current authenticator keeps working.
+pub async fn enroll_totp(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Json(payload): Json<EnrollTotpRequest>,
+) -> Response {
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    if let Err(e) = verify_step_up(
+        &state.db,
+        &state.password_hashing,
+        security,
+        user.id,
+        &payload.password,
+        payload.code.as_deref(),
+    )
+    .await
+    {
+        error!("TOTP ENROLLMENT FAILED: {}", e);
+
+        return e.into_response_with(enrollment_failed);
+    }
+
+    let secret = generate_totp_secret();
+
+    if let Err(e) = start_totp_enrollment(&state.db, user.id, &secret).await {
+        error!("TOTP ENROLLMENT FAILED: {}", e);
+
+        return enrollment_failed(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Database error: {}", e),
+        );
+    }
+
+    let provisioning_uri = totp_provisioning_uri(&security.totp_issuer, &user.email, &secret);
+
+    (
+        StatusCode::OK,
+        Json(EnrollTotpResponse {
+            response_message: "Confirm the enrollment with a code from your authenticator app"
+                .to_string(),
+            response: Some(ResponseCore {
+                secret,
+                provisioning_uri,
+            }),
+            error: None,
+        }),
+    )
+        .into_response()
+}
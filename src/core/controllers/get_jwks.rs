@@ -0,0 +1,25 @@
+use crate::AppState;
+use axum::extract::State;
+use axum::{
+    Json,
+    http::{HeaderValue, header::CACHE_CONTROL},
+    response::IntoResponse,
+};
+
+/// Publishes the public keys tokens are signed with, as a standard JWK set.
+///
+/// Retired keys stay listed until their grace period ends, so tokens signed before a
+/// rotation keep verifying downstream.
+///
+/// The body is the bare `{"keys": [...]}` document JWT libraries expect, so it is not
+/// wrapped in the usual response envelope. Under `HS256` the set is empty, since a
+/// shared secret must never be published.
+pub async fn get_jwks(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(
+            CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=300"),
+        )],
+        Json(state.jwt_keys.jwks()),
+    )
+}
@@ -0,0 +1,102 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, sqlx::FromRow)]
+struct SessionRecord {
+    id: Uuid,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    created_at: NaiveDateTime,
+    last_used_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionSummary {
+    id: Uuid,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    created_at: NaiveDateTime,
+    last_seen: NaiveDateTime,
+    expires_at: NaiveDateTime,
+    /// Whether this is the session the request was made from.
+    current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    sessions: Vec<SessionSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+/// Lists the active sessions (devices) of the authenticated user, most recently used first.
+pub async fn list_user_sessions(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let sessions = sqlx::query_as::<_, SessionRecord>(
+        r#"
+        SELECT
+            id,
+            user_agent,
+            ip_address,
+            created_at,
+            last_used_at,
+            expires_at
+        FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY last_used_at DESC
+        "#,
+    )
+    .bind(user.id)
+    .fetch_all(&state.db)
+    .await;
+
+    match sessions {
+        Ok(sessions) => (
+            StatusCode::OK,
+            Json(SessionsResponse {
+                response_message: "Sessions retrieved successfully".to_string(),
+                response: Some(ResponseCore {
+                    sessions: sessions
+                        .into_iter()
+                        .map(|session| SessionSummary {
+                            current: Some(session.id) == user.session_id,
+                            id: session.id,
+                            user_agent: session.user_agent,
+                            ip_address: session.ip_address,
+                            created_at: session.created_at,
+                            last_seen: session.last_used_at,
+                            expires_at: session.expires_at,
+                        })
+                        .collect(),
+                }),
+                error: None,
+            }),
+        ),
+        Err(e) => {
+            error!("FAILED TO LIST SESSIONS: {}", e);
+
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SessionsResponse {
+                    response_message: "Failed to retrieve sessions".to_string(),
+                    response: None,
+                    error: Some(format!("Database error: {}", e)),
+                }),
+            )
+        }
+    }
+}
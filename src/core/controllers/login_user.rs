@@ -1,12 +1,23 @@
-use crate::utils::generate_tokens::{User, generate_tokens};
+use crate::utils::generate_tokens::{User, generate_auth_tokens, generate_mfa_pending_token};
 use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::response::Response;
 use axum::{Json, http::StatusCode, response::IntoResponse};
 use serde::{Deserialize, Serialize};
 // utils import
 use crate::AppState;
+use crate::mailer::templates;
+use crate::utils::client_metadata::ClientMetadata;
 use crate::utils::cookie_deploy_handler::deploy_auth_cookie;
-use crate::utils::verification_handler::verification_handler; // your existing password verification function
-use chrono::NaiveDateTime;
+use crate::utils::hashing_handler::{PasswordHashError, hashing_handler, needs_rehash};
+use crate::utils::load_config::SecuritySection;
+use crate::utils::login_lockout_handler::{
+    LoginBlock, login_block, record_failed_login, record_failed_unknown_email_login,
+    unknown_email_login_block,
+};
+use crate::utils::session_handler::{create_session, is_known_device};
+use crate::utils::verification_handler::{dummy_verification_handler, verification_handler}; // your existing password verification function
+use chrono::{NaiveDateTime, Utc};
 use tower_cookies::Cookies;
 use tracing::error;
 
@@ -25,10 +36,19 @@ pub struct UserProfile {
     country: String,
     phone_number: String,
     is_logged_out: bool,
+    email_verified_at: Option<NaiveDateTime>,
+    phone_verified_at: Option<NaiveDateTime>,
+    /// Whether logging in takes a code from an authenticator app after the password.
+    mfa_enabled: bool,
+    /// Unused recovery codes, each standing in for the authenticator app once.
+    recovery_codes_remaining: i64,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+/// The columns of `users` a [`UserProfile`] is read from.
+pub(crate) const USER_PROFILE_COLUMNS: &str = "id, full_name, email, profile_image, password, is_active, is_admin, country, phone_number, is_logged_out, status, email_verified_at, phone_verified_at, totp_enabled_at IS NOT NULL AS mfa_enabled, (SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = users.id AND used_at IS NULL) AS recovery_codes_remaining, created_at, updated_at";
+
 #[derive(Debug, Serialize)]
 pub struct ResponseCore {
     user_profile: UserProfile,
@@ -49,18 +69,98 @@ pub struct LoginResponse {
     error: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct MfaChallenge {
+    /// Exchanged for auth tokens at `/login/mfa` together with a code.
+    mfa_token: String,
+    /// Expiration timestamp (seconds since epoch).
+    expires_at: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaRequiredResponse {
+    response_message: String,
+    response: Option<MfaChallenge>,
+    error: Option<String>,
+}
+
 // Reuse UserProfile and ResponseCore from register controller
 
+pub(crate) fn login_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(LoginResponse {
+            response_message: "Login failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+        .into_response()
+}
+
+/// Refuses the login of an account that failed to log in too often.
+pub(crate) fn login_blocked(block: LoginBlock) -> Response {
+    let (status, retry_after_in_seconds, error) = match block {
+        LoginBlock::Locked {
+            retry_after_in_seconds,
+        } => (
+            StatusCode::LOCKED,
+            retry_after_in_seconds,
+            format!(
+                "Account is locked after too many failed login attempts, try again in {} seconds",
+                retry_after_in_seconds
+            ),
+        ),
+        LoginBlock::BackOff {
+            retry_after_in_seconds,
+        } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            retry_after_in_seconds,
+            format!(
+                "Too many failed login attempts, try again in {} seconds",
+                retry_after_in_seconds
+            ),
+        ),
+    };
+
+    (
+        status,
+        [(RETRY_AFTER, retry_after_in_seconds.to_string())],
+        Json(LoginResponse {
+            response_message: "Login failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+        .into_response()
+}
+
+/// Refuses the login while every password hashing slot is taken.
+pub(crate) fn login_saturated(retry_after_in_seconds: u64) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, retry_after_in_seconds.to_string())],
+        Json(LoginResponse {
+            response_message: "Login failed".to_string(),
+            response: None,
+            error: Some("Too many login attempts, try again shortly".to_string()),
+        }),
+    )
+        .into_response()
+}
+
 pub async fn login_user(
     cookies: Cookies,
+    client: ClientMetadata,
     // Extension(db_pool): Extension<PgPool>,
     State(state): State<AppState>,
     Json(payload): Json<LoginRequest>,
-) -> impl IntoResponse {
+) -> Response {
     // Fetch user by email
-    let user_result = sqlx::query_as::<_, UserProfile>(
-        "SELECT id, full_name, email, profile_image, password, is_active, is_admin, country, phone_number, is_logged_out, status, created_at, updated_at FROM users WHERE email = $1",
-    )
+    let user_result = sqlx::query_as::<_, UserProfile>(&format!(
+        "SELECT {} FROM users WHERE email = $1",
+        USER_PROFILE_COLUMNS
+    ))
     .bind(&payload.email)
     .fetch_optional(&state.db)
     .await;
@@ -70,17 +170,36 @@ pub async fn login_user(
         Ok(None) => {
             error!("LOGIN FAILED: PROVIDE EMAIL AND PASSWORD!");
 
+            return unknown_email_login(&state, &payload).await;
+        }
+        Err(e) => {
+            error!("USER LOGIN FAILED!");
+
             return (
-                StatusCode::UNAUTHORIZED,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(LoginResponse {
                     response_message: "Login failed".to_string(),
                     response: None,
-                    error: Some("Invalid email or password".to_string()),
+                    error: Some(format!("Database error: {}", e)),
                 }),
-            );
+            )
+                .into_response();
+        }
+    };
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    // Checked before the password, so a locked account cannot be guessed at
+    match login_block(&state.db, user.id, security).await {
+        Ok(None) => {}
+        Ok(Some(block)) => {
+            error!("LOGIN FAILED: TOO MANY FAILED LOGIN ATTEMPTS!");
+
+            return login_blocked(block);
         }
         Err(e) => {
-            error!("USER LOGIN FAILED!");
+            error!("FAILED TO LOOK UP FAILED LOGIN ATTEMPTS: {}", e);
 
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -89,75 +208,104 @@ pub async fn login_user(
                     response: None,
                     error: Some(format!("Database error: {}", e)),
                 }),
-            );
+            )
+                .into_response();
         }
-    };
+    }
+
+    let require_verified_email = state
+        .config
+        .auth
+        .as_ref()
+        .is_some_and(|auth| auth.require_verified_email);
+
+    let verified =
+        verification_handler(&payload.password, &user.password, &state.password_hashing).await;
+
+    // Upgrade a hash made with outdated settings while the password is at hand
+    if let Ok(true) = verified
+        && needs_rehash(&user.password, &state.password_hashing)
+    {
+        match hashing_handler(&payload.password, &state.password_hashing).await {
+            Ok(rehashed_password) => {
+                let updated = sqlx::query(
+                    "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2 AND password = $3",
+                )
+                .bind(&rehashed_password)
+                .bind(user.id)
+                .bind(&user.password)
+                .execute(&state.db)
+                .await;
+
+                if let Err(e) = updated {
+                    error!("FAILED TO STORE REHASHED PASSWORD: {}", e);
+                }
+            }
+            Err(e) => {
+                error!("FAILED TO REHASH PASSWORD: {}", e);
+            }
+        }
+    }
 
-    match verification_handler(&payload.password, &user.password).await {
-        Ok(true) => {
-            let tokens = match generate_tokens(
-                "auth",
-                User {
+    match verified {
+        Ok(true) if require_verified_email && user.email_verified_at.is_none() => {
+            error!("LOGIN FAILED: EMAIL ADDRESS HAS NOT BEEN VERIFIED!");
+
+            (
+                StatusCode::FORBIDDEN,
+                Json(LoginResponse {
+                    response_message: "Login failed".to_string(),
+                    response: None,
+                    error: Some("Email address has not been verified".to_string()),
+                }),
+            )
+                .into_response()
+        }
+        Ok(true) if user.mfa_enabled => {
+            // The failed login count is only reset once the second factor checks out too
+            let challenge = generate_mfa_pending_token(
+                &User {
                     id: user.id,
-                    email: payload.email.clone(),
+                    email: user.email.clone(),
+                    session_id: None,
                 },
                 &state.config,
-            )
-            .await
-            {
-                Ok(tokens) => tokens,
+                &state.jwt_keys,
+            );
+
+            match challenge {
+                Ok(challenge) => (
+                    StatusCode::ACCEPTED,
+                    Json(MfaRequiredResponse {
+                        response_message: "Two-factor authentication required".to_string(),
+                        response: Some(MfaChallenge {
+                            mfa_token: challenge.token,
+                            expires_at: challenge.expires_at,
+                        }),
+                        error: None,
+                    }),
+                )
+                    .into_response(),
                 Err(e) => {
                     error!("TOKEN GENERATION ERROR!");
-                    return (
+
+                    login_failed(
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(LoginResponse {
-                            response_message: "Failed to generate tokens".to_string(),
-                            response: None,
-                            error: Some(format!("Token generation error: {}", e)),
-                        }),
-                    );
+                        format!("Token generation error: {}", e),
+                    )
                 }
-            };
-
-            deploy_auth_cookie(cookies, tokens.auth_cookie.unwrap(), &state.config).await;
-
-            let _ = sqlx::query_as::<_, UserProfile>(
-                r#"
-                    UPDATE users
-                    SET
-                        access_token = $1,
-                        refresh_token = $2,
-                        is_logged_out = $3,
-                        updated_at = NOW()
-                    WHERE email = $4
-                "#,
-            )
-            .bind(&tokens.access_token)
-            .bind(&tokens.refresh_token)
-            .bind(false)
-            .bind(&payload.email)
-            .fetch_one(&state.db)
-            .await;
-
-            (
-                StatusCode::OK,
-                Json(LoginResponse {
-                    response_message: "Login successful".to_string(),
-                    response: Some(ResponseCore {
-                        user_profile: UserProfile {
-                            is_logged_out: false,
-                            ..user
-                        },
-                        access_token: tokens.access_token,
-                        refresh_token: tokens.refresh_token,
-                    }),
-                    error: None,
-                }),
-            )
+            }
         }
+        Ok(true) => complete_login(&state, cookies, &client, user).await,
         Ok(false) => {
             error!("USER LOGIN FAILED!");
 
+            match record_failed_login(&state.db, user.id, security).await {
+                Ok(Some(lock)) => return login_blocked(lock),
+                Ok(None) => {}
+                Err(e) => error!("FAILED TO RECORD FAILED LOGIN: {}", e),
+            }
+
             (
                 StatusCode::UNAUTHORIZED,
                 Json(LoginResponse {
@@ -166,6 +314,14 @@ pub async fn login_user(
                     error: Some("Invalid email or password".to_string()),
                 }),
             )
+                .into_response()
+        }
+        Err(PasswordHashError::Saturated {
+            retry_after_in_seconds,
+        }) => {
+            error!("USER LOGIN FAILED: PASSWORD HASHING IS SATURATED!");
+
+            login_saturated(retry_after_in_seconds)
         }
         Err(e) => {
             error!("USER LOGIN FAILED!");
@@ -178,6 +334,167 @@ pub async fn login_user(
                     error: Some(format!("Password verification error: {}", e)),
                 }),
             )
+                .into_response()
+        }
+    }
+}
+
+/// Refuses the login of an email no account has, with the same answers, the same back-off
+/// and lockout, and about the same latency as a wrong password for a registered one.
+async fn unknown_email_login(state: &AppState, payload: &LoginRequest) -> Response {
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    match unknown_email_login_block(&state.db, &payload.email, security).await {
+        Ok(None) => {}
+        Ok(Some(block)) => {
+            error!("LOGIN FAILED: TOO MANY FAILED LOGIN ATTEMPTS!");
+
+            return login_blocked(block);
+        }
+        Err(e) => {
+            error!("FAILED TO LOOK UP FAILED LOGIN ATTEMPTS: {}", e);
+
+            return login_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    // Take as long as checking a real password, so timing does not reveal which emails are
+    // registered
+    if let Err(PasswordHashError::Saturated {
+        retry_after_in_seconds,
+    }) = dummy_verification_handler(&payload.password, &state.password_hashing).await
+    {
+        return login_saturated(retry_after_in_seconds);
+    }
+
+    match record_failed_unknown_email_login(&state.db, &payload.email, security).await {
+        Ok(Some(lock)) => return login_blocked(lock),
+        Ok(None) => {}
+        Err(e) => error!("FAILED TO RECORD FAILED LOGIN: {}", e),
+    }
+
+    login_failed(
+        StatusCode::UNAUTHORIZED,
+        "Invalid email or password".to_string(),
+    )
+}
+
+/// Logs `user` in once every factor has been checked: issues their tokens, opens a session,
+/// sets the refresh cookie and clears their failed login attempts.
+pub(crate) async fn complete_login(
+    state: &AppState,
+    cookies: Cookies,
+    client: &ClientMetadata,
+    user: UserProfile,
+) -> Response {
+    let tokens = match generate_auth_tokens(
+        &User {
+            id: user.id,
+            email: user.email.clone(),
+            session_id: None,
+        },
+        &state.config,
+        &state.jwt_keys,
+    ) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            error!("TOKEN GENERATION ERROR!");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(LoginResponse {
+                    response_message: "Failed to generate tokens".to_string(),
+                    response: None,
+                    error: Some(format!("Token generation error: {}", e)),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    // Checked before the new session exists, which would always match
+    let is_new_device = match is_known_device(&state.db, user.id, client).await {
+        Ok(known) => !known,
+        Err(e) => {
+            error!("FAILED TO LOOK UP KNOWN DEVICES: {}", e);
+            false
+        }
+    };
+
+    if let Err(e) = create_session(
+        &state.db,
+        user.id,
+        tokens.session_id,
+        &tokens.refresh_token,
+        client,
+        state
+            .config
+            .auth
+            .as_ref()
+            .map(|auth| auth.jwt_refresh_expiration_time_in_hours)
+            .unwrap_or_default(),
+    )
+    .await
+    {
+        error!("FAILED TO CREATE SESSION!");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(LoginResponse {
+                response_message: "Login failed".to_string(),
+                response: None,
+                error: Some(format!("Database error: {}", e)),
+            }),
+        )
+            .into_response();
+    }
+
+    deploy_auth_cookie(cookies, tokens.refresh_token.clone(), &state.config).await;
+
+    if is_new_device {
+        let alert = templates::new_device_alert(&user.email, client, Utc::now());
+        if let Err(e) = state.mailer.send(&alert).await {
+            error!("FAILED TO SEND NEW DEVICE ALERT: {}", e);
         }
     }
+
+    let update_result = sqlx::query(
+        r#"
+            UPDATE users
+            SET
+                is_logged_out = $1,
+                failed_login_attempts = 0,
+                last_failed_login_at = NULL,
+                locked_until = NULL,
+                updated_at = NOW()
+            WHERE id = $2
+        "#,
+    )
+    .bind(false)
+    .bind(user.id)
+    .execute(&state.db)
+    .await;
+
+    if let Err(e) = update_result {
+        error!("FAILED TO UPDATE LOGIN STATE: {}", e);
+    }
+
+    (
+        StatusCode::OK,
+        Json(LoginResponse {
+            response_message: "Login successful".to_string(),
+            response: Some(ResponseCore {
+                user_profile: UserProfile {
+                    is_logged_out: false,
+                    ..user
+                },
+                access_token: Some(tokens.access_token),
+                refresh_token: Some(tokens.refresh_token),
+            }),
+            error: None,
+        }),
+    )
+        .into_response()
 }
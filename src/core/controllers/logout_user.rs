@@ -1,94 +1,74 @@
 use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::cookie_deploy_handler::remove_auth_cookie;
+use crate::utils::session_handler::{
+    refresh_logged_out_flag, revoke_session, revoke_user_sessions,
+};
 use axum::extract::State;
 use axum::{Json, extract::Query, http::StatusCode, response::IntoResponse};
-use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use tower_cookies::{Cookie, Cookies};
+use tower_cookies::Cookies;
 use tracing::error;
 
 #[derive(Debug, Serialize)]
 pub struct LogoutResponse {
     response_message: String,
-    response: Option<UserProfile>,
+    response: Option<ResponseCore>,
     error: Option<String>,
 }
 
-#[derive(Deserialize)]
-pub struct SearchParams {
-    user_email: String,
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    all_sessions: bool,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
-pub struct UserProfile {
-    id: i64,
-    full_name: String,
-    email: String,
-    profile_image: Option<String>,
-    is_admin: bool,
-    is_active: bool,
-    status: String,
-    last_seen: Option<String>,
-    country: String,
-    phone_number: String,
-    is_logged_out: bool,
-    pub created_at: NaiveDateTime,
-    pub updated_at: NaiveDateTime,
+#[derive(Debug, Deserialize)]
+pub struct LogoutParams {
+    /// Log out of every session of the user instead of only the caller's session.
+    #[serde(default)]
+    all_sessions: bool,
 }
 
+/// Logs the authenticated caller out.
+///
+/// By default only the session the access token belongs to is revoked; with
+/// `?all_sessions=true` every session of the user is revoked.
 pub async fn logout_user(
+    user: AuthenticatedUser,
     State(state): State<AppState>,
-    Query(params): Query<SearchParams>,
+    Query(params): Query<LogoutParams>,
     cookies: Cookies,
 ) -> impl IntoResponse {
-    // info!("Logout request for user: {}", params.user_email);
-
     // Remove auth cookie
-    let mut cookie = Cookie::new("rusty_chat_auth_cookie", "");
-    cookie.set_path("/");
-    cookie.set_max_age(tower_cookies::cookie::time::Duration::ZERO);
-    cookies.remove(cookie);
+    remove_auth_cookie(&cookies);
 
-    // Clear tokens in database - IMPORTANT: Add RETURNING clause
-    let user = sqlx::query_as::<_, UserProfile>(
-        r#"
-                UPDATE users
-                SET
-                    access_token = $1,
-                    refresh_token = $2,
-                    is_logged_out = $3,
-                    updated_at = NOW()
-                WHERE email = $4
-                RETURNING
-                id,
-                full_name,
-                is_logged_out,
-                email,
-                profile_image,
-                status,
-                last_seen,
-                is_admin,
-                is_active,
-                is_logged_out,
-                country,
-                phone_number,
-                created_at,
-                updated_at
-            "#,
-    )
-    .bind("") // access_token
-    .bind("") // refresh_token
-    .bind(true)
-    .bind(&params.user_email)
-    .fetch_one(&state.db)
-    .await;
+    let result = async {
+        let mut tx = state.db.begin().await?;
+
+        match (params.all_sessions, user.session_id) {
+            (false, Some(session_id)) => {
+                revoke_session(&mut *tx, user.id, session_id).await?;
+            }
+            _ => {
+                revoke_user_sessions(&mut *tx, user.id).await?;
+            }
+        }
 
-    match user {
-        Ok(_user) => (
+        // The user only counts as logged out once no session is left
+        refresh_logged_out_flag(&mut *tx, user.id).await?;
+
+        tx.commit().await
+    };
+
+    match result.await {
+        Ok(()) => (
             StatusCode::OK,
             Json(LogoutResponse {
                 response_message: "Logout successful".to_string(),
                 error: None,
-                response: None,
+                response: Some(ResponseCore {
+                    all_sessions: params.all_sessions,
+                }),
             }),
         ),
         Err(e) => {
@@ -1,3 +1,21 @@
+pub mod change_password;
+pub mod confirm_totp;
+pub mod disable_totp;
+pub mod enroll_totp;
+pub mod get_jwks;
+pub mod list_user_sessions;
 pub mod login_user;
 pub mod logout_user;
+pub mod refresh_user_tokens;
+pub mod regenerate_recovery_codes;
 pub mod register_user;
+pub mod request_password_reset;
+pub mod resend_email_verification;
+pub mod reset_password;
+pub mod revoke_user_session;
+pub mod rotate_signing_key;
+pub mod send_phone_verification_code;
+pub mod unlock_user_account;
+pub mod verify_email;
+pub mod verify_mfa_login;
+pub mod verify_phone_number;
@@ -0,0 +1,278 @@
+use crate::AppState;
+use crate::utils::client_metadata::ClientMetadata;
+use crate::utils::cookie_deploy_handler::{auth_cookie_from_headers, deploy_auth_cookie};
+use crate::utils::generate_tokens::{User, generate_auth_tokens, verify_refresh_token};
+use crate::utils::session_handler::{hash_refresh_token, revoke_session, rotate_session_token};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tower_cookies::Cookies;
+use tracing::{error, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct StoredSession {
+    user_id: i64,
+    refresh_token_hash: String,
+    is_revoked: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct TokenOwner {
+    email: String,
+    is_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn refresh_failed(status: StatusCode, error: String) -> (StatusCode, Json<RefreshResponse>) {
+    (
+        status,
+        Json(RefreshResponse {
+            response_message: "Token refresh failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+}
+
+/// Trades a valid refresh token for a new access/refresh token pair.
+///
+/// The refresh token is read from the JSON body, or from the auth cookie when no body is
+/// sent, and the auth cookie is replaced with the new refresh token.
+///
+/// The presented refresh token is rotated out: it can never be used again. If an already
+/// rotated token is presented, it is treated as stolen and its session is revoked, forcing
+/// both the legitimate client and the attacker to log in again.
+pub async fn refresh_user_tokens(
+    cookies: Cookies,
+    client: ClientMetadata,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<RefreshRequest>>,
+) -> impl IntoResponse {
+    let refresh_token = match payload {
+        Some(Json(payload)) => payload.refresh_token,
+        None => match auth_cookie_from_headers(&headers) {
+            Some(refresh_token) => refresh_token,
+            None => {
+                error!("TOKEN REFRESH FAILED: NO REFRESH TOKEN PROVIDED!");
+
+                return refresh_failed(
+                    StatusCode::UNAUTHORIZED,
+                    "Missing refresh token or auth cookie".to_string(),
+                );
+            }
+        },
+    };
+
+    let claims = match verify_refresh_token(&refresh_token, &state.config, &state.jwt_keys) {
+        Ok(claims) => claims,
+        Err(e) => {
+            error!("TOKEN REFRESH FAILED: INVALID REFRESH TOKEN!");
+
+            return refresh_failed(
+                StatusCode::UNAUTHORIZED,
+                format!("Invalid or expired refresh token: {}", e),
+            );
+        }
+    };
+
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            error!("TOKEN REFRESH FAILED: COULD NOT START TRANSACTION!");
+
+            return refresh_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    };
+
+    let session_id = match claims.sid {
+        Some(session_id) => session_id,
+        None => {
+            error!("TOKEN REFRESH FAILED: TOKEN IS NOT BOUND TO A SESSION!");
+
+            return refresh_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired refresh token".to_string(),
+            );
+        }
+    };
+
+    // Lock the session so two concurrent refreshes cannot both rotate it
+    let session = sqlx::query_as::<_, StoredSession>(
+        r#"
+        SELECT
+            user_id,
+            refresh_token_hash,
+            revoked_at IS NOT NULL OR expires_at <= NOW() AS is_revoked
+        FROM sessions
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(session_id)
+    .fetch_optional(&mut *tx)
+    .await;
+
+    let session = match session {
+        Ok(Some(session)) if session.user_id == claims.id => session,
+        Ok(_) => {
+            error!("TOKEN REFRESH FAILED: UNKNOWN SESSION!");
+
+            return refresh_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired refresh token".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("TOKEN REFRESH FAILED: {}", e);
+
+            return refresh_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    };
+
+    if session.is_revoked {
+        error!("TOKEN REFRESH FAILED: SESSION HAS BEEN REVOKED!");
+
+        return refresh_failed(
+            StatusCode::UNAUTHORIZED,
+            "Refresh token has been revoked".to_string(),
+        );
+    }
+
+    // A validly signed token of this session that is not its latest one was already rotated
+    if session.refresh_token_hash != hash_refresh_token(&refresh_token) {
+        warn!(
+            "REFRESH TOKEN REUSE DETECTED FOR USER {}: REVOKING SESSION {}",
+            session.user_id, session_id
+        );
+
+        let revoked = match revoke_session(&mut *tx, session.user_id, session_id).await {
+            Ok(_) => tx.commit().await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = revoked {
+            error!("FAILED TO REVOKE COMPROMISED SESSION: {}", e);
+        }
+
+        return refresh_failed(
+            StatusCode::UNAUTHORIZED,
+            "Refresh token reuse detected; session has been revoked".to_string(),
+        );
+    }
+
+    let owner = sqlx::query_as::<_, TokenOwner>("SELECT email, is_active FROM users WHERE id = $1")
+        .bind(session.user_id)
+        .fetch_optional(&mut *tx)
+        .await;
+
+    let owner = match owner {
+        Ok(Some(owner)) if owner.is_active => owner,
+        Ok(_) => {
+            error!("TOKEN REFRESH FAILED: USER IS MISSING OR INACTIVE!");
+
+            return refresh_failed(
+                StatusCode::UNAUTHORIZED,
+                "User account is not active".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("TOKEN REFRESH FAILED: {}", e);
+
+            return refresh_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    };
+
+    let tokens = match generate_auth_tokens(
+        &User {
+            id: session.user_id,
+            email: owner.email,
+            session_id: Some(session_id),
+        },
+        &state.config,
+        &state.jwt_keys,
+    ) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            error!("TOKEN GENERATION ERROR!");
+
+            return refresh_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Token generation error: {}", e),
+            );
+        }
+    };
+
+    let refresh_lifetime = state
+        .config
+        .auth
+        .as_ref()
+        .map(|auth| auth.jwt_refresh_expiration_time_in_hours)
+        .unwrap_or_default();
+
+    let rotation = async {
+        rotate_session_token(
+            &mut *tx,
+            session_id,
+            &tokens.refresh_token,
+            &client,
+            refresh_lifetime,
+        )
+        .await?;
+
+        tx.commit().await
+    };
+
+    if let Err(e) = rotation.await {
+        error!(
+            "TOKEN REFRESH FAILED: COULD NOT ROTATE REFRESH TOKEN: {}",
+            e
+        );
+
+        return refresh_failed(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Database error: {}", e),
+        );
+    }
+
+    deploy_auth_cookie(cookies, tokens.refresh_token.clone(), &state.config).await;
+
+    (
+        StatusCode::OK,
+        Json(RefreshResponse {
+            response_message: "Tokens refreshed successfully".to_string(),
+            response: Some(ResponseCore {
+                access_token: Some(tokens.access_token),
+                refresh_token: Some(tokens.refresh_token),
+            }),
+            error: None,
+        }),
+    )
+}
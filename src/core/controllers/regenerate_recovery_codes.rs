@@ -0,0 +1,113 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::load_config::SecuritySection;
+use crate::utils::recovery_codes_handler::{RecoveryCodeError, replace_recovery_codes};
+use crate::utils::step_up_handler::{StepUpError, verify_step_up};
+use crate::utils::totp_handler::is_totp_enabled;
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    password: String,
+    /// A code from the authenticator app, or one of the recovery codes being replaced.
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    /// Shown once; only their hashes are kept.
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegenerateRecoveryCodesResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn regeneration_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(RegenerateRecoveryCodesResponse {
+            response_message: "Recovery code regeneration failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+        .into_response()
+}
+
+/// Replaces the authenticated user's recovery codes with a new set, given the current
+/// password and a second factor. Every previous code stops working.
+pub async fn regenerate_recovery_codes(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Json(payload): Json<RegenerateRecoveryCodesRequest>,
+) -> Response {
+    match is_totp_enabled(&state.db, user.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return regeneration_failed(
+                StatusCode::CONFLICT,
+                "Two-factor authentication is not enabled".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("RECOVERY CODE REGENERATION FAILED: {}", e);
+
+            return regeneration_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    if let Err(e) = verify_step_up(
+        &state.db,
+        &state.password_hashing,
+        security,
+        user.id,
+        &payload.password,
+        Some(&payload.code),
+    )
+    .await
+    {
+        error!("RECOVERY CODE REGENERATION FAILED: {}", e);
+
+        return e.into_response_with(regeneration_failed);
+    }
+
+    match replace_recovery_codes(&state.db, &state.password_hashing, user.id).await {
+        Ok(recovery_codes) => {
+            info!("RECOVERY CODES REGENERATED FOR USER {}", user.id);
+
+            (
+                StatusCode::OK,
+                Json(RegenerateRecoveryCodesResponse {
+                    response_message: "Recovery codes regenerated".to_string(),
+                    response: Some(ResponseCore { recovery_codes }),
+                    error: None,
+                }),
+            )
+                .into_response()
+        }
+        Err(RecoveryCodeError::PasswordHash(e)) => {
+            error!("RECOVERY CODE REGENERATION FAILED: {}", e);
+
+            StepUpError::PasswordHash(e).into_response_with(regeneration_failed)
+        }
+        Err(e) => {
+            error!("RECOVERY CODE REGENERATION FAILED: {}", e);
+
+            regeneration_failed(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        }
+    }
+}
@@ -1,9 +1,20 @@
 use crate::AppState;
+use crate::mailer::templates;
+use crate::sms::SmsMessage;
+use crate::utils::client_metadata::ClientMetadata;
 use crate::utils::cookie_deploy_handler::deploy_auth_cookie;
+use crate::utils::email_verification_handler::send_email_verification;
 use crate::utils::generate_tokens::User;
-use crate::utils::generate_tokens::generate_tokens;
-use crate::utils::hashing_handler::hashing_handler;
+use crate::utils::generate_tokens::generate_auth_tokens;
+use crate::utils::hashing_handler::{PasswordHashError, hashing_handler};
+use crate::utils::load_config::SecuritySection;
+use crate::utils::password_policy::{
+    PasswordPolicyViolation, check_password_policy, describe_violations,
+};
+use crate::utils::session_handler::create_session;
 use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::response::Response;
 use axum::{Json, http::StatusCode, response::IntoResponse};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
@@ -28,6 +39,8 @@ pub struct UserProfile {
     profile_image: String,
     country: String,
     phone_number: String,
+    email_verified_at: Option<NaiveDateTime>,
+    phone_verified_at: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -53,29 +66,146 @@ pub struct RegisterResponse {
     response_message: String,
     response: Option<ResponseCore>,
     error: Option<String>,
+    /// Every password policy rule the chosen password breaks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_violations: Option<Vec<PasswordPolicyViolation>>,
 }
 
+/// The one answer to every well-formed registration when
+/// `auth.enumeration_resistant_registration` is set, whether or not an account was created.
+fn registration_accepted() -> Response {
+    (
+        StatusCode::ACCEPTED,
+        Json(RegisterResponse {
+            response_message:
+                "Registration received. Check your email to verify your address and finish signing up."
+                    .to_string(),
+            response: None,
+            error: None,
+            password_violations: None,
+        }),
+    )
+        .into_response()
+}
+
+/// What the owner of the details of an enumeration-resistant registration is sent.
+enum RegistrationNotice {
+    /// The email verification of a new account.
+    Verification(User),
+    /// A notice to the owner of a taken email, by mail.
+    EmailTaken(String),
+    /// A notice to the owner of a taken phone number, by text message.
+    PhoneNumberTaken(String),
+}
+
+/// Answers with [`registration_accepted`] and sends `notice` after answering, so a new
+/// account, a taken email and a taken phone number all take about as long.
+fn registration_accepted_with(state: &AppState, notice: RegistrationNotice) -> Response {
+    let state = state.clone();
+    tokio::spawn(async move {
+        match notice {
+            RegistrationNotice::Verification(user) => {
+                if let Err(e) =
+                    send_email_verification(&user, &state.config, &state.jwt_keys, &*state.mailer)
+                        .await
+                {
+                    error!("FAILED TO SEND EMAIL VERIFICATION: {}", e);
+                }
+            }
+            RegistrationNotice::EmailTaken(email) => {
+                let notice = templates::account_already_exists(&email);
+                if let Err(e) = state.mailer.send(&notice).await {
+                    error!("FAILED TO SEND ACCOUNT ALREADY EXISTS NOTICE: {}", e);
+                }
+            }
+            RegistrationNotice::PhoneNumberTaken(phone_number) => {
+                let notice = SmsMessage::account_already_exists(&phone_number);
+                if let Err(e) = state.sms_sender.send(&notice).await {
+                    error!("FAILED TO SEND ACCOUNT ALREADY EXISTS NOTICE: {}", e);
+                }
+            }
+        }
+    });
+
+    registration_accepted()
+}
+
+/// Creates a user account.
+///
+/// With `auth.enumeration_resistant_registration`, taken emails and phone numbers are not
+/// reported: every registration gets [`registration_accepted`], and the owner of a taken
+/// email or phone number is told by mail or text message instead.
 pub async fn register_user(
     cookies: Cookies,
+    client: ClientMetadata,
     State(state): State<AppState>,
     Json(payload): Json<InSpecs>,
-) -> impl IntoResponse {
+) -> Response {
+    let enumeration_resistant = state
+        .config
+        .auth
+        .as_ref()
+        .is_some_and(|auth| auth.enumeration_resistant_registration);
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    if let Err(violations) = check_password_policy(
+        &payload.password,
+        security,
+        &[&payload.first_name, &payload.last_name, &payload.email],
+        &state.password_blocklist,
+    ) {
+        error!("REGISTRATION FAILED: PASSWORD DOES NOT MEET THE POLICY!");
+
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(RegisterResponse {
+                response_message: "Registration failed".to_string(),
+                response: None,
+                error: Some(describe_violations(&violations)),
+                password_violations: Some(violations),
+            }),
+        )
+            .into_response();
+    }
+
     // Hash the password
-    let hashed_password = match hashing_handler(payload.password.as_str()).await {
-        Ok(hash) => hash,
-        Err(e) => {
-            error!("PASSWORD HASHING ERROR!");
+    let hashed_password =
+        match hashing_handler(payload.password.as_str(), &state.password_hashing).await {
+            Ok(hash) => hash,
+            Err(PasswordHashError::Saturated {
+                retry_after_in_seconds,
+            }) => {
+                error!("REGISTRATION FAILED: PASSWORD HASHING IS SATURATED!");
 
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(RegisterResponse {
-                    response_message: "Failed to hash password".to_string(),
-                    response: None,
-                    error: Some(format!("Password hashing error: {}", e)),
-                }),
-            );
-        }
-    };
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(RETRY_AFTER, retry_after_in_seconds.to_string())],
+                    Json(RegisterResponse {
+                        response_message: "Registration failed".to_string(),
+                        response: None,
+                        error: Some("Too many password requests, try again shortly".to_string()),
+                        password_violations: None,
+                    }),
+                )
+                    .into_response();
+            }
+            Err(e) => {
+                error!("PASSWORD HASHING ERROR!");
+
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(RegisterResponse {
+                        response_message: "Failed to hash password".to_string(),
+                        response: None,
+                        error: Some(format!("Password hashing error: {}", e)),
+                        password_violations: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
 
     // ===== Check for existing user by email =====
     let email_query = sqlx::query_as::<_, UserLookUp>(
@@ -99,14 +229,23 @@ pub async fn register_user(
             // Email already exists (query condition)
             error!("REGISTRATION FAILED: EMAIL ALREADY EXISTS");
 
+            if enumeration_resistant {
+                return registration_accepted_with(
+                    &state,
+                    RegistrationNotice::EmailTaken(payload.email),
+                );
+            }
+
             return (
                 StatusCode::FORBIDDEN,
                 Json(RegisterResponse {
                     response_message: "Registration failed".to_string(),
                     response: None,
                     error: Some("Email already exists".to_string()),
+                    password_violations: None,
                 }),
-            );
+            )
+                .into_response();
         }
 
         Ok(None) => {
@@ -122,8 +261,10 @@ pub async fn register_user(
                     response_message: "Registration failed".to_string(),
                     response: None,
                     error: Some(format!("Database error: {}", e)),
+                    password_violations: None,
                 }),
-            );
+            )
+                .into_response();
         }
     }
 
@@ -148,14 +289,23 @@ pub async fn register_user(
             // Email already exists (query condition)
             error!("REGISTRATION FAILED: PHONE NUMBER ALREADY EXISTS");
 
+            if enumeration_resistant {
+                return registration_accepted_with(
+                    &state,
+                    RegistrationNotice::PhoneNumberTaken(payload.phone_number),
+                );
+            }
+
             return (
                 StatusCode::FORBIDDEN,
                 Json(RegisterResponse {
                     response_message: "Registration failed".to_string(),
                     response: None,
                     error: Some("Phone number already exists".to_string()),
+                    password_violations: None,
                 }),
-            );
+            )
+                .into_response();
         }
 
         Ok(None) => {
@@ -171,8 +321,10 @@ pub async fn register_user(
                     response_message: "Registration failed".to_string(),
                     response: None,
                     error: Some(format!("Server error: {}", e)),
+                    password_violations: None,
                 }),
-            );
+            )
+                .into_response();
         }
     }
 
@@ -197,6 +349,8 @@ pub async fn register_user(
             profile_image,
             country,
             phone_number,
+            email_verified_at,
+            phone_verified_at,
             created_at,
             updated_at
         "#,
@@ -212,16 +366,51 @@ pub async fn register_user(
 
     match result {
         Ok(new_user) => {
-            let tokens = match generate_tokens(
-                "auth",
-                User {
-                    id: new_user.id,
-                    email: payload.email.clone(),
-                },
-                &state.config,
-            )
-            .await
+            let user = User {
+                id: new_user.id,
+                email: payload.email.clone(),
+                session_id: None,
+            };
+
+            // Tokens would tell a new account apart from a taken email
+            if enumeration_resistant {
+                return registration_accepted_with(&state, RegistrationNotice::Verification(user));
+            }
+
+            if let Err(e) =
+                send_email_verification(&user, &state.config, &state.jwt_keys, &*state.mailer).await
             {
+                error!("FAILED TO SEND EMAIL VERIFICATION: {}", e);
+            }
+
+            let require_verified_email = state
+                .config
+                .auth
+                .as_ref()
+                .is_some_and(|auth| auth.require_verified_email);
+
+            // Unverified users only get tokens once they have verified their email
+            if require_verified_email {
+                return (
+                    StatusCode::CREATED,
+                    Json(RegisterResponse {
+                        response_message: format!(
+                            "User with email '{}' registered successfully! Verify your email address to log in.",
+                            &payload.email
+                        ),
+                        response: Some(ResponseCore {
+                            user_profile: new_user,
+                            access_token: None,
+                            refresh_token: None,
+                        }),
+                        error: None,
+                        password_violations: None,
+                    }),
+                )
+                    .into_response();
+            }
+
+            let tokens = match generate_auth_tokens(&user, &state.config, &state.jwt_keys) {
                 Ok(tokens) => tokens,
                 Err(e) => {
                     error!("TOKEN GENERATION ERROR!");
@@ -232,33 +421,33 @@ pub async fn register_user(
                             response_message: "Failed to generate tokens".to_string(),
                             response: None,
                             error: Some(format!("Token generation error: {}", e)),
+                            password_violations: None,
                         }),
-                    );
+                    )
+                        .into_response();
                 }
             };
 
-            // Update tokens for the created user
-            let update_result = sqlx::query(
-                r#"
-                UPDATE users
-                SET
-                    access_token = $1,
-                    refresh_token = $2,
-                    updated_at = NOW()
-                WHERE id = $3
-                "#,
+            // Open the first session of the created user
+            if let Err(e) = create_session(
+                &state.db,
+                new_user.id,
+                tokens.session_id,
+                &tokens.refresh_token,
+                &client,
+                state
+                    .config
+                    .auth
+                    .as_ref()
+                    .map(|auth| auth.jwt_refresh_expiration_time_in_hours)
+                    .unwrap_or_default(),
             )
-            .bind(&tokens.access_token)
-            .bind(&tokens.refresh_token)
-            .bind(new_user.id)
-            .execute(&state.db)
-            .await;
-
-            if let Err(e) = update_result {
-                error!("FAILED TO UPDATE TOKENS: {}", e);
+            .await
+            {
+                error!("FAILED TO CREATE SESSION: {}", e);
             }
 
-            deploy_auth_cookie(cookies, tokens.auth_cookie.unwrap(), &state.config).await;
+            deploy_auth_cookie(cookies, tokens.refresh_token.clone(), &state.config).await;
 
             (
                 StatusCode::CREATED,
@@ -269,17 +458,25 @@ pub async fn register_user(
                     ),
                     response: Some(ResponseCore {
                         user_profile: new_user,
-                        access_token: tokens.access_token,
-                        refresh_token: tokens.refresh_token,
+                        access_token: Some(tokens.access_token),
+                        refresh_token: Some(tokens.refresh_token),
                     }),
                     error: None,
+                    password_violations: None,
                 }),
             )
+                .into_response()
         }
         Err(e) => {
             let error_msg =
                 if e.to_string().contains("unique") || e.to_string().contains("duplicate") {
                     error!("REGISTRATION FAILED: USER WITH EMAIL ALREADY EXIST!");
+
+                    // Lost a race with another registration of the same details
+                    if enumeration_resistant {
+                        return registration_accepted();
+                    }
+
                     "Email already exists".to_string()
                 } else {
                     error!("REGISTRATION FAILED: AN ERROR OCCURRED WHILE REGISTERING NEW USER!");
@@ -292,8 +489,10 @@ pub async fn register_user(
                     response_message: "Failed to register user".to_string(),
                     response: None,
                     error: Some(error_msg),
+                    password_violations: None,
                 }),
             )
+                .into_response()
         }
     }
 }
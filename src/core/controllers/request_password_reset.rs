@@ -0,0 +1,122 @@
+use crate::AppState;
+use crate::mailer::templates;
+use crate::utils::generate_tokens::{User, generate_one_time_password_token};
+use crate::utils::one_time_password_handler::store_one_time_password;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    email: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ResetCandidate {
+    id: i64,
+    email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordResetRequestResponse {
+    response_message: String,
+    response: Option<()>,
+    error: Option<String>,
+}
+
+/// Issues a one-time password allowing the owner of `email` to set a new password.
+///
+/// Responds identically and about as fast whether or not an active account exists for the
+/// email, so the endpoint cannot be used to find out which addresses are registered: the code
+/// is issued and mailed after answering. Requesting a new code invalidates the previous one.
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(payload): Json<PasswordResetRequest>,
+) -> impl IntoResponse {
+    let accepted = (
+        StatusCode::OK,
+        Json(PasswordResetRequestResponse {
+            response_message:
+                "If an account exists for this email, a password reset code has been sent"
+                    .to_string(),
+            response: None,
+            error: None,
+        }),
+    );
+
+    let candidate = sqlx::query_as::<_, ResetCandidate>(
+        "SELECT id, email FROM users WHERE email = $1 AND is_active = TRUE",
+    )
+    .bind(&payload.email)
+    .fetch_optional(&state.db)
+    .await;
+
+    let candidate = match candidate {
+        Ok(Some(candidate)) => candidate,
+        Ok(None) => return accepted,
+        Err(e) => {
+            error!("PASSWORD RESET REQUEST FAILED: {}", e);
+
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(PasswordResetRequestResponse {
+                    response_message: "Password reset request failed".to_string(),
+                    response: None,
+                    error: Some(format!("Database error: {}", e)),
+                }),
+            );
+        }
+    };
+
+    // Done after answering, so a registered email takes as long as an unknown one
+    tokio::spawn(send_password_reset_code(
+        state,
+        User {
+            id: candidate.id,
+            email: candidate.email,
+            session_id: None,
+        },
+    ));
+
+    accepted
+}
+
+/// Issues, stores and mails a password reset code to `user`. Failures are only logged, as
+/// the caller has already been answered.
+async fn send_password_reset_code(state: AppState, user: User) {
+    let one_time_password =
+        match generate_one_time_password_token(&user, &state.config, &state.jwt_keys) {
+            Ok(one_time_password) => one_time_password,
+            Err(e) => {
+                error!("TOKEN GENERATION ERROR: {}", e);
+
+                return;
+            }
+        };
+
+    let lifetime_in_minutes = state
+        .config
+        .auth
+        .as_ref()
+        .map(|auth| auth.jwt_one_time_password_lifetime_in_minutes)
+        .unwrap_or_default();
+
+    if let Err(e) = store_one_time_password(
+        &state.db,
+        user.id,
+        &one_time_password.token,
+        lifetime_in_minutes,
+    )
+    .await
+    {
+        error!("FAILED TO STORE PASSWORD RESET CODE: {}", e);
+
+        return;
+    }
+
+    let message = templates::password_reset(&state.config, &user.email, &one_time_password.token);
+    if let Err(e) = state.mailer.send(&message).await {
+        error!("FAILED TO SEND PASSWORD RESET CODE: {}", e);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::AppState;
+use crate::utils::email_verification_handler::send_email_verification;
+use crate::utils::generate_tokens::User;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    email: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct UnverifiedUser {
+    id: i64,
+    email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResendVerificationResponse {
+    response_message: String,
+    response: Option<()>,
+    error: Option<String>,
+}
+
+/// Sends a new email-verification token to an unverified address.
+///
+/// Responds identically whether or not an unverified account exists for the email, so the
+/// endpoint cannot be used to find out which addresses are registered or verified.
+pub async fn resend_email_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationRequest>,
+) -> impl IntoResponse {
+    let unverified = sqlx::query_as::<_, UnverifiedUser>(
+        r#"
+        SELECT id, email
+        FROM users
+        WHERE email = $1 AND is_active = TRUE AND email_verified_at IS NULL
+        "#,
+    )
+    .bind(&payload.email)
+    .fetch_optional(&state.db)
+    .await;
+
+    match unverified {
+        Ok(Some(user)) => {
+            let sent = send_email_verification(
+                &User {
+                    id: user.id,
+                    email: user.email,
+                    session_id: None,
+                },
+                &state.config,
+                &state.jwt_keys,
+                &*state.mailer,
+            )
+            .await;
+
+            // A delivery failure is not reported to the caller, who must not learn the account exists
+            if let Err(e) = sent {
+                error!("FAILED TO RESEND EMAIL VERIFICATION: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("FAILED TO RESEND EMAIL VERIFICATION: {}", e);
+
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ResendVerificationResponse {
+                    response_message: "Failed to resend verification".to_string(),
+                    response: None,
+                    error: Some(format!("Database error: {}", e)),
+                }),
+            );
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(ResendVerificationResponse {
+            response_message:
+                "If an unverified account exists for this email, a verification link has been sent"
+                    .to_string(),
+            response: None,
+            error: None,
+        }),
+    )
+}
@@ -0,0 +1,219 @@
+use crate::AppState;
+use crate::utils::generate_tokens::verify_one_time_password_token;
+use crate::utils::hashing_handler::{PasswordHashError, hashing_handler};
+use crate::utils::load_config::SecuritySection;
+use crate::utils::login_lockout_handler::clear_failed_logins;
+use crate::utils::one_time_password_handler::consume_one_time_password;
+use crate::utils::password_policy::{
+    PasswordPolicyViolation, check_password_policy, describe_violations,
+};
+use crate::utils::session_handler::{refresh_logged_out_flag, revoke_user_sessions};
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::response::Response;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    one_time_password: String,
+    new_password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    revoked_sessions: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResetPasswordResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+    /// Every password policy rule the chosen password breaks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_violations: Option<Vec<PasswordPolicyViolation>>,
+}
+
+fn reset_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(ResetPasswordResponse {
+            response_message: "Password reset failed".to_string(),
+            response: None,
+            error: Some(error),
+            password_violations: None,
+        }),
+    )
+        .into_response()
+}
+
+fn password_rejected(violations: Vec<PasswordPolicyViolation>) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ResetPasswordResponse {
+            response_message: "Password reset failed".to_string(),
+            response: None,
+            error: Some(describe_violations(&violations)),
+            password_violations: Some(violations),
+        }),
+    )
+        .into_response()
+}
+
+/// Refuses the request while every password hashing slot is taken.
+fn hashing_saturated(retry_after_in_seconds: u64) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, retry_after_in_seconds.to_string())],
+        Json(ResetPasswordResponse {
+            response_message: "Password reset failed".to_string(),
+            response: None,
+            error: Some("Too many password requests, try again shortly".to_string()),
+            password_violations: None,
+        }),
+    )
+        .into_response()
+}
+
+/// Sets a new password using a one-time password from [`request_password_reset`].
+///
+/// The one-time password is used up even if the caller never sees the response, and every
+/// session of the user is revoked, so whoever knew the old password is logged out everywhere.
+///
+/// [`request_password_reset`]: super::request_password_reset::request_password_reset
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Response {
+    let claims = match verify_one_time_password_token(
+        &payload.one_time_password,
+        &state.config,
+        &state.jwt_keys,
+    ) {
+        Ok(claims) => claims,
+        Err(e) => {
+            error!("PASSWORD RESET FAILED: INVALID ONE-TIME PASSWORD: {}", e);
+
+            return reset_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired password reset code".to_string(),
+            );
+        }
+    };
+
+    let owner =
+        sqlx::query_as::<_, (String, String)>("SELECT email, full_name FROM users WHERE id = $1")
+            .bind(claims.id)
+            .fetch_optional(&state.db)
+            .await;
+
+    let (email, full_name) = match owner {
+        Ok(Some(owner)) => owner,
+        Ok(None) => {
+            error!("PASSWORD RESET FAILED: USER NO LONGER EXISTS!");
+
+            return reset_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired password reset code".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("PASSWORD RESET FAILED: {}", e);
+
+            return reset_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    };
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+    let mut personal_info: Vec<&str> = full_name.split_whitespace().collect();
+    personal_info.push(&email);
+
+    if let Err(violations) = check_password_policy(
+        &payload.new_password,
+        security,
+        &personal_info,
+        &state.password_blocklist,
+    ) {
+        error!("PASSWORD RESET FAILED: PASSWORD DOES NOT MEET THE POLICY!");
+
+        return password_rejected(violations);
+    }
+
+    let hashed_password =
+        match hashing_handler(&payload.new_password, &state.password_hashing).await {
+            Ok(hash) => hash,
+            Err(PasswordHashError::Saturated {
+                retry_after_in_seconds,
+            }) => {
+                error!("PASSWORD RESET FAILED: PASSWORD HASHING IS SATURATED!");
+
+                return hashing_saturated(retry_after_in_seconds);
+            }
+            Err(e) => {
+                error!("PASSWORD HASHING ERROR!");
+
+                return reset_failed(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Password hashing error: {}", e),
+                );
+            }
+        };
+
+    let result = async {
+        let mut tx = state.db.begin().await?;
+
+        if !consume_one_time_password(&mut *tx, claims.id, &payload.one_time_password).await? {
+            return Ok(None);
+        }
+
+        sqlx::query("UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2")
+            .bind(&hashed_password)
+            .bind(claims.id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Whoever set the new password may log in with it right away
+        clear_failed_logins(&mut *tx, claims.id).await?;
+
+        let revoked_sessions = revoke_user_sessions(&mut *tx, claims.id).await?;
+        refresh_logged_out_flag(&mut *tx, claims.id).await?;
+        tx.commit().await?;
+
+        Ok::<Option<u64>, sqlx::Error>(Some(revoked_sessions))
+    };
+
+    match result.await {
+        Ok(Some(revoked_sessions)) => (
+            StatusCode::OK,
+            Json(ResetPasswordResponse {
+                response_message: "Password reset successfully".to_string(),
+                response: Some(ResponseCore { revoked_sessions }),
+                error: None,
+                password_violations: None,
+            }),
+        )
+            .into_response(),
+        Ok(None) => {
+            error!("PASSWORD RESET FAILED: ONE-TIME PASSWORD IS USED UP OR SUPERSEDED!");
+
+            reset_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired password reset code".to_string(),
+            )
+        }
+        Err(e) => {
+            error!("PASSWORD RESET FAILED: {}", e);
+
+            reset_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::session_handler::{refresh_logged_out_flag, revoke_session};
+use axum::extract::{Path, State};
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    session_id: Uuid,
+}
+
+/// Remotely ends one of the authenticated user's sessions, e.g. a lost device.
+///
+/// The session's refresh token stops working immediately, and so do its access tokens.
+pub async fn revoke_user_session(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Path(session_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let result = async {
+        let mut tx = state.db.begin().await?;
+        let revoked = revoke_session(&mut *tx, user.id, session_id).await?;
+        refresh_logged_out_flag(&mut *tx, user.id).await?;
+        tx.commit().await?;
+
+        Ok::<bool, sqlx::Error>(revoked)
+    };
+
+    match result.await {
+        Ok(true) => (
+            StatusCode::OK,
+            Json(RevokeSessionResponse {
+                response_message: "Session revoked successfully".to_string(),
+                response: Some(ResponseCore { session_id }),
+                error: None,
+            }),
+        ),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(RevokeSessionResponse {
+                response_message: "Failed to revoke session".to_string(),
+                response: None,
+                error: Some("Session not found".to_string()),
+            }),
+        ),
+        Err(e) => {
+            error!("FAILED TO REVOKE SESSION: {}", e);
+
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RevokeSessionResponse {
+                    response_message: "Failed to revoke session".to_string(),
+                    response: None,
+                    error: Some(format!("Database error: {}", e)),
+                }),
+            )
+        }
+    }
+}
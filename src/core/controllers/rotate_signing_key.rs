@@ -0,0 +1,132 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::jwt_keys::SigningKey;
+use crate::utils::load_config::load_config;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use chrono::Duration;
+use serde::Serialize;
+use tracing::{error, info};
+
+#[derive(Debug, Serialize)]
+pub struct RotateSigningKeyResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    /// Key id new tokens are signed with.
+    kid: String,
+    /// Key ids still accepted for verification.
+    retired_kids: Vec<String>,
+    grace_period_in_hours: u64,
+}
+
+fn rotation_failed(
+    status: StatusCode,
+    error: String,
+) -> (StatusCode, Json<RotateSigningKeyResponse>) {
+    (
+        status,
+        Json(RotateSigningKeyResponse {
+            response_message: "Signing key rotation failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+}
+
+/// Swaps the JWT signing key for the one currently configured, without a restart.
+///
+/// The operator first points `auth` at the new key (key paths, `jwt_key_id` or
+/// `jwt_secret`), then calls this endpoint as an admin. The configuration is reloaded,
+/// the new key starts signing immediately, and the previous key keeps verifying the
+/// tokens it signed for `auth.jwt_retired_key_grace_period_in_hours`.
+pub async fn rotate_signing_key(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !user.is_admin {
+        return rotation_failed(StatusCode::FORBIDDEN, "Admin access required".to_string());
+    }
+
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!(
+                "SIGNING KEY ROTATION FAILED: COULD NOT RELOAD CONFIGURATION: {}",
+                e
+            );
+            return rotation_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to reload configuration: {}", e),
+            );
+        }
+    };
+
+    if let Err(e) = config.validate() {
+        error!("SIGNING KEY ROTATION FAILED: INVALID CONFIGURATION: {}", e);
+        return rotation_failed(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Invalid configuration: {}", e),
+        );
+    }
+
+    // validate() guarantees the auth section is present
+    let Some(auth) = config.auth.as_ref() else {
+        return rotation_failed(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "auth section is missing".to_string(),
+        );
+    };
+
+    let next = match SigningKey::from_config(auth) {
+        Ok(key) => key,
+        Err(e) => {
+            error!(
+                "SIGNING KEY ROTATION FAILED: COULD NOT LOAD THE NEW KEY: {}",
+                e
+            );
+            return rotation_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load the new signing key: {}", e),
+            );
+        }
+    };
+
+    let grace_period_in_hours = auth.retired_key_grace_period_in_hours();
+    let grace_period = i64::try_from(grace_period_in_hours)
+        .ok()
+        .and_then(Duration::try_hours)
+        .unwrap_or(Duration::MAX);
+
+    let previous_kid = state.jwt_keys.kid();
+
+    if !state.jwt_keys.rotate(next, grace_period) {
+        return rotation_failed(
+            StatusCode::CONFLICT,
+            "The configured signing key is already in use".to_string(),
+        );
+    }
+
+    let kid = state.jwt_keys.kid();
+    info!(
+        "JWT SIGNING KEY ROTATED FROM {} TO {} BY USER {}",
+        previous_kid, kid, user.id
+    );
+
+    (
+        StatusCode::OK,
+        Json(RotateSigningKeyResponse {
+            response_message: "Signing key rotated successfully".to_string(),
+            response: Some(ResponseCore {
+                kid,
+                retired_kids: state.jwt_keys.retired_kids(),
+                grace_period_in_hours,
+            }),
+            error: None,
+        }),
+    )
+}
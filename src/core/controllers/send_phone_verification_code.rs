@@ -0,0 +1,170 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::sms::{SmsError, SmsMessage};
+use crate::utils::load_config::SmsSection;
+use crate::utils::phone_verification_handler::{
+    generate_phone_verification_code, phone_verification_cooldown_remaining,
+    store_phone_verification_code,
+};
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::response::Response;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use tracing::error;
+
+#[derive(Debug, sqlx::FromRow)]
+struct PhoneRecord {
+    phone_number: Option<String>,
+    phone_verified_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    phone_number: String,
+    expires_in_minutes: u64,
+    resend_available_in_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendPhoneCodeResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn sending_failed(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(SendPhoneCodeResponse {
+            response_message: "Failed to send phone verification code".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+        .into_response()
+}
+
+/// Texts a verification code to the authenticated user's phone number.
+///
+/// Also serves as the resend endpoint: a new code replaces the outstanding one, but only
+/// once the resend cooldown has passed. Until then the request is refused with a
+/// `Retry-After` header.
+pub async fn send_phone_verification_code(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+) -> Response {
+    let default_sms = SmsSection::default();
+    let sms = state.config.sms.as_ref().unwrap_or(&default_sms);
+
+    let record = sqlx::query_as::<_, PhoneRecord>(
+        "SELECT phone_number, phone_verified_at FROM users WHERE id = $1",
+    )
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await;
+
+    let phone_number = match record {
+        Ok(PhoneRecord {
+            phone_verified_at: Some(_),
+            ..
+        }) => {
+            return sending_failed(
+                StatusCode::CONFLICT,
+                "Phone number is already verified".to_string(),
+            );
+        }
+        Ok(PhoneRecord {
+            phone_number: Some(phone_number),
+            ..
+        }) if !phone_number.trim().is_empty() => phone_number,
+        Ok(_) => {
+            return sending_failed(
+                StatusCode::BAD_REQUEST,
+                "No phone number on this account".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("FAILED TO SEND PHONE VERIFICATION CODE: {}", e);
+
+            return sending_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    };
+
+    let code = generate_phone_verification_code();
+
+    let stored = store_phone_verification_code(
+        &state.db,
+        user.id,
+        &code,
+        sms.phone_code_lifetime_in_minutes,
+        sms.phone_code_resend_cooldown_in_seconds,
+    )
+    .await;
+
+    match stored {
+        Ok(true) => {}
+        Ok(false) => {
+            let retry_after = phone_verification_cooldown_remaining(
+                &state.db,
+                user.id,
+                sms.phone_code_resend_cooldown_in_seconds,
+            )
+            .await
+            .unwrap_or(sms.phone_code_resend_cooldown_in_seconds)
+            .max(1);
+
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after.to_string())],
+                Json(SendPhoneCodeResponse {
+                    response_message: "Failed to send phone verification code".to_string(),
+                    response: None,
+                    error: Some(format!(
+                        "A code was sent recently, try again in {} seconds",
+                        retry_after
+                    )),
+                }),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("FAILED TO STORE PHONE VERIFICATION CODE: {}", e);
+
+            return sending_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    let message =
+        SmsMessage::phone_verification(&phone_number, &code, sms.phone_code_lifetime_in_minutes);
+    if let Err(e) = state.sms_sender.send(&message).await {
+        error!("FAILED TO SEND PHONE VERIFICATION CODE: {}", e);
+
+        let status = match e {
+            SmsError::Disabled => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        return sending_failed(status, e.to_string());
+    }
+
+    (
+        StatusCode::OK,
+        Json(SendPhoneCodeResponse {
+            response_message: "Phone verification code sent".to_string(),
+            response: Some(ResponseCore {
+                phone_number,
+                expires_in_minutes: sms.phone_code_lifetime_in_minutes,
+                resend_available_in_seconds: sms.phone_code_resend_cooldown_in_seconds,
+            }),
+            error: None,
+        }),
+    )
+        .into_response()
+}
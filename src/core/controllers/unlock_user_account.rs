@@ -0,0 +1,67 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::login_lockout_handler::clear_failed_logins;
+use axum::extract::{Path, State};
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
+use tracing::{error, info};
+
+#[derive(Debug, Serialize)]
+pub struct UnlockAccountResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    user_id: i64,
+}
+
+fn unlock_failed(status: StatusCode, error: String) -> (StatusCode, Json<UnlockAccountResponse>) {
+    (
+        status,
+        Json(UnlockAccountResponse {
+            response_message: "Account unlock failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+}
+
+/// Lifts a login lockout before it expires and forgets the user's failed logins.
+///
+/// Admin only. The user can log in again immediately.
+pub async fn unlock_user_account(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<i64>,
+) -> impl IntoResponse {
+    if !user.is_admin {
+        return unlock_failed(StatusCode::FORBIDDEN, "Admin access required".to_string());
+    }
+
+    match clear_failed_logins(&state.db, user_id).await {
+        Ok(true) => {
+            info!("ACCOUNT {} UNLOCKED BY USER {}", user_id, user.id);
+
+            (
+                StatusCode::OK,
+                Json(UnlockAccountResponse {
+                    response_message: "Account unlocked successfully".to_string(),
+                    response: Some(ResponseCore { user_id }),
+                    error: None,
+                }),
+            )
+        }
+        Ok(false) => unlock_failed(StatusCode::NOT_FOUND, "User not found".to_string()),
+        Err(e) => {
+            error!("FAILED TO UNLOCK ACCOUNT: {}", e);
+
+            unlock_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
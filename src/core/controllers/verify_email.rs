@@ -0,0 +1,91 @@
+use crate::AppState;
+use crate::utils::email_verification_handler::mark_email_verified;
+use crate::utils::generate_tokens::verify_email_verification_token;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseCore {
+    email: String,
+    email_verified_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyEmailResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn verification_failed(
+    status: StatusCode,
+    error: String,
+) -> (StatusCode, Json<VerifyEmailResponse>) {
+    (
+        status,
+        Json(VerifyEmailResponse {
+            response_message: "Email verification failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+}
+
+/// Marks the email address an email-verification token was sent to as verified.
+///
+/// Verifying an already verified address succeeds and keeps the original verification time.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> impl IntoResponse {
+    let claims =
+        match verify_email_verification_token(&payload.token, &state.config, &state.jwt_keys) {
+            Ok(claims) => claims,
+            Err(e) => {
+                error!("EMAIL VERIFICATION FAILED: INVALID TOKEN: {}", e);
+
+                return verification_failed(
+                    StatusCode::UNAUTHORIZED,
+                    "Invalid or expired verification token".to_string(),
+                );
+            }
+        };
+
+    match mark_email_verified(&state.db, claims.id, &claims.email).await {
+        Ok(Some(email_verified_at)) => (
+            StatusCode::OK,
+            Json(VerifyEmailResponse {
+                response_message: "Email verified successfully".to_string(),
+                response: Some(ResponseCore {
+                    email: claims.email,
+                    email_verified_at,
+                }),
+                error: None,
+            }),
+        ),
+        Ok(None) => {
+            error!("EMAIL VERIFICATION FAILED: EMAIL ADDRESS HAS CHANGED!");
+
+            verification_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired verification token".to_string(),
+            )
+        }
+        Err(e) => {
+            error!("EMAIL VERIFICATION FAILED: {}", e);
+
+            verification_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
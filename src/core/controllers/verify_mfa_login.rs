@@ -0,0 +1,126 @@
+use crate::AppState;
+use crate::core::controllers::login_user::{
+    USER_PROFILE_COLUMNS, UserProfile, complete_login, login_blocked, login_failed, login_saturated,
+};
+use crate::utils::client_metadata::ClientMetadata;
+use crate::utils::generate_tokens::verify_mfa_pending_token;
+use crate::utils::load_config::SecuritySection;
+use crate::utils::login_lockout_handler::{login_block, record_failed_login};
+use crate::utils::step_up_handler::verify_second_factor;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Response;
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMfaLoginRequest {
+    /// The token `/login` answered with.
+    mfa_token: String,
+    /// A code from the user's authenticator app, or one of their recovery codes.
+    code: String,
+}
+
+/// The second step of a login with two-factor authentication: exchanges the MFA-pending
+/// token from `/login` and a code from the authenticator app or a recovery code for auth
+/// tokens.
+///
+/// A wrong code counts as a failed login, like a wrong password.
+pub async fn verify_mfa_login(
+    cookies: Cookies,
+    client: ClientMetadata,
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyMfaLoginRequest>,
+) -> Response {
+    let claims = verify_mfa_pending_token(&payload.mfa_token, &state.config, &state.jwt_keys);
+
+    let user_id = match claims.map(|claims| claims.sub.parse::<i64>()) {
+        Ok(Ok(user_id)) => user_id,
+        _ => {
+            error!("MFA LOGIN FAILED: INVALID MFA TOKEN!");
+
+            return login_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired MFA token".to_string(),
+            );
+        }
+    };
+
+    let default_security = SecuritySection::default();
+    let security = state.config.security.as_ref().unwrap_or(&default_security);
+
+    match login_block(&state.db, user_id, security).await {
+        Ok(None) => {}
+        Ok(Some(block)) => {
+            error!("MFA LOGIN FAILED: TOO MANY FAILED LOGIN ATTEMPTS!");
+
+            return login_blocked(block);
+        }
+        Err(e) => {
+            error!("FAILED TO LOOK UP FAILED LOGIN ATTEMPTS: {}", e);
+
+            return login_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    match verify_second_factor(
+        &state.db,
+        &state.password_hashing,
+        security,
+        user_id,
+        &payload.code,
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            error!("MFA LOGIN FAILED: INVALID CODE!");
+
+            match record_failed_login(&state.db, user_id, security).await {
+                Ok(Some(lock)) => return login_blocked(lock),
+                Ok(None) => {}
+                Err(e) => error!("FAILED TO RECORD FAILED LOGIN: {}", e),
+            }
+
+            return login_failed(
+                StatusCode::UNAUTHORIZED,
+                "Invalid authentication code".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("MFA LOGIN FAILED: {}", e);
+
+            if let Some(retry_after_in_seconds) = e.retry_after_in_seconds() {
+                return login_saturated(retry_after_in_seconds);
+            }
+
+            return login_failed(e.status_code(), e.to_string());
+        }
+    }
+
+    // Read after the code is consumed, so the remaining recovery codes are counted right
+    let user = sqlx::query_as::<_, UserProfile>(&format!(
+        "SELECT {} FROM users WHERE id = $1",
+        USER_PROFILE_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await;
+
+    match user {
+        Ok(user) => complete_login(&state, cookies, &client, user).await,
+        Err(e) => {
+            error!("MFA LOGIN FAILED: {}", e);
+
+            login_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
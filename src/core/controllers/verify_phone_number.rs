@@ -0,0 +1,108 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::load_config::SmsSection;
+use crate::utils::phone_verification_handler::confirm_phone_verification_code;
+use axum::extract::State;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyPhoneRequest {
+    code: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ResponseCore {
+    phone_number: Option<String>,
+    phone_verified_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyPhoneResponse {
+    response_message: String,
+    response: Option<ResponseCore>,
+    error: Option<String>,
+}
+
+fn verification_failed(
+    status: StatusCode,
+    error: String,
+) -> (StatusCode, Json<VerifyPhoneResponse>) {
+    (
+        status,
+        Json(VerifyPhoneResponse {
+            response_message: "Phone verification failed".to_string(),
+            response: None,
+            error: Some(error),
+        }),
+    )
+}
+
+/// Marks the authenticated user's phone number as verified, given the code texted to it.
+///
+/// Every wrong code counts against the outstanding code's attempts; once they run out a
+/// new code has to be requested.
+pub async fn verify_phone_number(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyPhoneRequest>,
+) -> impl IntoResponse {
+    let default_sms = SmsSection::default();
+    let sms = state.config.sms.as_ref().unwrap_or(&default_sms);
+
+    let confirmed = confirm_phone_verification_code(
+        &state.db,
+        user.id,
+        payload.code.trim(),
+        sms.phone_code_max_attempts,
+    )
+    .await;
+
+    match confirmed {
+        Ok(true) => {}
+        Ok(false) => {
+            error!("PHONE VERIFICATION FAILED: INVALID CODE!");
+
+            return verification_failed(
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired verification code".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("PHONE VERIFICATION FAILED: {}", e);
+
+            return verification_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            );
+        }
+    }
+
+    let verified = sqlx::query_as::<_, ResponseCore>(
+        "SELECT phone_number, phone_verified_at FROM users WHERE id = $1",
+    )
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await;
+
+    match verified {
+        Ok(verified) => (
+            StatusCode::OK,
+            Json(VerifyPhoneResponse {
+                response_message: "Phone number verified successfully".to_string(),
+                response: Some(verified),
+                error: None,
+            }),
+        ),
+        Err(e) => {
+            error!("PHONE VERIFICATION FAILED: {}", e);
+
+            verification_failed(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+        }
+    }
+}
@@ -1,14 +1,96 @@
 use crate::AppState;
+use crate::core::controllers::change_password::change_password;
+use crate::core::controllers::confirm_totp::confirm_totp;
+use crate::core::controllers::disable_totp::disable_totp;
+use crate::core::controllers::enroll_totp::enroll_totp;
+use crate::core::controllers::get_jwks::get_jwks;
+use crate::core::controllers::list_user_sessions::list_user_sessions;
 use crate::core::controllers::login_user::login_user;
 use crate::core::controllers::logout_user::logout_user;
+use crate::core::controllers::refresh_user_tokens::refresh_user_tokens;
+use crate::core::controllers::regenerate_recovery_codes::regenerate_recovery_codes;
 use crate::core::controllers::register_user::register_user;
-use axum::{Router, routing::post};
+use crate::core::controllers::request_password_reset::request_password_reset;
+use crate::core::controllers::resend_email_verification::resend_email_verification;
+use crate::core::controllers::reset_password::reset_password;
+use crate::core::controllers::revoke_user_session::revoke_user_session;
+use crate::core::controllers::rotate_signing_key::rotate_signing_key;
+use crate::core::controllers::send_phone_verification_code::send_phone_verification_code;
+use crate::core::controllers::unlock_user_account::unlock_user_account;
+use crate::core::controllers::verify_email::verify_email;
+use crate::core::controllers::verify_mfa_login::verify_mfa_login;
+use crate::core::controllers::verify_phone_number::verify_phone_number;
+use crate::middlewares::access_middleware::access_middleware;
+use crate::middlewares::admin_routes_protector_middleware::admin_routes_protector_middleware;
+use crate::middlewares::sessions_middleware::sessions_middleware;
+use axum::{
+    Router, middleware,
+    routing::{delete, get, post},
+};
 use tower_cookies::CookieManagerLayer;
 
-pub fn auth_routes(_state: &AppState) -> Router<AppState> {
-    Router::new()
+/// Builds the authentication routes.
+///
+/// Routes fall in three groups. Public routes take no credential. Protected routes need an
+/// authenticated user, and admin routes an admin. Each group is wrapped in the middlewares
+/// enabled under `[client_integrations]`; the handlers check credentials and admin rights
+/// themselves either way.
+pub fn auth_routes(state: &AppState) -> Router<AppState> {
+    let client_integrations = &state.config.client_integrations;
+
+    let public = Router::new()
         .route("/register", post(register_user))
         .route("/login", post(login_user))
+        .route("/login/mfa", post(verify_mfa_login))
+        .route("/refresh", post(refresh_user_tokens))
+        .route("/forgot-password", post(request_password_reset))
+        .route("/reset-password", post(reset_password))
+        .route("/verify-email", post(verify_email))
+        .route("/resend-verification", post(resend_email_verification));
+
+    let protected = Router::new()
         .route("/logout", post(logout_user))
-        .layer(CookieManagerLayer::new())
+        .route("/password", post(change_password))
+        .route("/phone/send-code", post(send_phone_verification_code))
+        .route("/phone/verify", post(verify_phone_number))
+        .route("/sessions", get(list_user_sessions))
+        .route("/sessions/{id}", delete(revoke_user_session))
+        .route("/mfa/totp/enroll", post(enroll_totp))
+        .route("/mfa/totp/confirm", post(confirm_totp))
+        .route("/mfa/totp/disable", post(disable_totp))
+        .route("/mfa/recovery-codes", post(regenerate_recovery_codes));
+
+    let mut admin = Router::new()
+        .route("/keys/rotate", post(rotate_signing_key))
+        .route("/admin/users/{id}/unlock", post(unlock_user_account));
+
+    if client_integrations.allow_admin_routes_protector_middleware {
+        admin = admin.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_routes_protector_middleware,
+        ));
+    }
+
+    // Layers added last run first, so access is verified before the session is touched
+    let mut authenticated = protected.merge(admin);
+
+    if client_integrations.allow_sessions_middleware {
+        authenticated = authenticated.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            sessions_middleware,
+        ));
+    }
+
+    if client_integrations.allow_access_middleware {
+        authenticated = authenticated.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            access_middleware,
+        ));
+    }
+
+    public.merge(authenticated).layer(CookieManagerLayer::new())
+}
+
+pub fn well_known_routes() -> Router<AppState> {
+    Router::new().route("/jwks.json", get(get_jwks))
 }
@@ -3,17 +3,27 @@
 //! This crate provides the core logic for the authentication server, including
 //! router setup, state management, and middleware integration.
 
-use crate::core::router::auth_routes;
+use crate::core::router::{auth_routes, well_known_routes};
+use crate::mailer::Mailer;
 use crate::middlewares::logging_middleware::logging_middleware;
+use crate::middlewares::rate_limit_middleware::rate_limit_middleware;
 use crate::middlewares::request_timeout_middleware::timeout_middleware;
+use crate::rate_limit::RateLimitStore;
+use crate::sms::SmsSender;
+use crate::utils::hashing_handler::PasswordHashing;
+use crate::utils::jwt_keys::JwtKeys;
 use crate::utils::load_config::AppConfig;
+use crate::utils::password_blocklist::PasswordBlocklist;
 use axum::{Router, middleware};
 use sqlx::PgPool;
 use std::sync::Arc;
 
 pub mod core;
 pub mod db;
+pub mod mailer;
 pub mod middlewares;
+pub mod rate_limit;
+pub mod sms;
 pub mod utils;
 
 /// Global application state shared across all routes and middlewares.
@@ -23,21 +33,53 @@ pub struct AppState {
     pub config: Arc<AppConfig>,
     /// Thread-safe PostgreSQL connection pool.
     pub db: PgPool,
+    /// Keys tokens are signed and verified with, loaded once at start-up.
+    pub jwt_keys: Arc<JwtKeys>,
+    /// Delivers every email the service sends, selected by the `[mail]` section.
+    pub mailer: Arc<dyn Mailer>,
+    /// Delivers every text message the service sends, selected by the `[sms]` section.
+    pub sms_sender: Arc<dyn SmsSender>,
+    /// Breached and common passwords, loaded once at start-up. Empty when disabled.
+    pub password_blocklist: Arc<PasswordBlocklist>,
+    /// Argon2id parameters and pepper passwords are hashed with.
+    pub password_hashing: Arc<PasswordHashing>,
+    /// Token buckets of the rate limiter, selected by the `[rate_limit]` section.
+    pub rate_limit_store: Arc<dyn RateLimitStore>,
 }
 
 /// Creates the main Axum application router.
 ///
 /// This function:
 /// - Nests the authentication routes under `/api/v1/auth`.
-/// - Integrates logging and request timeout middlewares.
+/// - Serves the public signing keys under `/.well-known`.
+/// - Builds its middleware stack from the `[client_integrations]` flags: rate limiting,
+///   logging and request timeouts here, access, sessions and admin-route protection on
+///   the authentication routes.
 /// - Provides the global `AppState` to all handlers.
 pub fn create_app(state: AppState) -> Router {
-    Router::new()
+    let client_integrations = &state.config.client_integrations;
+
+    let mut app = Router::new()
         .nest("/api/v1/auth", auth_routes(&state))
-        .layer(middleware::from_fn(logging_middleware))
-        .layer(middleware::from_fn_with_state(
+        .nest("/.well-known", well_known_routes());
+
+    if client_integrations.allow_rate_limit_middleware {
+        app = app.layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_middleware,
+        ));
+    }
+
+    if client_integrations.allow_logging_middleware {
+        app = app.layer(middleware::from_fn(logging_middleware));
+    }
+
+    if client_integrations.allow_request_timeout_middleware {
+        app = app.layer(middleware::from_fn_with_state(
             state.clone(),
             timeout_middleware,
-        ))
-        .with_state(state)
+        ));
+    }
+
+    app.with_state(state)
 }
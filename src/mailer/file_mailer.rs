@@ -0,0 +1,76 @@
+use crate::mailer::{EmailMessage, MailError, Mailer};
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Drops every message into a directory as an `.eml` file instead of delivering it.
+///
+/// The files open in any mail client, which makes the HTML bodies easy to review locally.
+#[derive(Debug)]
+pub struct FileMailer {
+    directory: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(directory: impl AsRef<Path>, from: Mailbox) -> Self {
+        FileMailer {
+            directory: directory.as_ref().to_path_buf(),
+            from,
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        let mime = message.to_mime(&self.from)?;
+
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        // Sortable by sending time, and unique even within the same millisecond
+        let file_name = format!(
+            "{}_{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%3f"),
+            Uuid::new_v4()
+        );
+        tokio::fs::write(self.directory.join(file_name), mime.formatted()).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_drops_eml_file() {
+        let directory = std::env::temp_dir().join(format!("file_mailer_{}", Uuid::new_v4()));
+        let mailer = FileMailer::new(&directory, "no-reply@localhost".parse().unwrap());
+
+        mailer
+            .send(&EmailMessage {
+                to: "user@example.com".to_string(),
+                subject: "Dropped".to_string(),
+                text_body: "Plain body".to_string(),
+                html_body: "<p>HTML body</p>".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+
+        let contents = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(contents.contains("Subject: Dropped"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
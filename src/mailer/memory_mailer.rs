@@ -0,0 +1,81 @@
+use crate::mailer::{EmailMessage, MailError, Mailer};
+use async_trait::async_trait;
+use std::sync::{Mutex, PoisonError};
+
+/// Keeps every message in memory instead of delivering it, so tests can read them back.
+#[derive(Debug, Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<EmailMessage>>,
+}
+
+impl MemoryMailer {
+    /// Every message sent so far, oldest first.
+    pub fn sent(&self) -> Vec<EmailMessage> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The most recent message sent to `to`, if any.
+    pub fn last_sent_to(&self, to: &str) -> Option<EmailMessage> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .rev()
+            .find(|message| message.to == to)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(message.clone());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(to: &str, subject: &str) -> EmailMessage {
+        EmailMessage {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            text_body: String::new(),
+            html_body: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_last_sent_to() {
+        let mailer = MemoryMailer::default();
+        assert!(mailer.last_sent_to("a@example.com").is_none());
+
+        mailer
+            .send(&message("a@example.com", "first"))
+            .await
+            .unwrap();
+        mailer
+            .send(&message("b@example.com", "other"))
+            .await
+            .unwrap();
+        mailer
+            .send(&message("a@example.com", "second"))
+            .await
+            .unwrap();
+
+        assert_eq!(mailer.sent().len(), 3);
+        assert_eq!(
+            mailer.last_sent_to("a@example.com").unwrap().subject,
+            "second"
+        );
+    }
+}
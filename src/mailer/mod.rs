@@ -0,0 +1,173 @@
+//! # Outbound Mail
+//!
+//! Every email the service sends goes through the [`Mailer`] trait, so the delivery
+//! mechanism is picked by the `[mail]` config section:
+//! - `smtp`: delivers through an SMTP relay.
+//! - `file`: drops every message into a directory as an `.eml` file.
+//! - `memory`: keeps messages in memory, for tests.
+//! - `stdout`: prints messages, the default for local development.
+//!
+//! Messages themselves are built from the [`templates`].
+
+use crate::utils::load_config::MailSection;
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use std::fmt::Debug;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub mod file_mailer;
+pub mod memory_mailer;
+pub mod smtp_mailer;
+pub mod stdout_mailer;
+pub mod templates;
+
+pub use file_mailer::FileMailer;
+pub use memory_mailer::MemoryMailer;
+pub use smtp_mailer::SmtpMailer;
+pub use stdout_mailer::StdoutMailer;
+
+/// Values accepted for `mail.transport`.
+pub const SUPPORTED_MAIL_TRANSPORTS: [&str; 4] = ["smtp", "file", "memory", "stdout"];
+
+/// Values accepted for `mail.smtp_security`.
+pub const SUPPORTED_SMTP_SECURITY: [&str; 3] = ["starttls", "tls", "none"];
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("Invalid email address {0}: {1}")]
+    InvalidAddress(String, lettre::address::AddressError),
+    #[error("Failed to build message: {0}")]
+    Build(#[from] lettre::error::Error),
+    #[error("SMTP error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("Failed to write message: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unsupported mail transport: {0}")]
+    UnsupportedTransport(String),
+    #[error("{0} is not configured")]
+    MissingSetting(&'static str),
+}
+
+/// An email with a plain-text body and an equivalent HTML body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+impl EmailMessage {
+    /// Builds the MIME message sent from `from`, with both bodies as alternatives.
+    pub fn to_mime(&self, from: &Mailbox) -> Result<lettre::Message, MailError> {
+        let to = self
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::InvalidAddress(self.to.clone(), e))?;
+
+        Ok(lettre::Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(&self.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                self.text_body.clone(),
+                self.html_body.clone(),
+            ))?)
+    }
+}
+
+/// Delivers emails.
+#[async_trait]
+pub trait Mailer: Debug + Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailError>;
+}
+
+/// Builds the mailer selected by the `[mail]` section, printing to stdout without one.
+pub fn mailer_from_config(mail: Option<&MailSection>) -> Result<Arc<dyn Mailer>, MailError> {
+    let default_section = MailSection::default();
+    let mail = mail.unwrap_or(&default_section);
+
+    let from = mail
+        .from_address
+        .parse::<Mailbox>()
+        .map_err(|e| MailError::InvalidAddress(mail.from_address.clone(), e))?;
+
+    match mail.transport.as_str() {
+        "smtp" => Ok(Arc::new(SmtpMailer::from_config(mail, from)?)),
+        "file" => {
+            let directory = mail
+                .drop_directory
+                .as_deref()
+                .ok_or(MailError::MissingSetting("mail.drop_directory"))?;
+
+            Ok(Arc::new(FileMailer::new(directory, from)))
+        }
+        "memory" => Ok(Arc::new(MemoryMailer::default())),
+        "stdout" => Ok(Arc::new(StdoutMailer)),
+        other => Err(MailError::UnsupportedTransport(other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            to: "user@example.com".to_string(),
+            subject: "Hello".to_string(),
+            text_body: "Plain body".to_string(),
+            html_body: "<p>HTML body</p>".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_to_mime_has_both_bodies() {
+        let from = "Krabby Chat <no-reply@localhost>".parse().unwrap();
+        let mime = String::from_utf8(message().to_mime(&from).unwrap().formatted()).unwrap();
+
+        assert!(mime.contains("To: user@example.com"));
+        assert!(mime.contains("Subject: Hello"));
+        assert!(mime.contains("multipart/alternative"));
+        assert!(mime.contains("Plain body"));
+        assert!(mime.contains("<p>HTML body</p>"));
+    }
+
+    #[test]
+    fn test_to_mime_rejects_invalid_recipient() {
+        let from = "no-reply@localhost".parse().unwrap();
+        let message = EmailMessage {
+            to: "not an address".to_string(),
+            ..message()
+        };
+
+        assert!(matches!(
+            message.to_mime(&from),
+            Err(MailError::InvalidAddress(..))
+        ));
+    }
+
+    #[test]
+    fn test_mailer_from_config() {
+        assert!(mailer_from_config(None).is_ok());
+
+        let file = MailSection {
+            transport: "file".to_string(),
+            ..MailSection::default()
+        };
+        assert!(matches!(
+            mailer_from_config(Some(&file)),
+            Err(MailError::MissingSetting("mail.drop_directory"))
+        ));
+
+        let unknown = MailSection {
+            transport: "pigeon".to_string(),
+            ..MailSection::default()
+        };
+        assert!(matches!(
+            mailer_from_config(Some(&unknown)),
+            Err(MailError::UnsupportedTransport(_))
+        ));
+    }
+}
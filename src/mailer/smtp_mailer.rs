@@ -0,0 +1,50 @@
+use crate::mailer::{EmailMessage, MailError, Mailer};
+use crate::utils::load_config::MailSection;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+/// Delivers mail through an SMTP relay, reusing pooled connections.
+#[derive(Debug)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn from_config(mail: &MailSection, from: Mailbox) -> Result<Self, MailError> {
+        let host = mail
+            .smtp_host
+            .as_deref()
+            .ok_or(MailError::MissingSetting("mail.smtp_host"))?;
+
+        let mut builder = match mail.smtp_security.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            // Plain-text connection, e.g. to a local mail catcher
+            _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+
+        if let Some(port) = mail.smtp_port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&mail.smtp_username, &mail.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        self.transport.send(message.to_mime(&self.from)?).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+use crate::mailer::{EmailMessage, MailError, Mailer};
+use async_trait::async_trait;
+use std::io::Write;
+
+/// Prints the plain-text version of every message instead of delivering it.
+#[derive(Debug)]
+pub struct StdoutMailer;
+
+#[async_trait]
+impl Mailer for StdoutMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        let mut stdout = std::io::stdout().lock();
+
+        writeln!(
+            stdout,
+            "==================== MAIL ====================\nTo: {}\nSubject: {}\n\n{}\n==============================================",
+            message.to, message.subject, message.text_body
+        )?;
+
+        Ok(())
+    }
+}
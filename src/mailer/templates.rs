@@ -0,0 +1,297 @@
+//! # Message Templates
+//!
+//! Every message has a plain-text body and an HTML body carrying the same content.
+//! When `mail.public_base_url` is set, tokens are sent as links into the client;
+//! otherwise the bare token is sent for the user to paste.
+
+use crate::mailer::EmailMessage;
+use crate::utils::client_metadata::ClientMetadata;
+use crate::utils::load_config::AppConfig;
+use chrono::{DateTime, Utc};
+
+const PRODUCT_NAME: &str = "Krabby Chat";
+
+/// Escapes text for use inside HTML element content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Wraps already escaped HTML paragraphs into a complete document.
+fn html_document(subject: &str, paragraphs: &[String]) -> String {
+    let body: String = paragraphs
+        .iter()
+        .map(|paragraph| format!("    <p>{}</p>\n", paragraph))
+        .collect();
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n  <head>\n    <meta charset=\"utf-8\">\n    <title>{}</title>\n  </head>\n  <body style=\"font-family: sans-serif; line-height: 1.5;\">\n{}    <p>&mdash; The {} team</p>\n  </body>\n</html>\n",
+        escape_html(subject),
+        body,
+        PRODUCT_NAME
+    )
+}
+
+/// The link into the client for `path`, or `None` without `mail.public_base_url`.
+fn client_link(config: &AppConfig, path: &str, token: &str) -> Option<String> {
+    let base_url = config.mail.as_ref()?.public_base_url.as_deref()?;
+
+    Some(format!(
+        "{}/{}?token={}",
+        base_url.trim_end_matches('/'),
+        path,
+        token
+    ))
+}
+
+/// The line and HTML paragraph handing `token` to the user, as a link when possible.
+fn token_call_to_action(config: &AppConfig, path: &str, token: &str) -> (String, String) {
+    match client_link(config, path, token) {
+        Some(link) => (
+            link.clone(),
+            format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(&link),
+                escape_html(&link)
+            ),
+        ),
+        None => (
+            token.to_string(),
+            format!("<code>{}</code>", escape_html(token)),
+        ),
+    }
+}
+
+/// The message carrying an email-verification token.
+pub fn email_verification(config: &AppConfig, to: &str, token: &str) -> EmailMessage {
+    let subject = format!("Verify your {} email address", PRODUCT_NAME);
+    let lifetime_in_hours = config
+        .auth
+        .as_ref()
+        .map(|auth| auth.jwt_email_verification_lifetime_in_hours)
+        .unwrap_or_default();
+    let (token_line, token_html) = token_call_to_action(config, "verify-email", token);
+
+    EmailMessage {
+        to: to.to_string(),
+        text_body: format!(
+            "Hi,\n\nPlease confirm that {} is your email address using the verification token below:\n\n{}\n\nIt expires in {} hours. If you did not create a {} account, you can ignore this message.\n\n— The {} team\n",
+            to, token_line, lifetime_in_hours, PRODUCT_NAME, PRODUCT_NAME
+        ),
+        html_body: html_document(
+            &subject,
+            &[
+                "Hi,".to_string(),
+                format!(
+                    "Please confirm that <strong>{}</strong> is your email address using the verification token below:",
+                    escape_html(to)
+                ),
+                token_html,
+                format!(
+                    "It expires in {} hours. If you did not create a {} account, you can ignore this message.",
+                    lifetime_in_hours, PRODUCT_NAME
+                ),
+            ],
+        ),
+        subject,
+    }
+}
+
+/// The message carrying a password-reset code.
+pub fn password_reset(config: &AppConfig, to: &str, code: &str) -> EmailMessage {
+    let subject = format!("Reset your {} password", PRODUCT_NAME);
+    let lifetime_in_minutes = config
+        .auth
+        .as_ref()
+        .map(|auth| auth.jwt_one_time_password_lifetime_in_minutes)
+        .unwrap_or_default();
+    let (code_line, code_html) = token_call_to_action(config, "reset-password", code);
+
+    EmailMessage {
+        to: to.to_string(),
+        text_body: format!(
+            "Hi,\n\nSomeone asked to reset the password of your {} account. Use the code below to choose a new password:\n\n{}\n\nIt expires in {} minutes and works once. Resetting your password logs you out on every device. If you did not ask for this, you can ignore this message.\n\n— The {} team\n",
+            PRODUCT_NAME, code_line, lifetime_in_minutes, PRODUCT_NAME
+        ),
+        html_body: html_document(
+            &subject,
+            &[
+                "Hi,".to_string(),
+                format!(
+                    "Someone asked to reset the password of your {} account. Use the code below to choose a new password:",
+                    PRODUCT_NAME
+                ),
+                code_html,
+                format!(
+                    "It expires in {} minutes and works once. Resetting your password logs you out on every device. If you did not ask for this, you can ignore this message.",
+                    lifetime_in_minutes
+                ),
+            ],
+        ),
+        subject,
+    }
+}
+
+/// The message telling the owner of `to` that someone tried to register it again.
+///
+/// Sent instead of an error when registration must not reveal which emails are taken.
+pub fn account_already_exists(to: &str) -> EmailMessage {
+    let subject = format!("Your {} account already exists", PRODUCT_NAME);
+
+    EmailMessage {
+        to: to.to_string(),
+        text_body: format!(
+            "Hi,\n\nSomeone tried to create a {} account with {}, but this address already has one. If it was you, log in, or reset your password if you have forgotten it. Otherwise, you can ignore this message.\n\n— The {} team\n",
+            PRODUCT_NAME, to, PRODUCT_NAME
+        ),
+        html_body: html_document(
+            &subject,
+            &[
+                "Hi,".to_string(),
+                format!(
+                    "Someone tried to create a {} account with <strong>{}</strong>, but this address already has one.",
+                    PRODUCT_NAME,
+                    escape_html(to)
+                ),
+                "If it was you, log in, or reset your password if you have forgotten it. Otherwise, you can ignore this message.".to_string(),
+            ],
+        ),
+        subject,
+    }
+}
+
+/// The message warning a user about a login from a device they have not used before.
+pub fn new_device_alert(
+    to: &str,
+    client: &ClientMetadata,
+    signed_in_at: DateTime<Utc>,
+) -> EmailMessage {
+    let subject = format!("New sign-in to your {} account", PRODUCT_NAME);
+    let device = client.user_agent.as_deref().unwrap_or("Unknown device");
+    let ip_address = client.ip_address.as_deref().unwrap_or("unknown");
+    let signed_in_at = signed_in_at.format("%Y-%m-%d %H:%M UTC").to_string();
+
+    EmailMessage {
+        to: to.to_string(),
+        text_body: format!(
+            "Hi,\n\nYour {} account was just signed in to from a new device:\n\nDevice: {}\nIP address: {}\nTime: {}\n\nIf this was you, there is nothing to do. Otherwise, reset your password and revoke the session from your active sessions.\n\n— The {} team\n",
+            PRODUCT_NAME, device, ip_address, signed_in_at, PRODUCT_NAME
+        ),
+        html_body: html_document(
+            &subject,
+            &[
+                "Hi,".to_string(),
+                format!(
+                    "Your {} account was just signed in to from a new device:",
+                    PRODUCT_NAME
+                ),
+                format!(
+                    "Device: {}<br>IP address: {}<br>Time: {}",
+                    escape_html(device),
+                    escape_html(ip_address),
+                    signed_in_at
+                ),
+                "If this was you, there is nothing to do. Otherwise, reset your password and revoke the session from your active sessions.".to_string(),
+            ],
+        ),
+        subject,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::load_config::{
+        AppSection, ClientIntegrationsSection, MailSection, ObservabilitySection,
+    };
+
+    fn config(public_base_url: Option<&str>) -> AppConfig {
+        AppConfig {
+            app: AppSection {
+                name: "Test App".to_string(),
+                environment: Some("test".to_string()),
+            },
+            client_integrations: ClientIntegrationsSection {
+                allow_access_middleware: false,
+                allow_sessions_middleware: false,
+                allow_logging_middleware: false,
+                allow_request_timeout_middleware: false,
+                allow_admin_routes_protector_middleware: false,
+                allow_rate_limit_middleware: false,
+            },
+            observability: ObservabilitySection {
+                enable_tracing: false,
+                enable_metrics: false,
+            },
+            server: None,
+            database: None,
+            auth: None,
+            mail: Some(MailSection {
+                public_base_url: public_base_url.map(str::to_string),
+                ..MailSection::default()
+            }),
+            sms: None,
+            security: None,
+            rate_limit: None,
+        }
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & 'Jerry'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_tokens_are_sent_bare_without_base_url() {
+        let message = email_verification(&config(None), "user@example.com", "abc.def.ghi");
+
+        assert_eq!(message.to, "user@example.com");
+        assert!(message.text_body.contains("\n\nabc.def.ghi\n\n"));
+        assert!(message.html_body.contains("<code>abc.def.ghi</code>"));
+    }
+
+    #[test]
+    fn test_tokens_are_sent_as_links_with_base_url() {
+        let message = password_reset(
+            &config(Some("https://chat.example.com/")),
+            "user@example.com",
+            "abc.def.ghi",
+        );
+
+        let link = "https://chat.example.com/reset-password?token=abc.def.ghi";
+        assert!(message.text_body.contains(link));
+        assert!(
+            message
+                .html_body
+                .contains(&format!("<a href=\"{}\">", link))
+        );
+    }
+
+    #[test]
+    fn test_new_device_alert_escapes_client_metadata() {
+        let client = ClientMetadata {
+            user_agent: Some("<script>alert(1)</script>".to_string()),
+            ip_address: None,
+        };
+        let message = new_device_alert("user@example.com", &client, Utc::now());
+
+        assert!(message.text_body.contains("<script>alert(1)</script>"));
+        assert!(!message.html_body.contains("<script>"));
+        assert!(message.html_body.contains("IP address: unknown"));
+    }
+}
@@ -8,8 +8,14 @@
 //! - Server binding and execution.
 
 use chat_auth_server::db::connect_postgres::connect_pg;
+use chat_auth_server::mailer::mailer_from_config;
+use chat_auth_server::rate_limit::rate_limit_store_from_config;
+use chat_auth_server::sms::sms_sender_from_config;
+use chat_auth_server::utils::hashing_handler::PasswordHashing;
+use chat_auth_server::utils::jwt_keys::JwtKeys;
 use chat_auth_server::utils::load_config::load_config;
 use chat_auth_server::utils::load_env::load_env;
+use chat_auth_server::utils::password_blocklist::PasswordBlocklist;
 use chat_auth_server::{AppState, create_app};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -57,6 +63,71 @@ async fn main() {
         }
     };
 
+    let jwt_keys = match clean_config.auth.as_ref().map(JwtKeys::from_config) {
+        Some(Ok(keys)) => keys,
+        Some(Err(e)) => {
+            error!(
+                "SERVER START-UP ERROR: FAILED TO LOAD JWT SIGNING KEYS, {}",
+                e
+            );
+            std::process::exit(1);
+        }
+        None => {
+            error!("SERVER START-UP ERROR: AUTH CONFIGURATION IS MISSING!");
+            std::process::exit(1);
+        }
+    };
+
+    let mailer = match mailer_from_config(clean_config.mail.as_ref()) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            error!("SERVER START-UP ERROR: FAILED TO SET UP THE MAILER, {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let sms_sender = match sms_sender_from_config(clean_config.sms.as_ref()) {
+        Ok(sms_sender) => sms_sender,
+        Err(e) => {
+            error!(
+                "SERVER START-UP ERROR: FAILED TO SET UP THE SMS SENDER, {}",
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let password_blocklist = match PasswordBlocklist::from_config(clean_config.security.as_ref()) {
+        Ok(password_blocklist) => password_blocklist,
+        Err(e) => {
+            error!(
+                "SERVER START-UP ERROR: FAILED TO LOAD THE PASSWORD BLOCKLIST, {}",
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let password_hashing = match PasswordHashing::from_config(clean_config.security.as_ref()) {
+        Ok(password_hashing) => password_hashing,
+        Err(e) => {
+            error!(
+                "SERVER START-UP ERROR: FAILED TO SET UP PASSWORD HASHING, {}",
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    // Made now so the first login for an unknown email is not slower than the rest
+    if let Err(e) = password_hashing.dummy_hash().await {
+        error!(
+            "SERVER START-UP ERROR: FAILED TO SET UP PASSWORD HASHING, {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
     let db_config = match clean_config.database.as_ref() {
         Some(config) => config,
         None => {
@@ -93,9 +164,27 @@ async fn main() {
     )
     .await;
 
+    let rate_limit_store =
+        match rate_limit_store_from_config(clean_config.rate_limit.as_ref(), &db_pool) {
+            Ok(rate_limit_store) => rate_limit_store,
+            Err(e) => {
+                error!(
+                    "SERVER START-UP ERROR: FAILED TO SET UP THE RATE LIMITER, {}",
+                    e
+                );
+                std::process::exit(1);
+            }
+        };
+
     let state = AppState {
         config: Arc::new(clean_config),
         db: db_pool,
+        jwt_keys: Arc::new(jwt_keys),
+        mailer,
+        sms_sender,
+        password_blocklist: Arc::new(password_blocklist),
+        password_hashing: Arc::new(password_hashing),
+        rate_limit_store,
     };
 
     let app = create_app(state.clone());
@@ -143,7 +232,11 @@ async fn main() {
         }
     };
 
-    let server_result = axum::serve(listener, app).await;
+    let server_result = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await;
 
     match server_result {
         Ok(_) => {
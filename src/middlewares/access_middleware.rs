@@ -0,0 +1,223 @@
+use crate::AppState;
+use crate::utils::cookie_deploy_handler::auth_cookie_from_headers;
+use crate::utils::generate_tokens::{verify_access_token, verify_refresh_token};
+use crate::utils::session_handler::hash_refresh_token;
+use axum::{
+    Json,
+    extract::{FromRequestParts, Request, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use tracing::error;
+use uuid::Uuid;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct AccessErrorResponse {
+    pub error: String,
+    pub response_message: String,
+}
+
+pub type AccessRejection = (StatusCode, Json<AccessErrorResponse>);
+
+/// The caller behind a verified access token or auth cookie.
+///
+/// Use it as a handler argument to require authentication on a route. When the route sits
+/// behind [`access_middleware`], the already-verified user is reused instead of re-checked.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser {
+    pub id: i64,
+    pub email: String,
+    pub is_admin: bool,
+    /// Session the credential was issued for.
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct UserAccessState {
+    email: String,
+    is_admin: bool,
+    is_active: bool,
+    is_logged_out: bool,
+    is_session_active: bool,
+}
+
+fn unauthorized(error: &str) -> AccessRejection {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(AccessErrorResponse {
+            error: "Unauthorized".to_string(),
+            response_message: error.to_string(),
+        }),
+    )
+}
+
+/// Returns the token of an `Authorization: Bearer <token>` header, if present.
+pub fn extract_bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+
+    Some(token.trim())
+}
+
+/// Verifies the credential of a request and loads the user it belongs to.
+///
+/// A bearer access token is preferred. Without one, the auth cookie is accepted as long as
+/// it still holds the latest refresh token of its session, so a cookie that was rotated out
+/// by a refresh stops working.
+///
+/// Rejects with `401` when no credential is present, when it is invalid or expired, when the
+/// user no longer exists or has been deactivated, or when the session has been logged out.
+pub async fn authenticate(
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<AuthenticatedUser, AccessRejection> {
+    let (claims, refresh_token_hash) = match extract_bearer_token(headers) {
+        Some(token) => {
+            let claims =
+                verify_access_token(token, &state.config, &state.jwt_keys).map_err(|e| {
+                    error!("ACCESS DENIED: INVALID ACCESS TOKEN: {}", e);
+                    unauthorized("Invalid or expired access token")
+                })?;
+
+            (claims, None)
+        }
+        None => {
+            let cookie = auth_cookie_from_headers(headers)
+                .ok_or_else(|| unauthorized("Missing bearer access token or auth cookie"))?;
+
+            let claims =
+                verify_refresh_token(&cookie, &state.config, &state.jwt_keys).map_err(|e| {
+                    error!("ACCESS DENIED: INVALID AUTH COOKIE: {}", e);
+                    unauthorized("Invalid or expired auth cookie")
+                })?;
+
+            (claims, Some(hash_refresh_token(&cookie)))
+        }
+    };
+
+    let user = sqlx::query_as::<_, UserAccessState>(
+        r#"
+        SELECT
+            email,
+            is_admin,
+            is_active,
+            is_logged_out,
+            EXISTS (
+                SELECT 1 FROM sessions
+                WHERE id = $2 AND user_id = users.id AND revoked_at IS NULL AND expires_at > NOW()
+                    AND ($3::text IS NULL OR refresh_token_hash = $3)
+            ) AS is_session_active
+        FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(claims.id)
+    .bind(claims.sid)
+    .bind(refresh_token_hash)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        error!("ACCESS CHECK FAILED: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AccessErrorResponse {
+                error: "Internal Server Error".to_string(),
+                response_message: format!("Database error: {}", e),
+            }),
+        )
+    })?;
+
+    match user {
+        None => Err(unauthorized("User no longer exists")),
+        Some(user) if !user.is_active => Err(unauthorized("User account is not active")),
+        Some(user) if user.is_logged_out || !user.is_session_active => {
+            Err(unauthorized("Session has been logged out"))
+        }
+        Some(user) => Ok(AuthenticatedUser {
+            id: claims.id,
+            email: user.email,
+            is_admin: user.is_admin,
+            session_id: claims.sid,
+        }),
+    }
+}
+
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AccessRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(user.clone());
+        }
+
+        authenticate(&parts.headers, state).await
+    }
+}
+
+// ============================================================================
+// Access Middleware
+// ============================================================================
+
+/// Rejects every request without a valid access token or auth cookie, and makes the verified
+/// [`AuthenticatedUser`] available to the handlers behind it.
+pub async fn access_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AccessRejection> {
+    let user = authenticate(req.headers(), &state).await?;
+    req.extensions_mut().insert(user);
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_extract_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(extract_bearer_token(&headers), None);
+
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer abc.def.ghi"),
+        );
+        assert_eq!(extract_bearer_token(&headers), Some("abc.def.ghi"));
+
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("bearer abc.def.ghi"),
+        );
+        assert_eq!(extract_bearer_token(&headers), Some("abc.def.ghi"));
+    }
+
+    #[test]
+    fn test_extract_bearer_token_rejects_other_schemes() {
+        let mut headers = HeaderMap::new();
+
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Basic dXNlcjpwYXNz"),
+        );
+        assert_eq!(extract_bearer_token(&headers), None);
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer "));
+        assert_eq!(extract_bearer_token(&headers), None);
+    }
+}
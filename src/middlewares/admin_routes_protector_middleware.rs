@@ -0,0 +1,37 @@
+use crate::middlewares::access_middleware::{
+    AccessErrorResponse, AccessRejection, AuthenticatedUser,
+};
+use axum::{Json, extract::Request, http::StatusCode, middleware::Next, response::Response};
+use tracing::error;
+
+// ============================================================================
+// Admin Routes Protector Middleware
+// ============================================================================
+
+/// Rejects every request not made by an admin: `401` without a valid credential, `403`
+/// for other users.
+pub async fn admin_routes_protector_middleware(
+    user: AuthenticatedUser,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AccessRejection> {
+    if !user.is_admin {
+        error!(
+            "ACCESS DENIED: USER {} IS NOT AN ADMIN: {}",
+            user.id,
+            req.uri().path()
+        );
+
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(AccessErrorResponse {
+                error: "Admin access required".to_string(),
+                response_message: "Access denied".to_string(),
+            }),
+        ));
+    }
+
+    req.extensions_mut().insert(user);
+
+    Ok(next.run(req).await)
+}
@@ -1,2 +1,6 @@
+pub mod access_middleware;
+pub mod admin_routes_protector_middleware;
 pub mod logging_middleware;
+pub mod rate_limit_middleware;
 pub mod request_timeout_middleware;
+pub mod sessions_middleware;
@@ -0,0 +1,300 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::extract_bearer_token;
+use crate::rate_limit::RateLimitOutcome;
+use crate::utils::client_metadata::ClientMetadata;
+use crate::utils::generate_tokens::verify_access_token;
+use crate::utils::load_config::{RateLimitRule, RateLimitSection};
+use axum::{
+    Json,
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Largest body read to find the targeted email, matching axum's default body limit.
+const MAX_INSPECTED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+#[derive(Debug, Serialize)]
+pub struct RateLimitErrorResponse {
+    pub error: String,
+    pub response_message: String,
+}
+
+/// The rule of the `[rate_limit]` section a route is metered with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitedRoute {
+    Login,
+    Register,
+    OneTimePassword,
+    Default,
+}
+
+impl RateLimitedRoute {
+    pub fn from_path(path: &str) -> Self {
+        match path.strip_prefix("/api/v1/auth") {
+            Some("/login" | "/login/mfa") => RateLimitedRoute::Login,
+            Some("/register") => RateLimitedRoute::Register,
+            Some(
+                "/forgot-password"
+                | "/reset-password"
+                | "/verify-email"
+                | "/resend-verification"
+                | "/phone/send-code"
+                | "/phone/verify"
+                | "/mfa/totp/enroll"
+                | "/mfa/totp/confirm"
+                | "/mfa/totp/disable"
+                | "/mfa/recovery-codes",
+            ) => RateLimitedRoute::OneTimePassword,
+            _ => RateLimitedRoute::Default,
+        }
+    }
+
+    /// Prefix of the bucket keys, so routes never share a bucket.
+    fn name(self) -> &'static str {
+        match self {
+            RateLimitedRoute::Login => "login",
+            RateLimitedRoute::Register => "register",
+            RateLimitedRoute::OneTimePassword => "one_time_password",
+            RateLimitedRoute::Default => "default",
+        }
+    }
+
+    fn rule(self, rate_limit: &RateLimitSection) -> &RateLimitRule {
+        match self {
+            RateLimitedRoute::Login => &rate_limit.login,
+            RateLimitedRoute::Register => &rate_limit.register,
+            RateLimitedRoute::OneTimePassword => &rate_limit.one_time_password,
+            RateLimitedRoute::Default => &rate_limit.default,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TargetedEmail {
+    email: Option<String>,
+}
+
+// ============================================================================
+// Rate Limit Middleware
+// ============================================================================
+
+fn rejection(status: StatusCode, error: &str, response_message: &str) -> Response {
+    (
+        status,
+        Json(RateLimitErrorResponse {
+            error: error.to_string(),
+            response_message: response_message.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, outcome: &RateLimitOutcome) {
+    for (name, value) in [
+        (RATELIMIT_LIMIT, outcome.limit.to_string()),
+        (RATELIMIT_REMAINING, outcome.remaining.to_string()),
+        (RATELIMIT_RESET, outcome.reset_in_seconds.to_string()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+fn too_many_requests(outcome: &RateLimitOutcome) -> Response {
+    let mut response = rejection(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too Many Requests",
+        "Too many requests, try again later",
+    );
+    insert_rate_limit_headers(response.headers_mut(), outcome);
+    response.headers_mut().insert(
+        RETRY_AFTER,
+        HeaderValue::from(outcome.retry_after_in_seconds),
+    );
+    response
+}
+
+/// The account a request targets: the email in its JSON body on routes with a rule of
+/// their own, otherwise the user behind a valid bearer access token.
+///
+/// The body is read to find the email, so the request is handed back rebuilt.
+async fn targeted_account(
+    state: &AppState,
+    route: RateLimitedRoute,
+    req: Request,
+) -> Result<(Request, Option<String>), Response> {
+    let (req, email) = if route == RateLimitedRoute::Default {
+        (req, None)
+    } else {
+        let (parts, body) = req.into_parts();
+        let bytes = to_bytes(body, MAX_INSPECTED_BODY_BYTES)
+            .await
+            .map_err(|e| {
+                error!(
+                    "RATE LIMIT MIDDLEWARE: FAILED TO READ THE REQUEST BODY: {}",
+                    e
+                );
+
+                rejection(
+                    StatusCode::BAD_REQUEST,
+                    "Bad Request",
+                    "Failed to read the request body",
+                )
+            })?;
+
+        let email = serde_json::from_slice::<TargetedEmail>(&bytes)
+            .ok()
+            .and_then(|targeted| targeted.email)
+            .map(|email| email.trim().to_lowercase())
+            .filter(|email| !email.is_empty());
+
+        (Request::from_parts(parts, Body::from(bytes)), email)
+    };
+
+    if let Some(email) = email {
+        return Ok((req, Some(format!("email:{}", email))));
+    }
+
+    let user_id = extract_bearer_token(req.headers())
+        .and_then(|token| verify_access_token(token, &state.config, &state.jwt_keys).ok())
+        .map(|claims| claims.id);
+
+    Ok((req, user_id.map(|id| format!("user:{}", id))))
+}
+
+/// Meters requests with token buckets, one per client IP and one per targeted account,
+/// following the rule of the route in the `[rate_limit]` section.
+///
+/// Refuses with `429`, `Retry-After` and `RateLimit-*` headers once either bucket is empty.
+/// Allowed responses carry the `RateLimit-*` headers of the emptier bucket. Requests go
+/// through unmetered when the store fails, so an outage of the store is not an outage of
+/// the service.
+pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
+    client: ClientMetadata,
+    req: Request,
+    next: Next,
+) -> Response {
+    let default_rate_limit = RateLimitSection::default();
+    let rate_limit = state
+        .config
+        .rate_limit
+        .as_ref()
+        .unwrap_or(&default_rate_limit);
+
+    let route = RateLimitedRoute::from_path(req.uri().path());
+    let rule = route.rule(rate_limit);
+
+    let (req, account) = match targeted_account(&state, route, req).await {
+        Ok(targeted) => targeted,
+        Err(rejection) => return rejection,
+    };
+
+    // Accounts are keyed by digest, so emails are not kept in the store
+    let keys = client
+        .ip_address
+        .map(|ip| format!("{}:ip:{}", route.name(), ip))
+        .into_iter()
+        .chain(account.map(|account| {
+            format!(
+                "{}:account:{:x}",
+                route.name(),
+                Sha256::digest(account.as_bytes())
+            )
+        }));
+
+    let mut tightest: Option<RateLimitOutcome> = None;
+    for key in keys {
+        let outcome = match state.rate_limit_store.take(&key, rule).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                error!("RATE LIMIT MIDDLEWARE: STORE ERROR: {}", e);
+                continue;
+            }
+        };
+
+        if !outcome.allowed {
+            return too_many_requests(&outcome);
+        }
+
+        if tightest.is_none_or(|tightest| outcome.remaining < tightest.remaining) {
+            tightest = Some(outcome);
+        }
+    }
+
+    let mut response = next.run(req).await;
+    if let Some(outcome) = tightest {
+        insert_rate_limit_headers(response.headers_mut(), &outcome);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_are_matched_to_their_rule() {
+        for path in ["/api/v1/auth/login", "/api/v1/auth/login/mfa"] {
+            assert_eq!(RateLimitedRoute::from_path(path), RateLimitedRoute::Login);
+        }
+        assert_eq!(
+            RateLimitedRoute::from_path("/api/v1/auth/register"),
+            RateLimitedRoute::Register
+        );
+        for path in [
+            "/api/v1/auth/forgot-password",
+            "/api/v1/auth/reset-password",
+            "/api/v1/auth/verify-email",
+            "/api/v1/auth/resend-verification",
+            "/api/v1/auth/phone/send-code",
+            "/api/v1/auth/phone/verify",
+            "/api/v1/auth/mfa/totp/enroll",
+            "/api/v1/auth/mfa/totp/confirm",
+            "/api/v1/auth/mfa/totp/disable",
+            "/api/v1/auth/mfa/recovery-codes",
+        ] {
+            assert_eq!(
+                RateLimitedRoute::from_path(path),
+                RateLimitedRoute::OneTimePassword
+            );
+        }
+        for path in ["/api/v1/auth/sessions", "/.well-known/jwks.json", "/login"] {
+            assert_eq!(RateLimitedRoute::from_path(path), RateLimitedRoute::Default);
+        }
+    }
+
+    #[test]
+    fn test_too_many_requests_headers() {
+        let response = too_many_requests(&RateLimitOutcome {
+            allowed: false,
+            limit: 5,
+            remaining: 0,
+            reset_in_seconds: 60,
+            retry_after_in_seconds: 12,
+        });
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let headers = response.headers();
+        assert_eq!(headers[RETRY_AFTER], "12");
+        assert_eq!(headers["ratelimit-limit"], "5");
+        assert_eq!(headers["ratelimit-remaining"], "0");
+        assert_eq!(headers["ratelimit-reset"], "60");
+    }
+}
@@ -0,0 +1,38 @@
+use crate::AppState;
+use crate::middlewares::access_middleware::AuthenticatedUser;
+use crate::utils::client_metadata::ClientMetadata;
+use crate::utils::session_handler::touch_session;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tracing::error;
+
+// ============================================================================
+// Sessions Middleware
+// ============================================================================
+
+/// Keeps the `last_seen` time and IP address of the caller's session current, so the
+/// session list reflects every authenticated request rather than only token refreshes.
+///
+/// Rejects unauthenticated requests like [`AuthenticatedUser`] does. A failure to record
+/// the activity is logged and does not fail the request.
+pub async fn sessions_middleware(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    client: ClientMetadata,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Some(session_id) = user.session_id
+        && let Err(e) = touch_session(&state.db, session_id, &client).await
+    {
+        error!("FAILED TO RECORD SESSION ACTIVITY: {}", e);
+    }
+
+    // Spares the handler a second verification when the access middleware is off
+    req.extensions_mut().insert(user);
+
+    next.run(req).await
+}
@@ -0,0 +1,101 @@
+use crate::rate_limit::{RateLimitError, RateLimitOutcome, RateLimitStore, refill};
+use crate::utils::load_config::RateLimitRule;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full ones are swept away. A full bucket is the same as none.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    full_at: Instant,
+}
+
+/// Keeps the buckets in this process. Limits are per instance and reset on restart.
+#[derive(Debug, Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn take(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<RateLimitOutcome, RateLimitError> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if buckets.len() >= SWEEP_THRESHOLD && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let tokens = buckets.get(key).map_or(f64::from(rule.burst), |bucket| {
+            refill(
+                bucket.tokens,
+                now.duration_since(bucket.updated_at).as_secs_f64(),
+                rule,
+            )
+        });
+
+        let allowed = tokens >= 1.0;
+        let tokens = if allowed { tokens - 1.0 } else { tokens };
+        let outcome = RateLimitOutcome::new(allowed, tokens, rule);
+
+        buckets.insert(
+            key.to_string(),
+            Bucket {
+                tokens,
+                updated_at: now,
+                full_at: now + Duration::from_secs(outcome.reset_in_seconds),
+            },
+        );
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULE: RateLimitRule = RateLimitRule {
+        burst: 2,
+        per_minute: 1,
+    };
+
+    #[tokio::test]
+    async fn test_bucket_refuses_once_the_burst_is_used_up() {
+        let store = MemoryRateLimitStore::default();
+
+        let first = store.take("login:ip:203.0.113.7", &RULE).await.unwrap();
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+
+        let second = store.take("login:ip:203.0.113.7", &RULE).await.unwrap();
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let third = store.take("login:ip:203.0.113.7", &RULE).await.unwrap();
+        assert!(!third.allowed);
+        assert!(third.retry_after_in_seconds > 0 && third.retry_after_in_seconds <= 60);
+    }
+
+    #[tokio::test]
+    async fn test_buckets_are_independent() {
+        let store = MemoryRateLimitStore::default();
+
+        for _ in 0..2 {
+            store.take("login:ip:203.0.113.7", &RULE).await.unwrap();
+        }
+
+        let other = store.take("login:ip:198.51.100.2", &RULE).await.unwrap();
+        assert!(other.allowed);
+        assert_eq!(store.buckets.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,156 @@
+//! # Rate Limiting
+//!
+//! Requests are metered with token buckets: a bucket holds up to `burst` tokens, every
+//! request takes one, and tokens flow back at `per_minute` a minute. A request that finds
+//! its bucket empty is refused until a token is back.
+//!
+//! Buckets are kept behind the [`RateLimitStore`] trait, picked by the `[rate_limit]`
+//! config section:
+//! - `memory`: in this process, the default. Each instance counts on its own.
+//! - `postgres`: in the `rate_limit_buckets` table, shared by every instance.
+
+use crate::utils::load_config::{RateLimitRule, RateLimitSection};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::fmt::Debug;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub mod memory_rate_limit_store;
+pub mod postgres_rate_limit_store;
+
+pub use memory_rate_limit_store::MemoryRateLimitStore;
+pub use postgres_rate_limit_store::PostgresRateLimitStore;
+
+/// Values accepted for `rate_limit.store`.
+pub const SUPPORTED_RATE_LIMIT_STORES: [&str; 2] = ["memory", "postgres"];
+
+#[derive(Debug, Error)]
+pub enum RateLimitError {
+    #[error("Rate limit store error: {0}")]
+    Store(#[from] sqlx::Error),
+    #[error("Unsupported rate limit store: {0}")]
+    UnsupportedStore(String),
+}
+
+/// The state of a bucket after a request drew from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimitOutcome {
+    /// Whether the bucket held a token for the request.
+    pub allowed: bool,
+    /// The `burst` of the bucket.
+    pub limit: u32,
+    /// Whole tokens left.
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_in_seconds: u64,
+    /// Seconds until a refused request may be retried, 0 when allowed.
+    pub retry_after_in_seconds: u64,
+}
+
+impl RateLimitOutcome {
+    /// The outcome of a draw that left `tokens` in a bucket following `rule`.
+    pub fn new(allowed: bool, tokens: f64, rule: &RateLimitRule) -> Self {
+        let retry_after_in_seconds = if allowed {
+            0
+        } else {
+            seconds_to_refill(1.0 - tokens, rule).max(1)
+        };
+
+        RateLimitOutcome {
+            allowed,
+            limit: rule.burst,
+            remaining: tokens.max(0.0).floor() as u32,
+            reset_in_seconds: seconds_to_refill(f64::from(rule.burst) - tokens, rule),
+            retry_after_in_seconds,
+        }
+    }
+}
+
+/// The tokens in a bucket that held `tokens` `elapsed_in_seconds` ago.
+pub fn refill(tokens: f64, elapsed_in_seconds: f64, rule: &RateLimitRule) -> f64 {
+    let refilled = elapsed_in_seconds.max(0.0) * f64::from(rule.per_minute) / 60.0;
+
+    (tokens + refilled).min(f64::from(rule.burst))
+}
+
+/// Seconds, rounded up, for `missing` tokens to flow back into a bucket.
+fn seconds_to_refill(missing: f64, rule: &RateLimitRule) -> u64 {
+    if missing <= 0.0 || rule.per_minute == 0 {
+        return 0;
+    }
+
+    (missing * 60.0 / f64::from(rule.per_minute)).ceil() as u64
+}
+
+/// Keeps the token buckets of the rate limiter.
+#[async_trait]
+pub trait RateLimitStore: Debug + Send + Sync {
+    /// Takes a token from the bucket under `key`, which starts out full.
+    async fn take(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<RateLimitOutcome, RateLimitError>;
+}
+
+/// Builds the store selected by the `[rate_limit]` section, in memory without one.
+pub fn rate_limit_store_from_config(
+    rate_limit: Option<&RateLimitSection>,
+    db: &PgPool,
+) -> Result<Arc<dyn RateLimitStore>, RateLimitError> {
+    match rate_limit
+        .map(|rate_limit| rate_limit.store.as_str())
+        .unwrap_or("memory")
+    {
+        "memory" => Ok(Arc::new(MemoryRateLimitStore::default())),
+        "postgres" => Ok(Arc::new(PostgresRateLimitStore::new(db.clone()))),
+        other => Err(RateLimitError::UnsupportedStore(other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULE: RateLimitRule = RateLimitRule {
+        burst: 4,
+        per_minute: 30,
+    };
+
+    #[test]
+    fn test_refill_is_capped_at_the_burst() {
+        assert_eq!(refill(0.0, 2.0, &RULE), 1.0);
+        assert_eq!(refill(1.5, 0.0, &RULE), 1.5);
+        assert_eq!(refill(3.0, 60.0, &RULE), 4.0);
+        // A clock going backwards refills nothing
+        assert_eq!(refill(1.0, -5.0, &RULE), 1.0);
+    }
+
+    #[test]
+    fn test_outcome_of_an_allowed_draw() {
+        let outcome = RateLimitOutcome::new(true, 2.5, &RULE);
+
+        assert_eq!(
+            outcome,
+            RateLimitOutcome {
+                allowed: true,
+                limit: 4,
+                remaining: 2,
+                reset_in_seconds: 3,
+                retry_after_in_seconds: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_outcome_of_a_refused_draw() {
+        let outcome = RateLimitOutcome::new(false, 0.25, &RULE);
+
+        assert!(!outcome.allowed);
+        assert_eq!(outcome.remaining, 0);
+        // 0.75 tokens at half a token a second
+        assert_eq!(outcome.retry_after_in_seconds, 2);
+        assert_eq!(outcome.reset_in_seconds, 8);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::rate_limit::{RateLimitError, RateLimitOutcome, RateLimitStore};
+use crate::utils::load_config::RateLimitRule;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How often idle buckets are deleted.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Buckets untouched for this long are deleted. Any sensible rule has refilled them by then.
+const IDLE_BUCKET_LIFETIME_IN_HOURS: i32 = 24;
+
+/// Keeps the buckets in the `rate_limit_buckets` table, so every instance sharing the
+/// database enforces the same limits.
+#[derive(Debug)]
+pub struct PostgresRateLimitStore {
+    db: PgPool,
+    last_swept_at: Mutex<Instant>,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(db: PgPool) -> Self {
+        PostgresRateLimitStore {
+            db,
+            last_swept_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Deletes idle buckets, at most once every [`SWEEP_INTERVAL`] per instance.
+    async fn sweep_if_due(&self) -> Result<(), sqlx::Error> {
+        {
+            let mut last_swept_at = self
+                .last_swept_at
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if last_swept_at.elapsed() < SWEEP_INTERVAL {
+                return Ok(());
+            }
+            *last_swept_at = Instant::now();
+        }
+
+        sqlx::query(
+            "DELETE FROM rate_limit_buckets WHERE updated_at < NOW() - make_interval(hours => $1)",
+        )
+        .bind(IDLE_BUCKET_LIFETIME_IN_HOURS)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn take(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<RateLimitOutcome, RateLimitError> {
+        self.sweep_if_due().await?;
+
+        let burst = f64::from(rule.burst);
+        let per_minute = f64::from(rule.per_minute);
+
+        // A new bucket starts out full. An existing one is refilled and only drawn from
+        // when that gives it a whole token; otherwise the row is left alone
+        let drawn: Option<f64> = sqlx::query_scalar(
+            r#"
+            INSERT INTO rate_limit_buckets AS bucket (key, tokens, updated_at)
+            VALUES ($1, $2 - 1, NOW())
+            ON CONFLICT (key) DO UPDATE
+            SET
+                tokens = LEAST($2, bucket.tokens
+                    + EXTRACT(EPOCH FROM NOW() - bucket.updated_at)::float8 * $3 / 60) - 1,
+                updated_at = NOW()
+            WHERE LEAST($2, bucket.tokens
+                + EXTRACT(EPOCH FROM NOW() - bucket.updated_at)::float8 * $3 / 60) >= 1
+            RETURNING tokens
+            "#,
+        )
+        .bind(key)
+        .bind(burst)
+        .bind(per_minute)
+        .fetch_optional(&self.db)
+        .await?;
+
+        if let Some(tokens) = drawn {
+            return Ok(RateLimitOutcome::new(true, tokens, rule));
+        }
+
+        let tokens: Option<f64> = sqlx::query_scalar(
+            r#"
+            SELECT LEAST($2, tokens + EXTRACT(EPOCH FROM NOW() - updated_at)::float8 * $3 / 60)
+            FROM rate_limit_buckets
+            WHERE key = $1
+            "#,
+        )
+        .bind(key)
+        .bind(burst)
+        .bind(per_minute)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(RateLimitOutcome::new(false, tokens.unwrap_or(0.0), rule))
+    }
+}
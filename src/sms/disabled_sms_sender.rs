@@ -0,0 +1,15 @@
+use crate::sms::{SmsError, SmsMessage, SmsSender};
+use async_trait::async_trait;
+
+/// Refuses every message, for deployments without an SMS provider.
+///
+/// Phone verification is unavailable with it, but codes never end up in the logs.
+#[derive(Debug)]
+pub struct DisabledSmsSender;
+
+#[async_trait]
+impl SmsSender for DisabledSmsSender {
+    async fn send(&self, _message: &SmsMessage) -> Result<(), SmsError> {
+        Err(SmsError::Disabled)
+    }
+}
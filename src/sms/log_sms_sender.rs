@@ -0,0 +1,18 @@
+use crate::sms::{SmsError, SmsMessage, SmsSender};
+use async_trait::async_trait;
+use tracing::info;
+
+/// Logs every message instead of delivering it.
+///
+/// Codes end up in the logs, so this is only meant for local development.
+#[derive(Debug)]
+pub struct LogSmsSender;
+
+#[async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, message: &SmsMessage) -> Result<(), SmsError> {
+        info!("SMS TO {}: {}", message.to, message.body);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use crate::sms::{SmsError, SmsMessage, SmsSender};
+use async_trait::async_trait;
+use std::sync::{Mutex, PoisonError};
+
+/// Keeps every message in memory instead of delivering it, so tests can read them back.
+#[derive(Debug, Default)]
+pub struct MemorySmsSender {
+    sent: Mutex<Vec<SmsMessage>>,
+}
+
+impl MemorySmsSender {
+    /// Every message sent so far, oldest first.
+    pub fn sent(&self) -> Vec<SmsMessage> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The most recent message sent to `to`, if any.
+    pub fn last_sent_to(&self, to: &str) -> Option<SmsMessage> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .rev()
+            .find(|message| message.to == to)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl SmsSender for MemorySmsSender {
+    async fn send(&self, message: &SmsMessage) -> Result<(), SmsError> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(message.clone());
+
+        Ok(())
+    }
+}
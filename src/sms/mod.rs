@@ -0,0 +1,122 @@
+//! # Outbound SMS
+//!
+//! Every text message the service sends goes through the [`SmsSender`] trait, so the
+//! delivery mechanism is picked by the `[sms]` config section:
+//! - `log`: logs messages instead of delivering them, the default until a provider is
+//!   wired in.
+//! - `memory`: keeps messages in memory, for tests.
+//! - `disabled`: refuses every message, for production until a provider is wired in.
+
+use crate::utils::load_config::SmsSection;
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub mod disabled_sms_sender;
+pub mod log_sms_sender;
+pub mod memory_sms_sender;
+
+pub use disabled_sms_sender::DisabledSmsSender;
+pub use log_sms_sender::LogSmsSender;
+pub use memory_sms_sender::MemorySmsSender;
+
+/// Values accepted for `sms.transport`.
+pub const SUPPORTED_SMS_TRANSPORTS: [&str; 3] = ["log", "memory", "disabled"];
+
+const PRODUCT_NAME: &str = "Krabby Chat";
+
+#[derive(Debug, Error)]
+pub enum SmsError {
+    #[error("Failed to deliver SMS: {0}")]
+    Delivery(String),
+    #[error("Sending SMS is disabled")]
+    Disabled,
+    #[error("Unsupported SMS transport: {0}")]
+    UnsupportedTransport(String),
+}
+
+/// A plain-text message to a phone number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmsMessage {
+    pub to: String,
+    pub body: String,
+}
+
+impl SmsMessage {
+    /// The message carrying a phone verification code.
+    pub fn phone_verification(to: &str, code: &str, lifetime_in_minutes: u64) -> Self {
+        SmsMessage {
+            to: to.to_string(),
+            body: format!(
+                "{} is your {} verification code. It expires in {} minutes. Never share it with anyone.",
+                code, PRODUCT_NAME, lifetime_in_minutes
+            ),
+        }
+    }
+
+    /// The message telling the owner of a phone number that someone tried to register
+    /// with it. Sent instead of an error when registration must not reveal which numbers
+    /// are taken.
+    pub fn account_already_exists(to: &str) -> Self {
+        SmsMessage {
+            to: to.to_string(),
+            body: format!(
+                "Someone tried to create a {} account with this phone number, which already has one. If it was not you, you can ignore this message.",
+                PRODUCT_NAME
+            ),
+        }
+    }
+}
+
+/// Delivers text messages.
+#[async_trait]
+pub trait SmsSender: Debug + Send + Sync {
+    async fn send(&self, message: &SmsMessage) -> Result<(), SmsError>;
+}
+
+/// Builds the sender selected by the `[sms]` section, logging messages without one.
+pub fn sms_sender_from_config(sms: Option<&SmsSection>) -> Result<Arc<dyn SmsSender>, SmsError> {
+    match sms.map(|sms| sms.transport.as_str()).unwrap_or("log") {
+        "log" => Ok(Arc::new(LogSmsSender)),
+        "memory" => Ok(Arc::new(MemorySmsSender::default())),
+        "disabled" => Ok(Arc::new(DisabledSmsSender)),
+        other => Err(SmsError::UnsupportedTransport(other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phone_verification_message() {
+        let message = SmsMessage::phone_verification("+2348012345678", "042137", 10);
+
+        assert_eq!(message.to, "+2348012345678");
+        assert!(message.body.starts_with("042137 is your Krabby Chat"));
+        assert!(message.body.contains("10 minutes"));
+    }
+
+    #[test]
+    fn test_account_already_exists_message() {
+        let message = SmsMessage::account_already_exists("+2348012345678");
+
+        assert_eq!(message.to, "+2348012345678");
+        assert!(message.body.contains("Krabby Chat account"));
+    }
+
+    #[test]
+    fn test_sms_sender_from_config() {
+        assert!(sms_sender_from_config(None).is_ok());
+
+        let unknown = SmsSection {
+            transport: "carrier-pigeon".to_string(),
+            ..SmsSection::default()
+        };
+        assert!(matches!(
+            sms_sender_from_config(Some(&unknown)),
+            Err(SmsError::UnsupportedTransport(_))
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+//! # Client Metadata
+//!
+//! This module provides an extractor describing the device behind a request
+//! (user agent and IP address), recorded on sessions at login.
+
+use crate::AppState;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::{HeaderMap, header::USER_AGENT, request::Parts};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+/// Longest user agent kept, matching `sessions.user_agent`.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// The user agent and IP address of the client that sent a request.
+#[derive(Clone, Debug, Default)]
+pub struct ClientMetadata {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Returns the originating client IP announced by a reverse proxy, if any.
+///
+/// `X-Forwarded-For` takes precedence over `X-Real-IP`. Only its last entry is used: the one
+/// appended by the trusted proxy, since the client can put anything before it.
+pub fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    let forwarded_for = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next());
+
+    let real_ip = headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok());
+
+    forwarded_for
+        .or(real_ip)
+        .and_then(|ip| ip.trim().parse().ok())
+}
+
+impl FromRequestParts<AppState> for ClientMetadata {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        // Proxy headers can be forged by any client, so they are only honoured when configured
+        let trust_proxy_headers = state
+            .config
+            .server
+            .as_ref()
+            .is_some_and(|server| server.trust_proxy_headers);
+
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        let ip_address = trust_proxy_headers
+            .then(|| forwarded_ip(&parts.headers))
+            .flatten()
+            .or(peer_ip)
+            .map(|ip| ip.to_string());
+
+        Ok(ClientMetadata {
+            user_agent,
+            ip_address,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_forwarded_ip_prefers_last_forwarded_for_entry() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("10.0.0.1, 203.0.113.7"),
+        );
+        headers.insert("x-real-ip", HeaderValue::from_static("198.51.100.2"));
+
+        assert_eq!(forwarded_ip(&headers), "203.0.113.7".parse().ok());
+    }
+
+    #[test]
+    fn test_forwarded_ip_falls_back_to_real_ip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", HeaderValue::from_static("198.51.100.2"));
+
+        assert_eq!(forwarded_ip(&headers), "198.51.100.2".parse().ok());
+    }
+
+    #[test]
+    fn test_forwarded_ip_ignores_garbage() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(forwarded_ip(&headers), None);
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("not-an-ip"));
+        assert_eq!(forwarded_ip(&headers), None);
+    }
+}
@@ -1,19 +1,20 @@
+//! # Auth Cookie
+//!
+//! Browser clients keep their session in an `HttpOnly` cookie holding the session's
+//! current refresh token, so they never handle tokens in JavaScript. The cookie is read
+//! by the `AuthenticatedUser` extractor and by the refresh endpoint, and is replaced on
+//! every refresh.
+
 use crate::utils::load_config::AppConfig;
+use axum::http::{HeaderMap, header::COOKIE};
 use time;
 use tower_cookies::{Cookie, Cookies};
 
-pub async fn deploy_auth_cookie(cookies: Cookies, cookie_value: String, config: &AppConfig) {
-    // let cookie = Cookie::build(("name", "value"))
-    //     .domain("www.rustychat.com")
-    //     .path("/")
-    //     .secure(true)
-    //     .http_only(true);
-    //
-    // jar.add(cookie);
-    // // jar.remove(Cookie::build("name").path("/"));
+/// Name of the cookie holding the refresh token.
+pub const AUTH_COOKIE_NAME: &str = "rusty_chat_auth_cookie";
 
-    // Create a basic cookie
-    let mut cookie = Cookie::new("rusty_chat_auth_cookie", cookie_value);
+pub async fn deploy_auth_cookie(cookies: Cookies, refresh_token: String, config: &AppConfig) {
+    let mut cookie = Cookie::new(AUTH_COOKIE_NAME, refresh_token);
 
     let auth = config
         .auth
@@ -26,12 +27,64 @@ pub async fn deploy_auth_cookie(cookies: Cookies, cookie_value: String, config:
     cookie.set_http_only(true);
     // Only set secure in non-development or if explicitly needed
     cookie.set_secure(!is_dev);
-    cookie.set_same_site(tower_cookies::cookie::SameSite::Lax);
+    // The cookie authenticates requests on its own, so it must never ride along cross-site requests
+    cookie.set_same_site(tower_cookies::cookie::SameSite::Strict);
 
-    // Optional: set expiration from config
+    // Lives exactly as long as the refresh token it holds
     cookie.set_max_age(time::Duration::hours(
         auth.jwt_refresh_expiration_time_in_hours as i64,
     ));
 
     cookies.add(cookie);
 }
+
+/// Tells the browser to drop the auth cookie.
+pub fn remove_auth_cookie(cookies: &Cookies) {
+    let mut cookie = Cookie::new(AUTH_COOKIE_NAME, "");
+    cookie.set_path("/");
+    cookie.set_max_age(time::Duration::ZERO);
+    cookies.remove(cookie);
+}
+
+/// Returns the refresh token held by the auth cookie of a request, if any.
+///
+/// Reads the `Cookie` header directly, so it also works outside the cookie manager layer.
+pub fn auth_cookie_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(Cookie::split_parse)
+        .filter_map(Result::ok)
+        .find(|cookie| cookie.name() == AUTH_COOKIE_NAME && !cookie.value().is_empty())
+        .map(|cookie| cookie.value().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_auth_cookie_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(auth_cookie_from_headers(&headers), None);
+
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_static("theme=dark; rusty_chat_auth_cookie=abc.def.ghi"),
+        );
+        assert_eq!(
+            auth_cookie_from_headers(&headers).as_deref(),
+            Some("abc.def.ghi")
+        );
+    }
+
+    #[test]
+    fn test_auth_cookie_from_headers_ignores_empty_value() {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static("rusty_chat_auth_cookie="));
+
+        assert_eq!(auth_cookie_from_headers(&headers), None);
+    }
+}
@@ -0,0 +1,65 @@
+//! # Email Verification
+//!
+//! A user proves ownership of their email address by presenting an email-verification
+//! token sent to it. The token carries the address it was issued for, so it stops
+//! working once the user's email changes.
+
+use crate::mailer::{MailError, Mailer, templates};
+use crate::utils::generate_tokens::{JwtError, User, generate_email_verification_token};
+use crate::utils::jwt_keys::JwtKeys;
+use crate::utils::load_config::AppConfig;
+use chrono::NaiveDateTime;
+use sqlx::PgExecutor;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EmailVerificationError {
+    #[error(transparent)]
+    Token(#[from] JwtError),
+    #[error(transparent)]
+    Mail(#[from] MailError),
+}
+
+/// Issues an email-verification token for `user` and mails it to their address.
+pub async fn send_email_verification(
+    user: &User,
+    config: &AppConfig,
+    keys: &JwtKeys,
+    mailer: &dyn Mailer,
+) -> Result<(), EmailVerificationError> {
+    let verification = generate_email_verification_token(user, config, keys)?;
+
+    mailer
+        .send(&templates::email_verification(
+            config,
+            &user.email,
+            &verification.token,
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// Marks `email` as verified for the user, keeping the first verification time.
+///
+/// Returns `None` when the user no longer has that email address.
+pub async fn mark_email_verified<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    email: &str,
+) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        UPDATE users
+        SET
+            email_verified_at = COALESCE(email_verified_at, NOW()),
+            updated_at = NOW()
+        WHERE id = $1 AND email = $2
+        RETURNING email_verified_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(email)
+    .fetch_optional(executor)
+    .await
+}
@@ -1,49 +1,120 @@
 //! # Token Generation
 //!
-//! This module handles the creation of JSON Web Tokens (JWTs) for authentication,
-//! including access tokens, refresh tokens, and one-time passwords (OTPs).
-//! It also generates specialized authentication cookies.
+//! This module handles the creation of JSON Web Tokens (JWTs) for authentication:
+//! access/refresh token pairs, one-time passwords (OTPs), email-verification and
+//! MFA-pending tokens. Every kind has its own `generate_*` function and return type, and
+//! its own `verify_*` function that rejects tokens of any other kind.
 
-use crate::utils::hashing_handler::hashing_handler;
-use crate::utils::load_config::AppConfig;
+use crate::utils::jwt_keys::JwtKeys;
+use crate::utils::load_config::{AppConfig, AuthSection};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{EncodingKey, Header, encode};
+use jsonwebtoken::Validation;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 
 #[derive(Debug, Error)]
 pub enum JwtError {
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
-    #[error("Hashing error: {0}")]
-    Hashing(argon2::password_hash::Error),
     #[error("Auth configuration is missing")]
     MissingAuth,
-    #[error("Invalid token type: {0}")]
-    InvalidTokenType(String),
     #[error("Expiration calculation failed: {0}")]
     ExpirationCalculation(String),
+    #[error("Unsupported JWT algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("{0} is not configured")]
+    MissingKeyPath(&'static str),
+    #[error("Failed to read key file {0}: {1}")]
+    KeyFile(String, std::io::Error),
+    #[error("Token was signed with an unknown key")]
+    UnknownKey,
+    #[error("Expected {expected} token, got {actual} token")]
+    WrongTokenKind {
+        expected: TokenKind,
+        actual: TokenKind,
+    },
 }
 
-impl From<argon2::password_hash::Error> for JwtError {
-    fn from(err: argon2::password_hash::Error) -> Self {
-        JwtError::Hashing(err)
+/// The kinds of token this service issues, carried in the `token_use` claim.
+/// Verification rejects tokens of any other kind, so e.g. a refresh token can
+/// never pass as an access token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+    OneTimePassword,
+    EmailVerification,
+    MfaPending,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Access => write!(f, "access"),
+            TokenKind::Refresh => write!(f, "refresh"),
+            TokenKind::OneTimePassword => write!(f, "one_time_password"),
+            TokenKind::EmailVerification => write!(f, "email_verification"),
+            TokenKind::MfaPending => write!(f, "mfa_pending"),
+        }
     }
 }
 
 /// JWT Claims structure.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    /// Issuer, `auth.jwt_issuer`.
+    pub iss: String,
+    /// Audience, `auth.jwt_audience`.
+    pub aud: String,
+    /// Subject: the user ID as a string.
+    pub sub: String,
     /// User ID.
     pub id: i64,
     /// User email address.
     pub email: String,
+    /// Session (refresh-token family) the token belongs to. Only set on access and refresh tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+    /// Unique token identifier, so two tokens minted in the same second never collide.
+    pub jti: String,
+    /// What the token may be used for.
+    pub token_use: TokenKind,
     /// Expiration timestamp (seconds since epoch).
     pub exp: usize,
+    /// Not-before timestamp (seconds since epoch).
+    pub nbf: usize,
     /// Issued-at timestamp (seconds since epoch).
     pub iat: usize,
 }
 
+impl Claims {
+    fn new(
+        auth: &AuthSection,
+        user: &User,
+        session_id: Option<Uuid>,
+        token_use: TokenKind,
+        exp: usize,
+    ) -> Self {
+        let now = Utc::now().timestamp() as usize;
+
+        Claims {
+            iss: auth.jwt_issuer.clone(),
+            aud: auth.jwt_audience.clone(),
+            sub: user.id.to_string(),
+            id: user.id,
+            email: user.email.clone(),
+            sid: session_id,
+            token_use,
+            jti: Uuid::new_v4().to_string(),
+            exp,
+            nbf: now,
+            iat: now,
+        }
+    }
+}
+
 /// Simplified User structure for token generation.
 #[derive(Clone, Debug)]
 pub struct User {
@@ -51,109 +122,225 @@ pub struct User {
     pub id: i64,
     /// User email address.
     pub email: String,
+    /// Session to issue auth tokens for. `None` starts a new session.
+    pub session_id: Option<Uuid>,
+}
+
+/// The access/refresh token pair of one session.
+#[derive(Debug)]
+pub struct AuthTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub session_id: Uuid,
+}
+
+/// A one-time-password token.
+#[derive(Debug)]
+pub struct OneTimePasswordToken {
+    pub token: String,
+    /// Expiration timestamp (seconds since epoch).
+    pub expires_at: usize,
 }
 
-/// Container for generated tokens and cookies.
-#[derive(Debug, Serialize)]
-pub struct Tokens {
-    pub access_token: Option<String>,
-    pub refresh_token: Option<String>,
-    pub one_time_password_token: Option<String>,
-    pub auth_cookie: Option<String>,
+/// A token proving ownership of the email address it was sent to.
+#[derive(Debug)]
+pub struct EmailVerificationToken {
+    pub token: String,
+    /// Expiration timestamp (seconds since epoch).
+    pub expires_at: usize,
+}
+
+/// A token proving the password step of a login, exchanged for auth tokens together with a
+/// second factor.
+#[derive(Debug)]
+pub struct MfaPendingToken {
+    pub token: String,
+    /// Expiration timestamp (seconds since epoch).
+    pub expires_at: usize,
 }
 
-/// Generates tokens based on the requested `token_type`.
+/// Generates the access/refresh token pair of a session.
 ///
 /// # Arguments
-/// - `token_type`: Either `"auth"` for access/refresh tokens or `"one_time_password"` for OTP.
-/// - `user`: The user for whom tokens are being generated.
-/// - `config`: Application configuration for JWT secrets and lifetimes.
-pub async fn generate_tokens(
-    token_type: &str,
-    user: User,
+/// - `user`: The user for whom tokens are being generated. The tokens are bound to
+///   `user.session_id`, or to a new session when it is `None`.
+/// - `config`: Application configuration for token lifetimes.
+/// - `keys`: The keys tokens are signed with.
+pub fn generate_auth_tokens(
+    user: &User,
     config: &AppConfig,
-) -> Result<Tokens, JwtError> {
+    keys: &JwtKeys,
+) -> Result<AuthTokens, JwtError> {
     let auth = config.auth.as_ref().ok_or(JwtError::MissingAuth)?;
 
-    let jwt_secret = &auth.jwt_secret;
-    let access_expiry = auth.jwt_access_expiration_time_in_hours;
-    let session_expiry = auth.jwt_refresh_expiration_time_in_hours;
-    let otp_expiry = auth.jwt_one_time_password_lifetime_in_minutes;
-
     let now = Utc::now();
+    let access_token_expiration =
+        calculate_expiration(now, auth.jwt_access_expiration_time_in_hours, true)?;
+    let refresh_token_expiration =
+        calculate_expiration(now, auth.jwt_refresh_expiration_time_in_hours, true)?;
+
+    let session_id = user.session_id.unwrap_or_else(Uuid::new_v4);
+
+    let access_claims = Claims::new(
+        auth,
+        user,
+        Some(session_id),
+        TokenKind::Access,
+        access_token_expiration,
+    );
+
+    let access_token = keys.sign(&access_claims)?;
+
+    let refresh_claims = Claims::new(
+        auth,
+        user,
+        Some(session_id),
+        TokenKind::Refresh,
+        refresh_token_expiration,
+    );
+
+    let refresh_token = keys.sign(&refresh_claims)?;
+
+    Ok(AuthTokens {
+        access_token,
+        refresh_token,
+        session_id,
+    })
+}
 
-    let access_token_expiration = calculate_expiration(now, access_expiry, true)?;
-    let refresh_token_expiration = calculate_expiration(now, session_expiry, true)?;
-    let otp_token_expiration = calculate_expiration(now, otp_expiry, false)?;
-
-    match token_type {
-        "auth" => {
-            let access_claims = Claims {
-                id: user.id,
-                email: user.email.clone(),
-                exp: access_token_expiration,
-                iat: Utc::now().timestamp() as usize,
-            };
-
-            let access_token = encode(
-                &Header::default(),
-                &access_claims,
-                &EncodingKey::from_secret(jwt_secret.as_bytes()),
-            )?;
-
-            let refresh_claims = Claims {
-                id: user.id,
-                email: user.email.clone(),
-                exp: refresh_token_expiration,
-                iat: Utc::now().timestamp() as usize,
-            };
-
-            let refresh_token = encode(
-                &Header::default(),
-                &refresh_claims,
-                &EncodingKey::from_secret(jwt_secret.as_bytes()),
-            )?;
-
-            let auth_cookie_part_a = hashing_handler(user.email.as_str()).await?;
-            let auth_cookie_part_b = hashing_handler(jwt_secret).await?;
-
-            let auth_cookie = format!(
-                "rusty_chat____{}____{}",
-                auth_cookie_part_a, auth_cookie_part_b
-            );
-
-            Ok(Tokens {
-                access_token: Some(access_token),
-                refresh_token: Some(refresh_token),
-                one_time_password_token: None,
-                auth_cookie: Some(auth_cookie),
-            })
-        }
+/// Signs a token of `kind` that is not bound to a session, valid for `lifetime`.
+fn sign_standalone_token(
+    user: &User,
+    kind: TokenKind,
+    lifetime: (u64, bool),
+    auth: &AuthSection,
+    keys: &JwtKeys,
+) -> Result<(String, usize), JwtError> {
+    let (amount, is_hours) = lifetime;
+    let expires_at = calculate_expiration(Utc::now(), amount, is_hours)?;
+    let token = keys.sign(&Claims::new(auth, user, None, kind, expires_at))?;
+
+    Ok((token, expires_at))
+}
 
-        "one_time_password" => {
-            let otp_claims = Claims {
-                id: user.id,
-                email: user.email.clone(),
-                exp: otp_token_expiration,
-                iat: Utc::now().timestamp() as usize,
-            };
-
-            let otp_token = encode(
-                &Header::default(),
-                &otp_claims,
-                &EncodingKey::from_secret(jwt_secret.as_bytes()),
-            )?;
-
-            Ok(Tokens {
-                access_token: None,
-                refresh_token: None,
-                one_time_password_token: Some(otp_token),
-                auth_cookie: None,
-            })
-        }
+/// Generates a one-time-password token, valid for `auth.jwt_one_time_password_lifetime_in_minutes`.
+pub fn generate_one_time_password_token(
+    user: &User,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<OneTimePasswordToken, JwtError> {
+    let auth = config.auth.as_ref().ok_or(JwtError::MissingAuth)?;
+    let lifetime = (auth.jwt_one_time_password_lifetime_in_minutes, false);
+    let (token, expires_at) =
+        sign_standalone_token(user, TokenKind::OneTimePassword, lifetime, auth, keys)?;
+
+    Ok(OneTimePasswordToken { token, expires_at })
+}
+
+/// Generates an email-verification token, valid for `auth.jwt_email_verification_lifetime_in_hours`.
+pub fn generate_email_verification_token(
+    user: &User,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<EmailVerificationToken, JwtError> {
+    let auth = config.auth.as_ref().ok_or(JwtError::MissingAuth)?;
+    let lifetime = (auth.jwt_email_verification_lifetime_in_hours, true);
+    let (token, expires_at) =
+        sign_standalone_token(user, TokenKind::EmailVerification, lifetime, auth, keys)?;
+
+    Ok(EmailVerificationToken { token, expires_at })
+}
+
+/// Generates an MFA-pending token, valid for `auth.jwt_mfa_pending_lifetime_in_minutes`.
+pub fn generate_mfa_pending_token(
+    user: &User,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<MfaPendingToken, JwtError> {
+    let auth = config.auth.as_ref().ok_or(JwtError::MissingAuth)?;
+    let lifetime = (auth.jwt_mfa_pending_lifetime_in_minutes, false);
+    let (token, expires_at) =
+        sign_standalone_token(user, TokenKind::MfaPending, lifetime, auth, keys)?;
+
+    Ok(MfaPendingToken { token, expires_at })
+}
+
+/// Decodes a token issued by this module, checking its signature, expiry, not-before
+/// time, issuer, audience and kind.
+///
+/// The key is picked from the keyring by the token's `kid` header, so tokens signed before
+/// a rotation stay valid while their key is retired. Returns the token's claims, or an `Err`
+/// if the token is malformed, tampered with, expired, signed with an unknown key, meant for
+/// another issuer or audience, or not an `expected` token.
+fn verify_token(
+    token: &str,
+    expected: TokenKind,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<Claims, JwtError> {
+    let auth = config.auth.as_ref().ok_or(JwtError::MissingAuth)?;
+
+    let mut validation = Validation::default();
+    validation.set_issuer(&[&auth.jwt_issuer]);
+    validation.set_audience(&[&auth.jwt_audience]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
 
-        token_type => Err(JwtError::InvalidTokenType(token_type.to_string())),
+    let claims = keys.verify::<Claims>(token, &validation)?.claims;
+
+    if claims.token_use != expected {
+        return Err(JwtError::WrongTokenKind {
+            expected,
+            actual: claims.token_use,
+        });
     }
+
+    Ok(claims)
+}
+
+/// Verifies an access token. See [`verify_token`] for what is checked.
+pub fn verify_access_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<Claims, JwtError> {
+    verify_token(token, TokenKind::Access, config, keys)
+}
+
+/// Verifies a refresh token. See [`verify_token`] for what is checked.
+pub fn verify_refresh_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<Claims, JwtError> {
+    verify_token(token, TokenKind::Refresh, config, keys)
+}
+
+/// Verifies a one-time-password token. See [`verify_token`] for what is checked.
+pub fn verify_one_time_password_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<Claims, JwtError> {
+    verify_token(token, TokenKind::OneTimePassword, config, keys)
+}
+
+/// Verifies an email-verification token. See [`verify_token`] for what is checked.
+pub fn verify_email_verification_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<Claims, JwtError> {
+    verify_token(token, TokenKind::EmailVerification, config, keys)
+}
+
+/// Verifies an MFA-pending token. See [`verify_token`] for what is checked.
+pub fn verify_mfa_pending_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &JwtKeys,
+) -> Result<Claims, JwtError> {
+    verify_token(token, TokenKind::MfaPending, config, keys)
 }
 
 /// Safely calculates expiration timestamp.
@@ -196,6 +383,7 @@ mod tests {
                 allow_logging_middleware: true,
                 allow_request_timeout_middleware: true,
                 allow_admin_routes_protector_middleware: true,
+                allow_rate_limit_middleware: true,
             },
             observability: ObservabilitySection {
                 enable_tracing: false,
@@ -203,62 +391,213 @@ mod tests {
             },
             server: None,
             database: None,
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
             auth: Some(AuthSection {
                 jwt_secret: "test_secret".to_string(),
                 jwt_access_expiration_time_in_hours: 1,
                 jwt_refresh_expiration_time_in_hours: 24,
                 jwt_one_time_password_lifetime_in_minutes: 5,
+                jwt_email_verification_lifetime_in_hours: 24,
+                jwt_mfa_pending_lifetime_in_minutes: 5,
+                require_verified_email: false,
+                enumeration_resistant_registration: false,
+                jwt_algorithm: "HS256".to_string(),
+                jwt_private_key_path: None,
+                jwt_public_key_path: None,
+                jwt_key_id: None,
+                jwt_issuer: "chat_auth_server".to_string(),
+                jwt_audience: "krabby_chat".to_string(),
+                jwt_retired_key_grace_period_in_hours: None,
+                jwt_retired_keys: Vec::new(),
             }),
         }
     }
 
-    #[tokio::test]
-    async fn test_generate_tokens_auth() {
-        let config = mock_config();
-        let user = User {
+    fn mock_keys() -> JwtKeys {
+        JwtKeys::from_secret(b"test_secret", None)
+    }
+
+    fn mock_user(session_id: Option<Uuid>) -> User {
+        User {
             id: 1,
             email: "test@example.com".to_string(),
-        };
-
-        let result = generate_tokens("auth", user, &config).await;
-        assert!(result.is_ok());
-        let tokens = result.unwrap();
-        assert!(tokens.access_token.is_some());
-        assert!(tokens.refresh_token.is_some());
-        assert!(tokens.auth_cookie.is_some());
-        assert!(tokens.one_time_password_token.is_none());
+            session_id,
+        }
     }
 
-    #[tokio::test]
-    async fn test_generate_tokens_otp() {
+    #[test]
+    fn test_generate_auth_tokens() {
         let config = mock_config();
-        let user = User {
-            id: 1,
-            email: "test@example.com".to_string(),
-        };
-
-        let result = generate_tokens("one_time_password", user, &config).await;
-        assert!(result.is_ok());
-        let tokens = result.unwrap();
-        assert!(tokens.access_token.is_none());
-        assert!(tokens.refresh_token.is_none());
-        assert!(tokens.auth_cookie.is_none());
-        assert!(tokens.one_time_password_token.is_some());
+
+        let tokens = generate_auth_tokens(&mock_user(None), &config, &mock_keys()).unwrap();
+        assert!(!tokens.access_token.is_empty());
+        assert!(!tokens.refresh_token.is_empty());
     }
 
-    #[tokio::test]
-    async fn test_generate_tokens_invalid_type() {
+    #[test]
+    fn test_generate_standalone_tokens() {
         let config = mock_config();
-        let user = User {
-            id: 1,
-            email: "test@example.com".to_string(),
-        };
+        let keys = mock_keys();
+        let user = mock_user(None);
+        let now = Utc::now().timestamp() as usize;
 
-        let result = generate_tokens("invalid", user, &config).await;
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            JwtError::InvalidTokenType(t) => assert_eq!(t, "invalid"),
-            _ => panic!("Expected InvalidTokenType error"),
-        }
+        let otp = generate_one_time_password_token(&user, &config, &keys).unwrap();
+        assert!(otp.expires_at > now && otp.expires_at <= now + 5 * 60);
+        let claims = verify_one_time_password_token(&otp.token, &config, &keys).unwrap();
+        assert_eq!(claims.sid, None);
+
+        let verification = generate_email_verification_token(&user, &config, &keys).unwrap();
+        assert!(verification.expires_at > now + 60 * 60);
+        assert!(verify_email_verification_token(&verification.token, &config, &keys).is_ok());
+
+        let mfa = generate_mfa_pending_token(&user, &config, &keys).unwrap();
+        assert!(mfa.expires_at <= now + 5 * 60);
+    }
+
+    #[test]
+    fn test_verify_token_round_trip() {
+        let config = mock_config();
+        let session_id = Uuid::new_v4();
+
+        let tokens =
+            generate_auth_tokens(&mock_user(Some(session_id)), &config, &mock_keys()).unwrap();
+        assert_eq!(tokens.session_id, session_id);
+
+        let claims = verify_refresh_token(&tokens.refresh_token, &config, &mock_keys()).unwrap();
+        assert_eq!(claims.id, 1);
+        assert_eq!(claims.sid, Some(session_id));
+    }
+
+    #[test]
+    fn test_verify_token_rejects_wrong_secret() {
+        let config = mock_config();
+
+        let tokens = generate_auth_tokens(&mock_user(None), &config, &mock_keys()).unwrap();
+
+        // Same key id, different secret: the signature check must fail
+        let other_keys = JwtKeys::from_secret(b"another_secret", Some(mock_keys().kid()));
+
+        let result = verify_access_token(&tokens.access_token, &config, &other_keys);
+        assert!(matches!(result, Err(JwtError::Jwt(_))));
+
+        let result = verify_access_token(
+            &tokens.access_token,
+            &config,
+            &JwtKeys::from_secret(b"another_secret", None),
+        );
+        assert!(matches!(result, Err(JwtError::UnknownKey)));
+    }
+
+    #[test]
+    fn test_verify_token_with_asymmetric_keys() {
+        let config = mock_config();
+        let keys = JwtKeys::from_pem(
+            jsonwebtoken::Algorithm::ES256,
+            include_bytes!("../../tests/fixtures/jwt_keys/es256_private.pem"),
+            include_bytes!("../../tests/fixtures/jwt_keys/es256_public.pem"),
+            Some("es256-test".to_string()),
+        )
+        .unwrap();
+
+        let tokens = generate_auth_tokens(&mock_user(None), &config, &keys).unwrap();
+
+        let header = jsonwebtoken::decode_header(&tokens.access_token).unwrap();
+        assert_eq!(header.alg, jsonwebtoken::Algorithm::ES256);
+        assert_eq!(header.kid.as_deref(), Some("es256-test"));
+        assert_eq!(
+            verify_access_token(&tokens.access_token, &config, &keys)
+                .unwrap()
+                .id,
+            1
+        );
+
+        // An HS256 token claiming the same kid must not be accepted
+        let hmac_keys = JwtKeys::from_secret(b"test_secret", Some("es256-test".to_string()));
+        let forged = generate_auth_tokens(&mock_user(None), &config, &hmac_keys).unwrap();
+        assert!(verify_access_token(&forged.access_token, &config, &keys).is_err());
+    }
+
+    #[test]
+    fn test_token_kinds_cannot_be_confused() {
+        let config = mock_config();
+        let keys = mock_keys();
+        let user = mock_user(None);
+
+        let tokens = generate_auth_tokens(&user, &config, &keys).unwrap();
+        let otp = generate_one_time_password_token(&user, &config, &keys).unwrap();
+        let mfa = generate_mfa_pending_token(&user, &config, &keys).unwrap();
+
+        let claims = verify_refresh_token(&tokens.refresh_token, &config, &keys).unwrap();
+        assert_eq!(claims.sub, "1");
+        assert_eq!(claims.iss, "chat_auth_server");
+        assert_eq!(claims.aud, "krabby_chat");
+        assert_eq!(claims.nbf, claims.iat);
+
+        assert!(matches!(
+            verify_access_token(&tokens.refresh_token, &config, &keys),
+            Err(JwtError::WrongTokenKind {
+                expected: TokenKind::Access,
+                actual: TokenKind::Refresh,
+            })
+        ));
+        assert!(matches!(
+            verify_refresh_token(&tokens.access_token, &config, &keys),
+            Err(JwtError::WrongTokenKind { .. })
+        ));
+        assert!(matches!(
+            verify_access_token(&otp.token, &config, &keys),
+            Err(JwtError::WrongTokenKind { .. })
+        ));
+        assert!(matches!(
+            verify_email_verification_token(&otp.token, &config, &keys),
+            Err(JwtError::WrongTokenKind {
+                expected: TokenKind::EmailVerification,
+                actual: TokenKind::OneTimePassword,
+            })
+        ));
+
+        // The password step alone must never pass for a logged-in session
+        assert_eq!(
+            verify_mfa_pending_token(&mfa.token, &config, &keys)
+                .unwrap()
+                .sub,
+            "1"
+        );
+        assert!(matches!(
+            verify_access_token(&mfa.token, &config, &keys),
+            Err(JwtError::WrongTokenKind {
+                expected: TokenKind::Access,
+                actual: TokenKind::MfaPending,
+            })
+        ));
+        assert!(matches!(
+            verify_mfa_pending_token(&tokens.access_token, &config, &keys),
+            Err(JwtError::WrongTokenKind { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_token_rejects_other_issuer_and_audience() {
+        let config = mock_config();
+        let keys = mock_keys();
+
+        let tokens = generate_auth_tokens(&mock_user(None), &config, &keys).unwrap();
+
+        let mut other_audience = mock_config();
+        other_audience.auth.as_mut().unwrap().jwt_audience = "billing".to_string();
+        assert!(matches!(
+            verify_access_token(&tokens.access_token, &other_audience, &keys),
+            Err(JwtError::Jwt(_))
+        ));
+
+        let mut other_issuer = mock_config();
+        other_issuer.auth.as_mut().unwrap().jwt_issuer = "someone_else".to_string();
+        assert!(matches!(
+            verify_access_token(&tokens.access_token, &other_issuer, &keys),
+            Err(JwtError::Jwt(_))
+        ));
     }
 }
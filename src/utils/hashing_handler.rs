@@ -1,42 +1,262 @@
 //! # Password Hashing
 //!
 //! This module provides functionality for hashing passwords using the Argon2 algorithm.
+//!
+//! The Argon2id cost parameters and an optional pepper come from the `[security]` config
+//! section. A peppered hash records a short fingerprint of the pepper as its `keyid`, so
+//! [`needs_rehash`] can tell hashes made before the pepper was added, or with another
+//! pepper, apart from current ones.
+//!
+//! Hashing and verification run on Tokio's blocking threads, at most
+//! `security.password_hash_max_concurrency` at a time. Up to
+//! `security.password_hash_max_queued` further requests wait for a slot; any more are
+//! refused with [`PasswordHashError::Saturated`] instead of piling up.
 
+use crate::utils::load_config::SecuritySection;
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, KeyId, Params, PasswordHash, Version,
     password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
 };
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::{OnceCell, Semaphore};
+
+#[derive(Debug, Error)]
+pub enum PasswordHashError {
+    #[error("{0}")]
+    Hash(argon2::password_hash::Error),
+    /// Every hashing slot is taken and the queue is full.
+    #[error("password hashing is saturated, retry in {retry_after_in_seconds} seconds")]
+    Saturated { retry_after_in_seconds: u64 },
+}
+
+// `password_hash::Error` does not implement `std::error::Error`, so no `#[from]`
+impl From<argon2::password_hash::Error> for PasswordHashError {
+    fn from(e: argon2::password_hash::Error) -> Self {
+        PasswordHashError::Hash(e)
+    }
+}
+
+/// Argon2id parameters and pepper every password is hashed with, and the bounded pool
+/// hashing runs on. Clones share the pool.
+#[derive(Clone, Debug)]
+pub struct PasswordHashing {
+    params: Params,
+    pepper: Option<Vec<u8>>,
+    pool: HashingPool,
+    /// Hash of a random password with these settings, made on first use.
+    dummy_hash: Arc<OnceCell<String>>,
+}
+
+#[derive(Clone, Debug)]
+struct HashingPool {
+    /// One permit per job allowed on a blocking thread at once.
+    running: Arc<Semaphore>,
+    /// One permit per job running or waiting to run.
+    admitted: Arc<Semaphore>,
+    retry_after_in_seconds: u64,
+}
+
+impl HashingPool {
+    fn new(security: &SecuritySection) -> Self {
+        let max_concurrency = security.password_hash_max_concurrency.max(1);
+        let max_admitted = max_concurrency
+            .saturating_add(security.password_hash_max_queued)
+            .min(Semaphore::MAX_PERMITS);
+
+        HashingPool {
+            running: Arc::new(Semaphore::new(max_concurrency)),
+            admitted: Arc::new(Semaphore::new(max_admitted)),
+            retry_after_in_seconds: security.password_hash_retry_after_in_seconds,
+        }
+    }
+}
+
+impl Default for PasswordHashing {
+    fn default() -> Self {
+        PasswordHashing {
+            params: Params::default(),
+            pepper: None,
+            pool: HashingPool::new(&SecuritySection::default()),
+            dummy_hash: Arc::default(),
+        }
+    }
+}
+
+impl PasswordHashing {
+    /// Builds the hashing settings from the `[security]` section, or the Argon2 defaults
+    /// without one. An empty pepper counts as none.
+    pub fn from_config(security: Option<&SecuritySection>) -> Result<Self, argon2::Error> {
+        let Some(security) = security else {
+            return Ok(Self::default());
+        };
+
+        let pepper = security
+            .password_pepper
+            .as_deref()
+            .filter(|pepper| !pepper.is_empty())
+            .map(|pepper| pepper.as_bytes().to_vec());
+
+        let mut params = argon2::ParamsBuilder::new();
+        params
+            .m_cost(security.password_hash_memory_cost_in_kib)
+            .t_cost(security.password_hash_iterations)
+            .p_cost(security.password_hash_parallelism);
+        if let Some(pepper) = &pepper {
+            params.keyid(pepper_key_id(pepper)?);
+        }
+
+        Ok(PasswordHashing {
+            params: params.build()?,
+            pepper,
+            pool: HashingPool::new(security),
+            dummy_hash: Arc::default(),
+        })
+    }
+
+    /// A hash of a random password made with the current settings.
+    ///
+    /// Verifying against it takes as long as verifying a real password, for requests
+    /// that have no stored hash to check. Computed once, on first use.
+    pub async fn dummy_hash(&self) -> Result<&str, PasswordHashError> {
+        let dummy_hash = self
+            .dummy_hash
+            .get_or_try_init(|| {
+                let password = SaltString::generate(&mut OsRng);
+                async move { hashing_handler(password.as_str(), self).await }
+            })
+            .await?;
+
+        Ok(dummy_hash)
+    }
+
+    /// Runs `job` on a blocking thread once a hashing slot is free.
+    ///
+    /// Fails with [`PasswordHashError::Saturated`] right away when the queue is full. The
+    /// slot is held until `job` returns, even if the caller stops waiting for it.
+    pub(crate) async fn run_blocking<T, F>(&self, job: F) -> Result<T, PasswordHashError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&PasswordHashing) -> Result<T, argon2::password_hash::Error> + Send + 'static,
+    {
+        let saturated = || PasswordHashError::Saturated {
+            retry_after_in_seconds: self.pool.retry_after_in_seconds,
+        };
+
+        let admitted = self
+            .pool
+            .admitted
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| saturated())?;
+        let running = self
+            .pool
+            .running
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| saturated())?;
+        let hashing = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _permits = (admitted, running);
+            job(&hashing)
+        })
+        .await
+        .map_err(|_| argon2::password_hash::Error::Password)?
+        .map_err(PasswordHashError::from)
+    }
+
+    /// The Argon2id instance hashing and verifying with these settings.
+    pub(crate) fn argon2(&self) -> Result<Argon2<'_>, argon2::Error> {
+        match &self.pepper {
+            Some(pepper) => Argon2::new_with_secret(
+                pepper,
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.params.clone(),
+            ),
+            None => Ok(Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.params.clone(),
+            )),
+        }
+    }
+
+    /// The `keyid` peppered hashes carry, empty without a pepper.
+    pub(crate) fn key_id(&self) -> &[u8] {
+        self.params.keyid()
+    }
+}
 
-/// Hashes a plain-text string using Argon2 with a random salt.
+/// Identifies a pepper in the hashes it was used for without revealing it.
+fn pepper_key_id(pepper: &[u8]) -> Result<KeyId, argon2::Error> {
+    KeyId::new(&Sha256::digest(pepper)[..4])
+}
+
+/// Hashes a plain-text string using Argon2id with a random salt.
 ///
-/// Returns the hashed string in PHC format, or an `Err` if hashing fails.
-pub async fn hashing_handler(string_to_hash: &str) -> Result<String, argon2::password_hash::Error> {
+/// Returns the hashed string in PHC format, or an `Err` if hashing fails or the hashing
+/// pool is saturated.
+pub async fn hashing_handler(
+    string_to_hash: &str,
+    hashing: &PasswordHashing,
+) -> Result<String, PasswordHashError> {
     let password = string_to_hash.to_string();
 
-    tokio::task::spawn_blocking(move || {
-        // Generate a random 16-byte salt
-        let salt = SaltString::generate(&mut OsRng);
+    hashing
+        .run_blocking(move |hashing| {
+            // Generate a random 16-byte salt
+            let salt = SaltString::generate(&mut OsRng);
 
-        // Argon2 with default params (Argon2id v19)
-        let argon2 = Argon2::default();
+            // Hash password to PHC string ($argon2id$v=19$...)
+            let password_hash = hashing
+                .argon2()?
+                .hash_password(password.as_bytes(), &salt)?;
 
-        // Hash password to PHC string ($argon2id$v=19$...)
-        let password_hash = argon2.hash_password(password.as_bytes(), &salt)?;
+            Ok(password_hash.to_string())
+        })
+        .await
+}
 
-        Ok(password_hash.to_string())
-    })
-    .await
-    .map_err(|_| argon2::password_hash::Error::Password)?
+/// Whether `hashed_string` was made with other settings than the current ones and should
+/// be replaced the next time the password is known.
+///
+/// Hashes that cannot be parsed are left alone.
+pub fn needs_rehash(hashed_string: &str, hashing: &PasswordHashing) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hashed_string) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&parsed_hash) else {
+        return false;
+    };
+
+    parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+        || params.m_cost() != hashing.params.m_cost()
+        || params.t_cost() != hashing.params.t_cost()
+        || params.p_cost() != hashing.params.p_cost()
+        || params.keyid() != hashing.key_id()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn peppered(pepper: &str) -> PasswordHashing {
+        PasswordHashing::from_config(Some(&SecuritySection {
+            password_pepper: Some(pepper.to_string()),
+            ..SecuritySection::default()
+        }))
+        .unwrap()
+    }
+
     #[tokio::test]
     async fn test_hashing_handler_success() {
         let password = "my_secure_password";
-        let result = hashing_handler(password).await;
+        let result = hashing_handler(password, &PasswordHashing::default()).await;
         assert!(result.is_ok());
         let hash = result.unwrap();
         assert!(hash.contains("$argon2id$"));
@@ -45,8 +265,112 @@ mod tests {
     #[tokio::test]
     async fn test_hashing_produces_unique_hashes() {
         let password = "same_password";
-        let hash1 = hashing_handler(password).await.unwrap();
-        let hash2 = hashing_handler(password).await.unwrap();
+        let hash1 = hashing_handler(password, &PasswordHashing::default())
+            .await
+            .unwrap();
+        let hash2 = hashing_handler(password, &PasswordHashing::default())
+            .await
+            .unwrap();
         assert_ne!(hash1, hash2, "Each hash should use a unique salt");
     }
+
+    #[tokio::test]
+    async fn test_hashing_uses_configured_parameters() {
+        let hashing = PasswordHashing::from_config(Some(&SecuritySection {
+            password_hash_memory_cost_in_kib: 8192,
+            password_hash_iterations: 3,
+            password_hash_parallelism: 2,
+            ..SecuritySection::default()
+        }))
+        .unwrap();
+
+        let hash = hashing_handler("my_secure_password", &hashing)
+            .await
+            .unwrap();
+        assert!(hash.contains("$m=8192,t=3,p=2$"));
+        assert!(!needs_rehash(&hash, &hashing));
+        assert!(needs_rehash(&hash, &PasswordHashing::default()));
+    }
+
+    #[tokio::test]
+    async fn test_needs_rehash_tracks_pepper() {
+        let unpeppered = hashing_handler("my_secure_password", &PasswordHashing::default())
+            .await
+            .unwrap();
+        let hash = hashing_handler("my_secure_password", &peppered("pepper"))
+            .await
+            .unwrap();
+
+        assert!(hash.contains(",keyid="));
+        assert!(needs_rehash(&unpeppered, &peppered("pepper")));
+        assert!(!needs_rehash(&hash, &peppered("pepper")));
+        assert!(needs_rehash(&hash, &peppered("another pepper")));
+        assert!(needs_rehash(&hash, &PasswordHashing::default()));
+    }
+
+    #[test]
+    fn test_invalid_parameters_are_rejected() {
+        let result = PasswordHashing::from_config(Some(&SecuritySection {
+            password_hash_iterations: 0,
+            ..SecuritySection::default()
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_saturated_pool_refuses_work() {
+        let hashing = PasswordHashing::from_config(Some(&SecuritySection {
+            password_hash_max_concurrency: 1,
+            password_hash_max_queued: 0,
+            password_hash_retry_after_in_seconds: 7,
+            ..SecuritySection::default()
+        }))
+        .unwrap();
+
+        // Clones share the pool, so a job holding the only slot blocks them all
+        let busy = hashing.clone().pool.admitted.try_acquire_owned().unwrap();
+        let result = hashing_handler("my_secure_password", &hashing).await;
+        assert!(matches!(
+            result,
+            Err(PasswordHashError::Saturated {
+                retry_after_in_seconds: 7
+            })
+        ));
+
+        drop(busy);
+        assert!(
+            hashing_handler("my_secure_password", &hashing)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queued_work_waits_for_a_slot() {
+        let hashing = PasswordHashing::from_config(Some(&SecuritySection {
+            password_hash_max_concurrency: 1,
+            password_hash_max_queued: 4,
+            ..SecuritySection::default()
+        }))
+        .unwrap();
+
+        // More jobs than slots: the queued ones run once a slot frees up
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..5 {
+            let hashing = hashing.clone();
+            tasks.spawn(async move { hashing_handler("my_secure_password", &hashing).await });
+        }
+        assert!(tasks.join_all().await.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_dummy_hash_uses_current_settings() {
+        let hashing = peppered("pepper");
+
+        let dummy_hash = hashing.dummy_hash().await.unwrap().to_string();
+        assert!(!needs_rehash(&dummy_hash, &hashing));
+        // Made once and shared by clones
+        assert_eq!(hashing.clone().dummy_hash().await.unwrap(), dummy_hash);
+    }
 }
@@ -0,0 +1,658 @@
+//! # JWT Signing Keys
+//!
+//! This module holds the keyring tokens are signed and verified with. Exactly one key
+//! signs new tokens at a time; after a rotation the previous key is retired but keeps
+//! verifying the tokens it signed until its grace period ends, so nobody is logged out.
+//!
+//! Keys are either the shared `jwt_secret` (HS256) or an asymmetric key pair read from
+//! PEM files (RS256, ES256 or EdDSA). The public half of every asymmetric key is
+//! published as a JWK set, so other services can verify tokens without the signing key.
+
+use crate::utils::generate_tokens::JwtError;
+use crate::utils::load_config::{AuthSection, RetiredJwtKeySection};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, decode_header,
+    encode,
+};
+use rsa::RsaPublicKey;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::traits::PublicKeyParts;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Algorithms accepted in `auth.jwt_algorithm`.
+pub const SUPPORTED_JWT_ALGORITHMS: [&str; 4] = ["HS256", "RS256", "ES256", "EdDSA"];
+
+/// Parses `auth.jwt_algorithm`, rejecting algorithms the service cannot sign with.
+pub fn parse_jwt_algorithm(name: &str) -> Result<Algorithm, JwtError> {
+    if !SUPPORTED_JWT_ALGORITHMS.contains(&name) {
+        return Err(JwtError::UnsupportedAlgorithm(name.to_string()));
+    }
+
+    name.parse()
+        .map_err(|_| JwtError::UnsupportedAlgorithm(name.to_string()))
+}
+
+/// Derives a key id from the public (or, for HMAC, the secret) key bytes.
+fn derive_kid(verification_key: &[u8]) -> String {
+    let digest = format!("{:x}", Sha256::digest(verification_key));
+    digest[..16].to_string()
+}
+
+fn read_key_file(path: Option<&str>, field: &'static str) -> Result<Vec<u8>, JwtError> {
+    let path = path
+        .filter(|path| !path.trim().is_empty())
+        .ok_or(JwtError::MissingKeyPath(field))?;
+
+    std::fs::read(path).map_err(|e| JwtError::KeyFile(path.to_string(), e))
+}
+
+/// Builds the public JWK of an asymmetric verification key.
+///
+/// jsonwebtoken can only derive JWKs from private keys (and not at all for Ed25519), while
+/// retired keys are configured by their public half alone. The parameters are therefore
+/// read from the raw key bytes: a PKCS#1 `RSAPublicKey`, an uncompressed P-256 point, or
+/// the Ed25519 public key.
+fn public_jwk(algorithm: Algorithm, kid: &str, key: &DecodingKey) -> Result<Jwk, JwtError> {
+    let bytes = key.as_bytes();
+
+    let (key_algorithm, parameters) = match algorithm {
+        Algorithm::RS256 => {
+            let public_key = RsaPublicKey::from_pkcs1_der(bytes).map_err(|e| {
+                jsonwebtoken::errors::Error::from(ErrorKind::InvalidRsaKey(e.to_string()))
+            })?;
+
+            (
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+                }),
+            )
+        }
+        Algorithm::ES256 => {
+            // 0x04 || x || y
+            if bytes.len() != 65 || bytes[0] != 0x04 {
+                return Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidEcdsaKey).into());
+            }
+
+            (
+                KeyAlgorithm::ES256,
+                AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(&bytes[1..33]),
+                    y: URL_SAFE_NO_PAD.encode(&bytes[33..]),
+                }),
+            )
+        }
+        Algorithm::EdDSA => (
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(bytes),
+            }),
+        ),
+        other => return Err(JwtError::UnsupportedAlgorithm(format!("{:?}", other))),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// A key that verifies tokens. Asymmetric keys carry the JWK they are published as.
+#[derive(Clone)]
+pub struct VerificationKey {
+    algorithm: Algorithm,
+    kid: String,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl fmt::Debug for VerificationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerificationKey")
+            .field("algorithm", &self.algorithm)
+            .field("kid", &self.kid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VerificationKey {
+    fn from_secret(secret: &[u8], kid: Option<String>) -> Self {
+        VerificationKey {
+            algorithm: Algorithm::HS256,
+            kid: kid.unwrap_or_else(|| derive_kid(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+            // A shared secret must never be published
+            jwk: None,
+        }
+    }
+
+    /// Builds an asymmetric verification key from a PEM-encoded public key.
+    pub fn from_pem(
+        algorithm: Algorithm,
+        public_pem: &[u8],
+        kid: Option<String>,
+    ) -> Result<Self, JwtError> {
+        let decoding_key = match algorithm {
+            Algorithm::RS256 => DecodingKey::from_rsa_pem(public_pem)?,
+            Algorithm::ES256 => DecodingKey::from_ec_pem(public_pem)?,
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(public_pem)?,
+            other => return Err(JwtError::UnsupportedAlgorithm(format!("{:?}", other))),
+        };
+
+        let kid = kid.unwrap_or_else(|| derive_kid(decoding_key.as_bytes()));
+        let jwk = public_jwk(algorithm, &kid, &decoding_key)?;
+
+        Ok(VerificationKey {
+            algorithm,
+            kid,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    /// Loads a retired key listed under `auth.jwt_retired_keys`.
+    pub fn from_retired_config(section: &RetiredJwtKeySection) -> Result<Self, JwtError> {
+        let algorithm = parse_jwt_algorithm(&section.algorithm)?;
+        let kid = Some(section.key_id.clone());
+
+        if algorithm == Algorithm::HS256 {
+            let secret = section
+                .secret
+                .as_deref()
+                .filter(|secret| !secret.trim().is_empty())
+                .ok_or(JwtError::MissingKeyPath("auth.jwt_retired_keys.secret"))?;
+            return Ok(Self::from_secret(secret.as_bytes(), kid));
+        }
+
+        let public_pem = read_key_file(
+            section.public_key_path.as_deref(),
+            "auth.jwt_retired_keys.public_key_path",
+        )?;
+
+        Self::from_pem(algorithm, &public_pem, kid)
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+}
+
+/// The key new tokens are signed with, together with its verification half.
+#[derive(Clone)]
+pub struct SigningKey {
+    verification: VerificationKey,
+    encoding_key: EncodingKey,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print key material
+        f.debug_struct("SigningKey")
+            .field("algorithm", &self.verification.algorithm)
+            .field("kid", &self.verification.kid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// Loads the signing key described by the `auth` configuration section.
+    pub fn from_config(auth: &AuthSection) -> Result<Self, JwtError> {
+        let algorithm = parse_jwt_algorithm(&auth.jwt_algorithm)?;
+
+        if algorithm == Algorithm::HS256 {
+            return Ok(Self::from_secret(
+                auth.jwt_secret.as_bytes(),
+                auth.jwt_key_id.clone(),
+            ));
+        }
+
+        let private_pem = read_key_file(
+            auth.jwt_private_key_path.as_deref(),
+            "auth.jwt_private_key_path",
+        )?;
+        let public_pem = read_key_file(
+            auth.jwt_public_key_path.as_deref(),
+            "auth.jwt_public_key_path",
+        )?;
+
+        Self::from_pem(
+            algorithm,
+            &private_pem,
+            &public_pem,
+            auth.jwt_key_id.clone(),
+        )
+    }
+
+    /// Builds an HS256 key from a shared secret.
+    pub fn from_secret(secret: &[u8], kid: Option<String>) -> Self {
+        SigningKey {
+            verification: VerificationKey::from_secret(secret, kid),
+            encoding_key: EncodingKey::from_secret(secret),
+        }
+    }
+
+    /// Builds an asymmetric key from a PEM-encoded private key and its PEM-encoded public key.
+    pub fn from_pem(
+        algorithm: Algorithm,
+        private_pem: &[u8],
+        public_pem: &[u8],
+        kid: Option<String>,
+    ) -> Result<Self, JwtError> {
+        let encoding_key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(private_pem)?,
+            Algorithm::ES256 => EncodingKey::from_ec_pem(private_pem)?,
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(private_pem)?,
+            other => return Err(JwtError::UnsupportedAlgorithm(format!("{:?}", other))),
+        };
+
+        Ok(SigningKey {
+            verification: VerificationKey::from_pem(algorithm, public_pem, kid)?,
+            encoding_key,
+        })
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.verification.kid
+    }
+}
+
+/// A key that no longer signs, but verifies its tokens until `valid_until`.
+#[derive(Clone, Debug)]
+struct RetiredKey {
+    key: VerificationKey,
+    valid_until: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct Keyring {
+    current: SigningKey,
+    retired: Vec<RetiredKey>,
+}
+
+impl Keyring {
+    fn verification_keys(&self, now: DateTime<Utc>) -> impl Iterator<Item = &VerificationKey> {
+        std::iter::once(&self.current.verification).chain(
+            self.retired
+                .iter()
+                .filter(move |retired| retired.valid_until > now)
+                .map(|retired| &retired.key),
+        )
+    }
+
+    /// Adds a retired key, replacing any earlier entry with the same `kid` and dropping
+    /// keys whose grace period has ended.
+    fn retire(&mut self, retired: RetiredKey, now: DateTime<Utc>) {
+        let current_kid = self.current.kid().to_string();
+
+        self.retired
+            .retain(|existing| existing.key.kid != retired.key.kid && existing.valid_until > now);
+
+        if retired.key.kid != current_kid {
+            self.retired.push(retired);
+        }
+    }
+}
+
+/// The keyring shared by every request: one current signing key plus retired keys.
+///
+/// Rotation swaps the current key in place, so it takes effect without a restart.
+#[derive(Debug)]
+pub struct JwtKeys {
+    keyring: RwLock<Keyring>,
+}
+
+impl JwtKeys {
+    /// Loads the current key and the retired keys described by the `auth` configuration section.
+    pub fn from_config(auth: &AuthSection) -> Result<Self, JwtError> {
+        let keys = Self::new(SigningKey::from_config(auth)?);
+
+        for section in &auth.jwt_retired_keys {
+            keys.retire(
+                VerificationKey::from_retired_config(section)?,
+                section.valid_until,
+            );
+        }
+
+        Ok(keys)
+    }
+
+    /// Creates a keyring holding only `current`.
+    pub fn new(current: SigningKey) -> Self {
+        JwtKeys {
+            keyring: RwLock::new(Keyring {
+                current,
+                retired: Vec::new(),
+            }),
+        }
+    }
+
+    /// Builds an HS256 keyring from a shared secret. Nothing is published in the JWK set.
+    pub fn from_secret(secret: &[u8], kid: Option<String>) -> Self {
+        Self::new(SigningKey::from_secret(secret, kid))
+    }
+
+    /// Builds an asymmetric keyring from a PEM-encoded private key and its PEM-encoded public key.
+    pub fn from_pem(
+        algorithm: Algorithm,
+        private_pem: &[u8],
+        public_pem: &[u8],
+        kid: Option<String>,
+    ) -> Result<Self, JwtError> {
+        Ok(Self::new(SigningKey::from_pem(
+            algorithm,
+            private_pem,
+            public_pem,
+            kid,
+        )?))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Keyring> {
+        // Every write leaves the keyring consistent, so a poisoned lock is still usable
+        self.keyring.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Keyring> {
+        self.keyring.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The algorithm new tokens are signed with.
+    pub fn algorithm(&self) -> Algorithm {
+        self.read().current.verification.algorithm
+    }
+
+    /// The key id embedded in the header of new tokens.
+    pub fn kid(&self) -> String {
+        self.read().current.kid().to_string()
+    }
+
+    /// Key ids of the retired keys still accepted for verification.
+    pub fn retired_kids(&self) -> Vec<String> {
+        let now = Utc::now();
+
+        self.read()
+            .retired
+            .iter()
+            .filter(|retired| retired.valid_until > now)
+            .map(|retired| retired.key.kid.clone())
+            .collect()
+    }
+
+    /// Signs `claims` with the current key, announcing its algorithm and `kid` in the header.
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let keyring = self.read();
+        let current = &keyring.current;
+
+        let mut header = Header::new(current.verification.algorithm);
+        header.kid = Some(current.kid().to_string());
+
+        Ok(encode(&header, claims, &current.encoding_key)?)
+    }
+
+    /// Verifies a token against the key named by its `kid` header.
+    ///
+    /// `validation` carries the claim checks; its algorithms are replaced by the algorithm
+    /// of the selected key. Tokens without a `kid` predate key ids and are checked against
+    /// the current key.
+    pub fn verify<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        validation: &Validation,
+    ) -> Result<TokenData<T>, JwtError> {
+        let header = decode_header(token)?;
+        let keyring = self.read();
+
+        let key = match header.kid.as_deref() {
+            Some(kid) => keyring
+                .verification_keys(Utc::now())
+                .find(|key| key.kid == kid)
+                .ok_or(JwtError::UnknownKey)?,
+            None => &keyring.current.verification,
+        };
+
+        let mut validation = validation.clone();
+        validation.algorithms = vec![key.algorithm];
+
+        Ok(decode::<T>(token, &key.decoding_key, &validation)?)
+    }
+
+    /// The public keys other services may verify tokens with, retired ones included.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .read()
+                .verification_keys(Utc::now())
+                .filter_map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+
+    /// Makes `next` the signing key. The previous key keeps verifying for `grace_period`.
+    ///
+    /// Returns `false`, changing nothing, when `next` has the same `kid` as the current key.
+    pub fn rotate(&self, next: SigningKey, grace_period: Duration) -> bool {
+        let now = Utc::now();
+        let mut keyring = self.write();
+
+        if keyring.current.kid() == next.kid() {
+            return false;
+        }
+
+        let previous = std::mem::replace(&mut keyring.current, next);
+        keyring.retire(
+            RetiredKey {
+                key: previous.verification,
+                valid_until: now
+                    .checked_add_signed(grace_period)
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            },
+            now,
+        );
+
+        true
+    }
+
+    /// Keeps accepting tokens signed with `key` until `valid_until`.
+    pub fn retire(&self, key: VerificationKey, valid_until: DateTime<Utc>) {
+        self.write()
+            .retire(RetiredKey { key, valid_until }, Utc::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "42".to_string(),
+            exp: jsonwebtoken::get_current_timestamp() as usize + 60,
+        }
+    }
+
+    fn fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!(
+            "{}/tests/fixtures/jwt_keys/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    fn load(algorithm: Algorithm, prefix: &str) -> SigningKey {
+        SigningKey::from_pem(
+            algorithm,
+            &fixture(&format!("{}_private.pem", prefix)),
+            &fixture(&format!("{}_public.pem", prefix)),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_asymmetric_keys_round_trip_and_publish_jwk() {
+        for (algorithm, prefix) in [
+            (Algorithm::RS256, "rs256"),
+            (Algorithm::ES256, "es256"),
+            (Algorithm::EdDSA, "eddsa"),
+        ] {
+            let keys = JwtKeys::new(load(algorithm, prefix));
+            let claims = claims();
+
+            let token = keys.sign(&claims).unwrap();
+            let header = decode_header(&token).unwrap();
+            assert_eq!(header.kid, Some(keys.kid()));
+            assert_eq!(header.alg, algorithm);
+            assert_eq!(
+                keys.verify::<TestClaims>(&token, &Validation::default())
+                    .unwrap()
+                    .claims,
+                claims
+            );
+
+            // The published JWK alone must be enough to verify tokens
+            let jwks = keys.jwks();
+            assert_eq!(jwks.keys.len(), 1);
+            let key = DecodingKey::from_jwk(jwks.find(&keys.kid()).unwrap()).unwrap();
+            assert!(decode::<TestClaims>(&token, &key, &Validation::new(algorithm)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_hmac_keys_publish_nothing() {
+        let keys = JwtKeys::from_secret(b"test_secret", None);
+
+        let token = keys.sign(&claims()).unwrap();
+        assert!(
+            keys.verify::<TestClaims>(&token, &Validation::default())
+                .is_ok()
+        );
+        assert!(keys.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_configured_kid_is_used() {
+        let keys = JwtKeys::from_secret(b"test_secret", Some("2026-10".to_string()));
+        assert_eq!(keys.kid(), "2026-10");
+
+        let other = JwtKeys::from_secret(b"test_secret", Some("another-key".to_string()));
+        let token = other.sign(&claims()).unwrap();
+        assert!(matches!(
+            keys.verify::<TestClaims>(&token, &Validation::default()),
+            Err(JwtError::UnknownKey)
+        ));
+    }
+
+    #[test]
+    fn test_rotation_keeps_retired_key_during_grace_period() {
+        let keys = JwtKeys::new(load(Algorithm::RS256, "rs256"));
+        let old_kid = keys.kid();
+        let old_token = keys.sign(&claims()).unwrap();
+
+        assert!(keys.rotate(load(Algorithm::ES256, "es256"), Duration::hours(1)));
+        assert_ne!(keys.kid(), old_kid);
+        assert_eq!(keys.retired_kids(), vec![old_kid]);
+
+        // New tokens use the new key, old tokens still verify
+        let new_token = keys.sign(&claims()).unwrap();
+        assert_eq!(decode_header(&new_token).unwrap().alg, Algorithm::ES256);
+        assert!(
+            keys.verify::<TestClaims>(&new_token, &Validation::default())
+                .is_ok()
+        );
+        assert!(
+            keys.verify::<TestClaims>(&old_token, &Validation::default())
+                .is_ok()
+        );
+        assert_eq!(keys.jwks().keys.len(), 2);
+    }
+
+    #[test]
+    fn test_rotation_drops_retired_key_after_grace_period() {
+        let keys = JwtKeys::new(load(Algorithm::EdDSA, "eddsa"));
+        let old_token = keys.sign(&claims()).unwrap();
+
+        assert!(keys.rotate(SigningKey::from_secret(b"next", None), Duration::zero()));
+
+        assert!(keys.retired_kids().is_empty());
+        assert!(matches!(
+            keys.verify::<TestClaims>(&old_token, &Validation::default()),
+            Err(JwtError::UnknownKey)
+        ));
+        assert!(keys.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_rotation_to_the_same_key_is_a_no_op() {
+        let keys = JwtKeys::from_secret(b"test_secret", None);
+
+        assert!(!keys.rotate(
+            SigningKey::from_secret(b"test_secret", None),
+            Duration::hours(1)
+        ));
+        assert!(keys.retired_kids().is_empty());
+    }
+
+    #[test]
+    fn test_retired_key_from_public_pem_only() {
+        let signing = load(Algorithm::ES256, "es256");
+        let token = JwtKeys::new(signing.clone()).sign(&claims()).unwrap();
+
+        let keys = JwtKeys::from_secret(b"test_secret", None);
+        let retired =
+            VerificationKey::from_pem(Algorithm::ES256, &fixture("es256_public.pem"), None)
+                .unwrap();
+        assert_eq!(retired.kid(), signing.kid());
+        keys.retire(retired, Utc::now() + Duration::hours(1));
+
+        assert!(
+            keys.verify::<TestClaims>(&token, &Validation::default())
+                .is_ok()
+        );
+        assert_eq!(keys.jwks().keys.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_jwt_algorithm() {
+        assert_eq!(parse_jwt_algorithm("RS256").unwrap(), Algorithm::RS256);
+        assert_eq!(parse_jwt_algorithm("EdDSA").unwrap(), Algorithm::EdDSA);
+        assert!(matches!(
+            parse_jwt_algorithm("none"),
+            Err(JwtError::UnsupportedAlgorithm(_))
+        ));
+        assert!(matches!(
+            parse_jwt_algorithm("PS512"),
+            Err(JwtError::UnsupportedAlgorithm(_))
+        ));
+    }
+}
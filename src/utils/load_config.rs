@@ -4,7 +4,12 @@
 //! multiple sources: base TOML files, environment-specific overrides, local
 //! overrides, and environment variables.
 
+use crate::mailer::{SUPPORTED_MAIL_TRANSPORTS, SUPPORTED_SMTP_SECURITY};
+use crate::rate_limit::SUPPORTED_RATE_LIMIT_STORES;
+use crate::sms::SUPPORTED_SMS_TRANSPORTS;
+use crate::utils::jwt_keys::SUPPORTED_JWT_ALGORITHMS;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use config::{Config, Environment, File};
 use serde::Deserialize;
 use std::fmt;
@@ -20,20 +25,30 @@ pub struct AppSection {
 
 #[derive(Debug, Deserialize)]
 pub struct ClientIntegrationsSection {
+    /// Verifies the credential of requests to protected and admin routes before they reach
+    /// their handler.
     #[serde(default)]
     pub allow_access_middleware: bool,
 
+    /// Records the last use and IP address of the session behind authenticated requests.
     #[serde(default)]
     pub allow_sessions_middleware: bool,
 
+    /// Logs the path and duration of every request.
     #[serde(default)]
     pub allow_logging_middleware: bool,
 
+    /// Cuts requests off after `server.request_timeout_secs`.
     #[serde(default)]
     pub allow_request_timeout_middleware: bool,
 
+    /// Refuses non-admins before admin routes.
     #[serde(default)]
     pub allow_admin_routes_protector_middleware: bool,
+
+    /// Meters requests with the limits of the `[rate_limit]` section.
+    #[serde(default)]
+    pub allow_rate_limit_middleware: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,6 +62,9 @@ pub struct ServerSection {
     pub host: String,
     pub port: u16,
     pub request_timeout_secs: u64,
+    /// Read client IPs from `X-Forwarded-For`/`X-Real-IP`. Only enable behind a trusted proxy.
+    #[serde(default)]
+    pub trust_proxy_headers: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,17 +81,441 @@ pub struct DatabaseSection {
 
 #[derive(Debug, Deserialize)]
 pub struct AuthSection {
+    /// Shared signing secret. Only required when `jwt_algorithm` is `HS256`.
+    #[serde(default)]
     pub jwt_secret: String,
     pub jwt_access_expiration_time_in_hours: u64,
     pub jwt_refresh_expiration_time_in_hours: u64,
     pub jwt_one_time_password_lifetime_in_minutes: u64,
+    #[serde(default = "default_jwt_email_verification_lifetime_in_hours")]
+    pub jwt_email_verification_lifetime_in_hours: u64,
+    /// How long the password step of a login with two-factor authentication stays good for
+    /// the second step.
+    #[serde(default = "default_jwt_mfa_pending_lifetime_in_minutes")]
+    pub jwt_mfa_pending_lifetime_in_minutes: u64,
+    /// Whether users must verify their email address before they can log in.
+    #[serde(default)]
+    pub require_verified_email: bool,
+    /// Answers every well-formed registration the same way, without tokens, so it cannot
+    /// be used to find out which emails and phone numbers are registered. The owner of an
+    /// email that is already registered is told by mail instead.
+    #[serde(default)]
+    pub enumeration_resistant_registration: bool,
+    /// One of `HS256`, `RS256`, `ES256` or `EdDSA`.
+    #[serde(default = "default_jwt_algorithm")]
+    pub jwt_algorithm: String,
+    /// PEM private key tokens are signed with (asymmetric algorithms only).
+    #[serde(default)]
+    pub jwt_private_key_path: Option<String>,
+    /// PEM public key published on the JWKS endpoint (asymmetric algorithms only).
+    #[serde(default)]
+    pub jwt_public_key_path: Option<String>,
+    /// `kid` header of issued tokens. Derived from the public key when not set.
+    #[serde(default)]
+    pub jwt_key_id: Option<String>,
+    /// `iss` claim of issued tokens; tokens from other issuers are rejected.
+    #[serde(default = "default_jwt_issuer")]
+    pub jwt_issuer: String,
+    /// `aud` claim of issued tokens; tokens for other audiences are rejected.
+    #[serde(default = "default_jwt_audience")]
+    pub jwt_audience: String,
+    /// How long a key replaced by a rotation keeps verifying tokens.
+    /// Defaults to the refresh token lifetime, so no session is cut short.
+    #[serde(default)]
+    pub jwt_retired_key_grace_period_in_hours: Option<u64>,
+    /// Previous keys that still verify tokens, e.g. across a restart after a rotation.
+    #[serde(default)]
+    pub jwt_retired_keys: Vec<RetiredJwtKeySection>,
+}
+
+impl AuthSection {
+    pub fn retired_key_grace_period_in_hours(&self) -> u64 {
+        self.jwt_retired_key_grace_period_in_hours
+            .unwrap_or(self.jwt_refresh_expiration_time_in_hours)
+    }
+}
+
+/// A retired signing key, accepted for verification until `valid_until`.
+#[derive(Debug, Deserialize)]
+pub struct RetiredJwtKeySection {
+    pub key_id: String,
+    #[serde(default = "default_jwt_algorithm")]
+    pub algorithm: String,
+    /// Shared secret of a retired `HS256` key.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// PEM public key of a retired asymmetric key.
+    #[serde(default)]
+    pub public_key_path: Option<String>,
+    /// RFC 3339 timestamp, e.g. `2026-11-01T00:00:00Z`.
+    pub valid_until: DateTime<Utc>,
+}
+
+fn default_jwt_algorithm() -> String {
+    "HS256".to_string()
+}
+
+fn default_jwt_email_verification_lifetime_in_hours() -> u64 {
+    24
+}
+
+fn default_jwt_mfa_pending_lifetime_in_minutes() -> u64 {
+    5
+}
+
+fn default_jwt_issuer() -> String {
+    "chat_auth_server".to_string()
+}
+
+fn default_jwt_audience() -> String {
+    "krabby_chat".to_string()
+}
+
+/// Outbound mail settings. Without a `[mail]` section, mail is printed to stdout.
+#[derive(Debug, Deserialize)]
+pub struct MailSection {
+    /// One of `smtp`, `file`, `memory` or `stdout`.
+    #[serde(default = "default_mail_transport")]
+    pub transport: String,
+    /// `From` mailbox of every message, e.g. `Krabby Chat <no-reply@krabby.chat>`.
+    #[serde(default = "default_mail_from_address")]
+    pub from_address: String,
+    /// Client URL that links in messages point to. Messages carry the bare token when not set.
+    #[serde(default)]
+    pub public_base_url: Option<String>,
+    /// Directory messages are dropped into as `.eml` files (`file` transport only).
+    #[serde(default)]
+    pub drop_directory: Option<String>,
+    /// SMTP relay host (`smtp` transport only).
+    #[serde(default)]
+    pub smtp_host: Option<String>,
+    /// Defaults to 465 for `tls`, 587 for `starttls` and 25 for `none`.
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub smtp_username: Option<String>,
+    #[serde(default)]
+    pub smtp_password: Option<String>,
+    /// One of `starttls`, `tls` or `none`.
+    #[serde(default = "default_smtp_security")]
+    pub smtp_security: String,
+}
+
+impl Default for MailSection {
+    fn default() -> Self {
+        MailSection {
+            transport: default_mail_transport(),
+            from_address: default_mail_from_address(),
+            public_base_url: None,
+            drop_directory: None,
+            smtp_host: None,
+            smtp_port: None,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_security: default_smtp_security(),
+        }
+    }
+}
+
+fn default_mail_transport() -> String {
+    "stdout".to_string()
+}
+
+fn default_mail_from_address() -> String {
+    "Krabby Chat <no-reply@localhost>".to_string()
+}
+
+fn default_smtp_security() -> String {
+    "starttls".to_string()
+}
+
+/// Outbound SMS settings. Without an `[sms]` section, messages are only logged.
+#[derive(Debug, Deserialize)]
+pub struct SmsSection {
+    /// One of `log`, `memory` or `disabled`.
+    #[serde(default = "default_sms_transport")]
+    pub transport: String,
+    /// How long a phone verification code stays valid.
+    #[serde(default = "default_phone_code_lifetime_in_minutes")]
+    pub phone_code_lifetime_in_minutes: u64,
+    /// Minimum wait between two phone verification codes sent to the same user.
+    #[serde(default = "default_phone_code_resend_cooldown_in_seconds")]
+    pub phone_code_resend_cooldown_in_seconds: u64,
+    /// Wrong guesses after which a phone verification code stops working.
+    #[serde(default = "default_phone_code_max_attempts")]
+    pub phone_code_max_attempts: u32,
+}
+
+impl Default for SmsSection {
+    fn default() -> Self {
+        SmsSection {
+            transport: default_sms_transport(),
+            phone_code_lifetime_in_minutes: default_phone_code_lifetime_in_minutes(),
+            phone_code_resend_cooldown_in_seconds: default_phone_code_resend_cooldown_in_seconds(),
+            phone_code_max_attempts: default_phone_code_max_attempts(),
+        }
+    }
+}
+
+fn default_sms_transport() -> String {
+    "log".to_string()
+}
+
+fn default_phone_code_lifetime_in_minutes() -> u64 {
+    10
+}
+
+fn default_phone_code_resend_cooldown_in_seconds() -> u64 {
+    60
+}
+
+fn default_phone_code_max_attempts() -> u32 {
+    5
+}
+
+/// Security settings. Without a `[security]` section, the defaults below apply.
+#[derive(Debug, Deserialize)]
+pub struct SecuritySection {
+    /// Shortest accepted password, in characters.
+    #[serde(default = "default_password_min_length")]
+    pub password_min_length: usize,
+    /// Longest accepted password, in characters. Bounds the cost of hashing.
+    #[serde(default = "default_password_max_length")]
+    pub password_max_length: usize,
+    #[serde(default)]
+    pub password_require_lowercase: bool,
+    #[serde(default)]
+    pub password_require_uppercase: bool,
+    #[serde(default)]
+    pub password_require_digit: bool,
+    /// Requires a character that is neither a letter nor a digit.
+    #[serde(default)]
+    pub password_require_symbol: bool,
+    /// Refuses passwords containing the user's names or the local part of their email.
+    #[serde(default = "default_password_disallow_personal_info")]
+    pub password_disallow_personal_info: bool,
+    /// Longest run of one repeated character, e.g. 3 refuses `aaaa`. 0 disables the rule.
+    #[serde(default = "default_password_max_repeated_characters")]
+    pub password_max_repeated_characters: usize,
+    /// Refuses passwords listed in the file at `password_blocklist_path`.
+    #[serde(default)]
+    pub enable_password_blocklist: bool,
+    /// Breached or common passwords (or their SHA-1 digests), one per line, optionally
+    /// gzip compressed. Loaded once at start-up.
+    #[serde(default)]
+    pub password_blocklist_path: Option<String>,
+    /// Argon2id memory cost. Raising a cost parameter rehashes passwords as users log in.
+    #[serde(default = "default_password_hash_memory_cost_in_kib")]
+    pub password_hash_memory_cost_in_kib: u32,
+    /// Argon2id time cost (passes over the memory).
+    #[serde(default = "default_password_hash_iterations")]
+    pub password_hash_iterations: u32,
+    /// Argon2id degree of parallelism (lanes).
+    #[serde(default = "default_password_hash_parallelism")]
+    pub password_hash_parallelism: u32,
+    /// Secret mixed into every password hash and kept out of the database. Changing or
+    /// removing it invalidates the passwords hashed with it.
+    #[serde(default)]
+    pub password_pepper: Option<String>,
+    /// Passwords hashed or verified at once, each on a blocking thread. Defaults to the
+    /// number of CPUs.
+    #[serde(default = "default_password_hash_max_concurrency")]
+    pub password_hash_max_concurrency: usize,
+    /// Requests allowed to wait for a hashing slot. Beyond that they are refused with
+    /// `503 Service Unavailable`.
+    #[serde(default = "default_password_hash_max_queued")]
+    pub password_hash_max_queued: usize,
+    /// `Retry-After` sent with those refusals.
+    #[serde(default = "default_password_hash_retry_after_in_seconds")]
+    pub password_hash_retry_after_in_seconds: u64,
+    /// Failed logins in a row that lock the account. 0 disables the lockout.
+    #[serde(default = "default_login_max_failed_attempts")]
+    pub login_max_failed_attempts: u32,
+    /// How long a locked account stays locked. Failures older than this are forgotten.
+    #[serde(default = "default_login_lockout_duration_in_minutes")]
+    pub login_lockout_duration_in_minutes: u64,
+    /// Wait after the second failed login in a row, doubled by every further one. 0
+    /// disables it.
+    #[serde(default = "default_login_backoff_base_delay_in_seconds")]
+    pub login_backoff_base_delay_in_seconds: u64,
+    /// Longest wait between two failed logins.
+    #[serde(default = "default_login_backoff_max_delay_in_seconds")]
+    pub login_backoff_max_delay_in_seconds: u64,
+    /// Service name authenticator apps show next to the account.
+    #[serde(default = "default_totp_issuer")]
+    pub totp_issuer: String,
+    /// 30-second steps a TOTP code may be early or late by, to absorb clock drift.
+    #[serde(default = "default_totp_allowed_drift_steps")]
+    pub totp_allowed_drift_steps: u64,
+}
+
+impl Default for SecuritySection {
+    fn default() -> Self {
+        SecuritySection {
+            password_min_length: default_password_min_length(),
+            password_max_length: default_password_max_length(),
+            password_require_lowercase: false,
+            password_require_uppercase: false,
+            password_require_digit: false,
+            password_require_symbol: false,
+            password_disallow_personal_info: default_password_disallow_personal_info(),
+            password_max_repeated_characters: default_password_max_repeated_characters(),
+            enable_password_blocklist: false,
+            password_blocklist_path: None,
+            password_hash_memory_cost_in_kib: default_password_hash_memory_cost_in_kib(),
+            password_hash_iterations: default_password_hash_iterations(),
+            password_hash_parallelism: default_password_hash_parallelism(),
+            password_pepper: None,
+            password_hash_max_concurrency: default_password_hash_max_concurrency(),
+            password_hash_max_queued: default_password_hash_max_queued(),
+            password_hash_retry_after_in_seconds: default_password_hash_retry_after_in_seconds(),
+            login_max_failed_attempts: default_login_max_failed_attempts(),
+            login_lockout_duration_in_minutes: default_login_lockout_duration_in_minutes(),
+            login_backoff_base_delay_in_seconds: default_login_backoff_base_delay_in_seconds(),
+            login_backoff_max_delay_in_seconds: default_login_backoff_max_delay_in_seconds(),
+            totp_issuer: default_totp_issuer(),
+            totp_allowed_drift_steps: default_totp_allowed_drift_steps(),
+        }
+    }
+}
+
+fn default_password_min_length() -> usize {
+    8
+}
+
+fn default_password_max_length() -> usize {
+    128
+}
+
+fn default_password_disallow_personal_info() -> bool {
+    true
+}
+
+fn default_password_max_repeated_characters() -> usize {
+    3
+}
+
+fn default_password_hash_memory_cost_in_kib() -> u32 {
+    argon2::Params::DEFAULT_M_COST
+}
+
+fn default_password_hash_iterations() -> u32 {
+    argon2::Params::DEFAULT_T_COST
+}
+
+fn default_password_hash_parallelism() -> u32 {
+    argon2::Params::DEFAULT_P_COST
+}
+
+fn default_password_hash_max_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(1, |cpus| cpus.get())
+}
+
+fn default_password_hash_max_queued() -> usize {
+    64
+}
+
+fn default_password_hash_retry_after_in_seconds() -> u64 {
+    1
+}
+
+fn default_login_max_failed_attempts() -> u32 {
+    5
+}
+
+fn default_login_lockout_duration_in_minutes() -> u64 {
+    15
+}
+
+fn default_login_backoff_base_delay_in_seconds() -> u64 {
+    1
+}
+
+fn default_login_backoff_max_delay_in_seconds() -> u64 {
+    30
+}
+
+fn default_totp_issuer() -> String {
+    "Krabby Chat".to_string()
+}
+
+fn default_totp_allowed_drift_steps() -> u64 {
+    1
+}
+
+/// A token bucket: up to `burst` requests at once, refilled at `per_minute` a minute.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub struct RateLimitRule {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+/// Request rate limits, enforced when `client_integrations.allow_rate_limit_middleware`
+/// is set. Each rule meters every client IP, and separately every targeted email or
+/// account. Without a `[rate_limit]` section, the defaults below apply.
+#[derive(Debug, Deserialize)]
+pub struct RateLimitSection {
+    /// One of `memory` or `postgres`. `postgres` shares the limits between instances.
+    #[serde(default = "default_rate_limit_store")]
+    pub store: String,
+    /// Every route without a rule of its own.
+    #[serde(default = "default_rate_limit_default_rule")]
+    pub default: RateLimitRule,
+    /// `POST /login`.
+    #[serde(default = "default_rate_limit_login_rule")]
+    pub login: RateLimitRule,
+    /// `POST /register`.
+    #[serde(default = "default_rate_limit_register_rule")]
+    pub register: RateLimitRule,
+    /// Routes sending or checking one-time codes: password reset, email and phone
+    /// verification.
+    #[serde(default = "default_rate_limit_one_time_password_rule")]
+    pub one_time_password: RateLimitRule,
+}
+
+impl Default for RateLimitSection {
+    fn default() -> Self {
+        RateLimitSection {
+            store: default_rate_limit_store(),
+            default: default_rate_limit_default_rule(),
+            login: default_rate_limit_login_rule(),
+            register: default_rate_limit_register_rule(),
+            one_time_password: default_rate_limit_one_time_password_rule(),
+        }
+    }
+}
+
+fn default_rate_limit_store() -> String {
+    "memory".to_string()
 }
 
-// #[derive(Debug, Deserialize)]
-// pub struct SecuritySection {
-//     pub bcrypt_cost: u32,
-//     pub rate_limit_per_minute: u32,
-// }
+fn default_rate_limit_default_rule() -> RateLimitRule {
+    RateLimitRule {
+        burst: 60,
+        per_minute: 60,
+    }
+}
+
+fn default_rate_limit_login_rule() -> RateLimitRule {
+    RateLimitRule {
+        burst: 10,
+        per_minute: 5,
+    }
+}
+
+fn default_rate_limit_register_rule() -> RateLimitRule {
+    RateLimitRule {
+        burst: 5,
+        per_minute: 2,
+    }
+}
+
+fn default_rate_limit_one_time_password_rule() -> RateLimitRule {
+    RateLimitRule {
+        burst: 5,
+        per_minute: 3,
+    }
+}
 
 /// Root configuration structure containing all application settings.
 #[derive(Debug, Deserialize)]
@@ -86,7 +528,10 @@ pub struct AppConfig {
     pub server: Option<ServerSection>,
     pub database: Option<DatabaseSection>,
     pub auth: Option<AuthSection>,
-    // pub security: Option<SecuritySection>,
+    pub mail: Option<MailSection>,
+    pub sms: Option<SmsSection>,
+    pub security: Option<SecuritySection>,
+    pub rate_limit: Option<RateLimitSection>,
 }
 
 /// Loads the application configuration.
@@ -160,6 +605,24 @@ pub enum ConfigError {
     MissingDatabasePassword,
     MissingAuthSection,
     MissingJwtSecret,
+    UnsupportedJwtAlgorithm,
+    MissingJwtKeyPath,
+    InvalidRetiredJwtKey,
+    UnsupportedMailTransport,
+    InvalidMailFromAddress,
+    MissingMailSetting,
+    InsecureProductionMail,
+    UnsupportedSmsTransport,
+    InsecureProductionSms,
+    InvalidPhoneCodeMaxAttempts,
+    InvalidPasswordLengthLimits,
+    MissingPasswordBlocklistPath,
+    InvalidPasswordHashParameters,
+    InvalidPasswordHashMaxConcurrency,
+    InvalidLoginLockoutDuration,
+    MissingTotpIssuer,
+    UnsupportedRateLimitStore,
+    InvalidRateLimitRule,
 }
 
 impl fmt::Display for ConfigError {
@@ -174,6 +637,80 @@ impl fmt::Display for ConfigError {
             ConfigError::MissingDatabasePassword => write!(f, "database.password cannot be empty"),
             ConfigError::MissingAuthSection => write!(f, "auth section is missing"),
             ConfigError::MissingJwtSecret => write!(f, "auth.jwt_secret cannot be empty"),
+            ConfigError::UnsupportedJwtAlgorithm => write!(
+                f,
+                "auth.jwt_algorithm must be one of {}",
+                SUPPORTED_JWT_ALGORITHMS.join(", ")
+            ),
+            ConfigError::MissingJwtKeyPath => write!(
+                f,
+                "auth.jwt_private_key_path and auth.jwt_public_key_path are required for asymmetric algorithms"
+            ),
+            ConfigError::InvalidRetiredJwtKey => write!(
+                f,
+                "auth.jwt_retired_keys entries need a key_id, a supported algorithm and a secret or public_key_path"
+            ),
+            ConfigError::UnsupportedMailTransport => write!(
+                f,
+                "mail.transport must be one of {} and mail.smtp_security one of {}",
+                SUPPORTED_MAIL_TRANSPORTS.join(", "),
+                SUPPORTED_SMTP_SECURITY.join(", ")
+            ),
+            ConfigError::InvalidMailFromAddress => {
+                write!(f, "mail.from_address must be a valid mailbox")
+            }
+            ConfigError::MissingMailSetting => write!(
+                f,
+                "mail.smtp_host is required for the smtp transport and mail.drop_directory for the file transport"
+            ),
+            ConfigError::InsecureProductionMail => write!(
+                f,
+                "a mail section with the smtp or file transport is required in production"
+            ),
+            ConfigError::UnsupportedSmsTransport => write!(
+                f,
+                "sms.transport must be one of {}",
+                SUPPORTED_SMS_TRANSPORTS.join(", ")
+            ),
+            ConfigError::InsecureProductionSms => write!(
+                f,
+                "an sms section with a transport other than log or memory is required in production"
+            ),
+            ConfigError::InvalidPhoneCodeMaxAttempts => {
+                write!(f, "sms.phone_code_max_attempts cannot be 0")
+            }
+            ConfigError::InvalidPasswordLengthLimits => write!(
+                f,
+                "security.password_min_length must be at least 1 and at most security.password_max_length"
+            ),
+            ConfigError::MissingPasswordBlocklistPath => write!(
+                f,
+                "security.password_blocklist_path is required when security.enable_password_blocklist is set"
+            ),
+            ConfigError::InvalidPasswordHashParameters => write!(
+                f,
+                "security.password_hash_memory_cost_in_kib, password_hash_iterations and password_hash_parallelism must be valid Argon2 parameters"
+            ),
+            ConfigError::InvalidPasswordHashMaxConcurrency => write!(
+                f,
+                "security.password_hash_max_concurrency must be greater than 0"
+            ),
+            ConfigError::InvalidLoginLockoutDuration => write!(
+                f,
+                "security.login_lockout_duration_in_minutes must be greater than 0 when security.login_max_failed_attempts is set"
+            ),
+            ConfigError::MissingTotpIssuer => {
+                write!(f, "security.totp_issuer cannot be empty or contain a colon")
+            }
+            ConfigError::UnsupportedRateLimitStore => write!(
+                f,
+                "rate_limit.store must be one of: {}",
+                SUPPORTED_RATE_LIMIT_STORES.join(", ")
+            ),
+            ConfigError::InvalidRateLimitRule => write!(
+                f,
+                "every rate_limit rule must have a burst and per_minute greater than 0"
+            ),
         }
     }
 }
@@ -181,6 +718,11 @@ impl fmt::Display for ConfigError {
 impl std::error::Error for ConfigError {}
 
 impl AppConfig {
+    /// Whether `app.environment` is `production`, as it is for staging too.
+    fn is_production(&self) -> bool {
+        self.app.environment.as_deref() == Some("production")
+    }
+
     pub fn validate(&self) -> std::result::Result<(), ConfigError> {
         // Check app name
         if self.app.name.trim().is_empty() {
@@ -223,8 +765,151 @@ impl AppConfig {
 
         // Check auth
         let auth = self.auth.as_ref().ok_or(ConfigError::MissingAuthSection)?;
-        if auth.jwt_secret.trim().is_empty() {
-            return Err(ConfigError::MissingJwtSecret);
+        if !SUPPORTED_JWT_ALGORITHMS.contains(&auth.jwt_algorithm.as_str()) {
+            return Err(ConfigError::UnsupportedJwtAlgorithm);
+        }
+        if auth.jwt_algorithm == "HS256" {
+            if auth.jwt_secret.trim().is_empty() {
+                return Err(ConfigError::MissingJwtSecret);
+            }
+        } else if [&auth.jwt_private_key_path, &auth.jwt_public_key_path]
+            .iter()
+            .any(|path| path.as_ref().map(|s| s.trim().is_empty()).unwrap_or(true))
+        {
+            return Err(ConfigError::MissingJwtKeyPath);
+        }
+        for retired in &auth.jwt_retired_keys {
+            let key_material = if retired.algorithm == "HS256" {
+                &retired.secret
+            } else {
+                &retired.public_key_path
+            };
+
+            if retired.key_id.trim().is_empty()
+                || !SUPPORTED_JWT_ALGORITHMS.contains(&retired.algorithm.as_str())
+                || key_material
+                    .as_ref()
+                    .map(|s| s.trim().is_empty())
+                    .unwrap_or(true)
+            {
+                return Err(ConfigError::InvalidRetiredJwtKey);
+            }
+        }
+
+        // Check mail
+        // stdout and memory would leave one-time passwords and reset links in the logs
+        if self.is_production()
+            && !matches!(
+                self.mail.as_ref().map(|mail| mail.transport.as_str()),
+                Some("smtp" | "file")
+            )
+        {
+            return Err(ConfigError::InsecureProductionMail);
+        }
+        if let Some(mail) = &self.mail {
+            if !SUPPORTED_MAIL_TRANSPORTS.contains(&mail.transport.as_str())
+                || !SUPPORTED_SMTP_SECURITY.contains(&mail.smtp_security.as_str())
+            {
+                return Err(ConfigError::UnsupportedMailTransport);
+            }
+            if mail
+                .from_address
+                .parse::<lettre::message::Mailbox>()
+                .is_err()
+            {
+                return Err(ConfigError::InvalidMailFromAddress);
+            }
+
+            let required_setting = match mail.transport.as_str() {
+                "smtp" => Some(&mail.smtp_host),
+                "file" => Some(&mail.drop_directory),
+                _ => None,
+            };
+            if let Some(setting) = required_setting
+                && setting
+                    .as_ref()
+                    .map(|s| s.trim().is_empty())
+                    .unwrap_or(true)
+            {
+                return Err(ConfigError::MissingMailSetting);
+            }
+        }
+
+        // Check SMS
+        // log and memory would leave phone verification codes in the logs
+        if self.is_production()
+            && self
+                .sms
+                .as_ref()
+                .is_none_or(|sms| matches!(sms.transport.as_str(), "log" | "memory"))
+        {
+            return Err(ConfigError::InsecureProductionSms);
+        }
+        if let Some(sms) = &self.sms {
+            if !SUPPORTED_SMS_TRANSPORTS.contains(&sms.transport.as_str()) {
+                return Err(ConfigError::UnsupportedSmsTransport);
+            }
+            if sms.phone_code_max_attempts == 0 {
+                return Err(ConfigError::InvalidPhoneCodeMaxAttempts);
+            }
+        }
+
+        // Check security
+        if let Some(security) = &self.security {
+            if security.password_min_length == 0
+                || security.password_min_length > security.password_max_length
+            {
+                return Err(ConfigError::InvalidPasswordLengthLimits);
+            }
+            if security.enable_password_blocklist
+                && security
+                    .password_blocklist_path
+                    .as_ref()
+                    .map(|path| path.trim().is_empty())
+                    .unwrap_or(true)
+            {
+                return Err(ConfigError::MissingPasswordBlocklistPath);
+            }
+            if argon2::Params::new(
+                security.password_hash_memory_cost_in_kib,
+                security.password_hash_iterations,
+                security.password_hash_parallelism,
+                None,
+            )
+            .is_err()
+            {
+                return Err(ConfigError::InvalidPasswordHashParameters);
+            }
+            if security.password_hash_max_concurrency == 0 {
+                return Err(ConfigError::InvalidPasswordHashMaxConcurrency);
+            }
+            if security.login_max_failed_attempts > 0
+                && security.login_lockout_duration_in_minutes == 0
+            {
+                return Err(ConfigError::InvalidLoginLockoutDuration);
+            }
+            // A colon would split the issuer from the account in provisioning URIs
+            if security.totp_issuer.trim().is_empty() || security.totp_issuer.contains(':') {
+                return Err(ConfigError::MissingTotpIssuer);
+            }
+        }
+
+        // Check rate limits
+        if let Some(rate_limit) = &self.rate_limit {
+            if !SUPPORTED_RATE_LIMIT_STORES.contains(&rate_limit.store.as_str()) {
+                return Err(ConfigError::UnsupportedRateLimitStore);
+            }
+            if [
+                &rate_limit.default,
+                &rate_limit.login,
+                &rate_limit.register,
+                &rate_limit.one_time_password,
+            ]
+            .iter()
+            .any(|rule| rule.burst == 0 || rule.per_minute == 0)
+            {
+                return Err(ConfigError::InvalidRateLimitRule);
+            }
         }
 
         Ok(())
@@ -248,6 +933,18 @@ mod tests {
             jwt_access_expiration_time_in_hours: 1,
             jwt_refresh_expiration_time_in_hours: 24,
             jwt_one_time_password_lifetime_in_minutes: 5,
+            jwt_email_verification_lifetime_in_hours: 24,
+            jwt_mfa_pending_lifetime_in_minutes: 5,
+            require_verified_email: false,
+            enumeration_resistant_registration: false,
+            jwt_algorithm: "HS256".to_string(),
+            jwt_private_key_path: None,
+            jwt_public_key_path: None,
+            jwt_key_id: None,
+            jwt_issuer: "chat_auth_server".to_string(),
+            jwt_audience: "krabby_chat".to_string(),
+            jwt_retired_key_grace_period_in_hours: None,
+            jwt_retired_keys: Vec::new(),
         }
     }
 
@@ -261,6 +958,7 @@ mod tests {
                 allow_logging_middleware: true,
                 allow_request_timeout_middleware: true,
                 allow_admin_routes_protector_middleware: true,
+                allow_rate_limit_middleware: true,
             },
             observability: ObservabilitySection {
                 enable_tracing: true,
@@ -270,6 +968,7 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
                 request_timeout_secs: 60,
+                trust_proxy_headers: false,
             }),
             database: Some(DatabaseSection {
                 engine: "postgres".to_string(),
@@ -281,6 +980,10 @@ mod tests {
                 max_connections: 5,
                 connect_timeout_secs: 3,
             }),
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
             auth: Some(valid_auth_section()),
         };
 
@@ -297,6 +1000,7 @@ mod tests {
                 allow_logging_middleware: false,
                 allow_request_timeout_middleware: false,
                 allow_admin_routes_protector_middleware: false,
+                allow_rate_limit_middleware: false,
             },
             observability: ObservabilitySection {
                 enable_tracing: false,
@@ -306,6 +1010,7 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
                 request_timeout_secs: 60,
+                trust_proxy_headers: false,
             }),
             database: Some(DatabaseSection {
                 engine: "postgres".to_string(),
@@ -317,6 +1022,10 @@ mod tests {
                 max_connections: 5,
                 connect_timeout_secs: 3,
             }),
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
             auth: Some(valid_auth_section()),
         };
         config.app.name = "".to_string();
@@ -336,6 +1045,7 @@ mod tests {
                 allow_logging_middleware: false,
                 allow_request_timeout_middleware: false,
                 allow_admin_routes_protector_middleware: false,
+                allow_rate_limit_middleware: false,
             },
             observability: ObservabilitySection {
                 enable_tracing: false,
@@ -345,6 +1055,7 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port: 0,
                 request_timeout_secs: 60,
+                trust_proxy_headers: false,
             }),
             database: Some(DatabaseSection {
                 engine: "postgres".to_string(),
@@ -356,6 +1067,10 @@ mod tests {
                 max_connections: 5,
                 connect_timeout_secs: 3,
             }),
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
             auth: Some(valid_auth_section()),
         };
 
@@ -374,6 +1089,7 @@ mod tests {
                 allow_logging_middleware: false,
                 allow_request_timeout_middleware: false,
                 allow_admin_routes_protector_middleware: false,
+                allow_rate_limit_middleware: false,
             },
             observability: ObservabilitySection {
                 enable_tracing: false,
@@ -383,6 +1099,7 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
                 request_timeout_secs: 60,
+                trust_proxy_headers: false,
             }),
             database: Some(DatabaseSection {
                 engine: "postgres".to_string(),
@@ -394,6 +1111,10 @@ mod tests {
                 max_connections: 5,
                 connect_timeout_secs: 3,
             }),
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
             auth: Some(valid_auth_section()),
         };
 
@@ -415,6 +1136,7 @@ mod tests {
                 allow_logging_middleware: false,
                 allow_request_timeout_middleware: false,
                 allow_admin_routes_protector_middleware: false,
+                allow_rate_limit_middleware: false,
             },
             observability: ObservabilitySection {
                 enable_tracing: false,
@@ -422,6 +1144,10 @@ mod tests {
             },
             server: None,
             database: None,
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
             auth: None,
         };
 
@@ -429,4 +1155,340 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().to_string(), "server section is missing");
     }
+
+    fn config_with_auth(auth: AuthSection) -> AppConfig {
+        AppConfig {
+            app: valid_app_section(),
+            client_integrations: ClientIntegrationsSection {
+                allow_access_middleware: false,
+                allow_sessions_middleware: false,
+                allow_logging_middleware: false,
+                allow_request_timeout_middleware: false,
+                allow_admin_routes_protector_middleware: false,
+                allow_rate_limit_middleware: false,
+            },
+            observability: ObservabilitySection {
+                enable_tracing: false,
+                enable_metrics: false,
+            },
+            server: Some(ServerSection {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                request_timeout_secs: 60,
+                trust_proxy_headers: false,
+            }),
+            database: Some(DatabaseSection {
+                engine: "postgres".to_string(),
+                host: "localhost".to_string(),
+                port: 5432,
+                user: Some("test".to_string()),
+                password: Some("pass".to_string()),
+                name: "db".to_string(),
+                max_connections: 5,
+                connect_timeout_secs: 3,
+            }),
+            mail: None,
+            sms: None,
+            security: None,
+            rate_limit: None,
+            auth: Some(auth),
+        }
+    }
+
+    #[test]
+    fn test_validate_jwt_algorithm() {
+        let mut auth = valid_auth_section();
+        auth.jwt_algorithm = "none".to_string();
+
+        let result = config_with_auth(auth).validate();
+        assert!(matches!(result, Err(ConfigError::UnsupportedJwtAlgorithm)));
+    }
+
+    #[test]
+    fn test_validate_asymmetric_keys() {
+        // Asymmetric algorithms need both key paths but no shared secret
+        let mut auth = valid_auth_section();
+        auth.jwt_secret = "".to_string();
+        auth.jwt_algorithm = "RS256".to_string();
+        auth.jwt_private_key_path = Some("keys/private.pem".to_string());
+
+        let result = config_with_auth(auth).validate();
+        assert!(matches!(result, Err(ConfigError::MissingJwtKeyPath)));
+
+        let mut auth = valid_auth_section();
+        auth.jwt_secret = "".to_string();
+        auth.jwt_algorithm = "RS256".to_string();
+        auth.jwt_private_key_path = Some("keys/private.pem".to_string());
+        auth.jwt_public_key_path = Some("keys/public.pem".to_string());
+
+        assert!(config_with_auth(auth).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_retired_keys() {
+        let mut auth = valid_auth_section();
+        auth.jwt_retired_keys.push(RetiredJwtKeySection {
+            key_id: "2026-04".to_string(),
+            algorithm: "RS256".to_string(),
+            secret: Some("old_secret".to_string()),
+            public_key_path: None,
+            valid_until: Utc::now(),
+        });
+
+        let result = config_with_auth(auth).validate();
+        assert!(matches!(result, Err(ConfigError::InvalidRetiredJwtKey)));
+
+        let mut auth = valid_auth_section();
+        auth.jwt_retired_keys.push(RetiredJwtKeySection {
+            key_id: "2026-04".to_string(),
+            algorithm: "HS256".to_string(),
+            secret: Some("old_secret".to_string()),
+            public_key_path: None,
+            valid_until: Utc::now(),
+        });
+
+        assert!(config_with_auth(auth).validate().is_ok());
+    }
+
+    #[test]
+    fn test_retired_key_grace_period_defaults_to_refresh_lifetime() {
+        let mut auth = valid_auth_section();
+        assert_eq!(auth.retired_key_grace_period_in_hours(), 24);
+
+        auth.jwt_retired_key_grace_period_in_hours = Some(48);
+        assert_eq!(auth.retired_key_grace_period_in_hours(), 48);
+    }
+
+    #[test]
+    fn test_validate_production_mail() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.app.environment = Some("production".to_string());
+        config.sms = Some(SmsSection {
+            transport: "disabled".to_string(),
+            ..SmsSection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InsecureProductionMail)
+        ));
+
+        for transport in ["stdout", "memory"] {
+            config.mail = Some(MailSection {
+                transport: transport.to_string(),
+                ..MailSection::default()
+            });
+            assert!(matches!(
+                config.validate(),
+                Err(ConfigError::InsecureProductionMail)
+            ));
+        }
+
+        config.mail = Some(MailSection {
+            transport: "smtp".to_string(),
+            smtp_host: Some("smtp.example.com".to_string()),
+            ..MailSection::default()
+        });
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_sms() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.sms = Some(SmsSection::default());
+        assert!(config.validate().is_ok());
+
+        config.sms = Some(SmsSection {
+            transport: "carrier-pigeon".to_string(),
+            ..SmsSection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnsupportedSmsTransport)
+        ));
+
+        config.sms = Some(SmsSection {
+            phone_code_max_attempts: 0,
+            ..SmsSection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidPhoneCodeMaxAttempts)
+        ));
+    }
+
+    #[test]
+    fn test_validate_production_sms() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.app.environment = Some("production".to_string());
+        config.mail = Some(MailSection {
+            transport: "smtp".to_string(),
+            smtp_host: Some("smtp.example.com".to_string()),
+            ..MailSection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InsecureProductionSms)
+        ));
+
+        for transport in ["log", "memory"] {
+            config.sms = Some(SmsSection {
+                transport: transport.to_string(),
+                ..SmsSection::default()
+            });
+            assert!(matches!(
+                config.validate(),
+                Err(ConfigError::InsecureProductionSms)
+            ));
+        }
+
+        config.sms = Some(SmsSection {
+            transport: "disabled".to_string(),
+            ..SmsSection::default()
+        });
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_password_length_limits() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.security = Some(SecuritySection::default());
+        assert!(config.validate().is_ok());
+
+        config.security = Some(SecuritySection {
+            password_min_length: 0,
+            ..SecuritySection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidPasswordLengthLimits)
+        ));
+
+        config.security = Some(SecuritySection {
+            password_min_length: 20,
+            password_max_length: 16,
+            ..SecuritySection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidPasswordLengthLimits)
+        ));
+    }
+
+    #[test]
+    fn test_validate_password_blocklist() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.security = Some(SecuritySection {
+            enable_password_blocklist: true,
+            ..SecuritySection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::MissingPasswordBlocklistPath)
+        ));
+
+        config.security = Some(SecuritySection {
+            enable_password_blocklist: true,
+            password_blocklist_path: Some("config/password_blocklist.txt".to_string()),
+            ..SecuritySection::default()
+        });
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_password_hash_parameters() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.security = Some(SecuritySection {
+            password_hash_memory_cost_in_kib: 65536,
+            password_hash_iterations: 3,
+            password_hash_parallelism: 4,
+            ..SecuritySection::default()
+        });
+        assert!(config.validate().is_ok());
+
+        config.security = Some(SecuritySection {
+            password_hash_memory_cost_in_kib: 1,
+            ..SecuritySection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidPasswordHashParameters)
+        ));
+    }
+
+    #[test]
+    fn test_validate_password_hash_max_concurrency() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.security = Some(SecuritySection {
+            password_hash_max_concurrency: 0,
+            ..SecuritySection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidPasswordHashMaxConcurrency)
+        ));
+    }
+
+    #[test]
+    fn test_validate_login_lockout_duration() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.security = Some(SecuritySection {
+            login_lockout_duration_in_minutes: 0,
+            ..SecuritySection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidLoginLockoutDuration)
+        ));
+
+        // Without a lockout the duration is unused
+        config.security = Some(SecuritySection {
+            login_max_failed_attempts: 0,
+            login_lockout_duration_in_minutes: 0,
+            ..SecuritySection::default()
+        });
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_totp_issuer() {
+        let mut config = config_with_auth(valid_auth_section());
+        for issuer in ["", "  ", "Krabby:Chat"] {
+            config.security = Some(SecuritySection {
+                totp_issuer: issuer.to_string(),
+                ..SecuritySection::default()
+            });
+            assert!(matches!(
+                config.validate(),
+                Err(ConfigError::MissingTotpIssuer)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_rate_limit() {
+        let mut config = config_with_auth(valid_auth_section());
+        config.rate_limit = Some(RateLimitSection::default());
+        assert!(config.validate().is_ok());
+
+        config.rate_limit = Some(RateLimitSection {
+            store: "redis".to_string(),
+            ..RateLimitSection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnsupportedRateLimitStore)
+        ));
+
+        config.rate_limit = Some(RateLimitSection {
+            login: RateLimitRule {
+                burst: 5,
+                per_minute: 0,
+            },
+            ..RateLimitSection::default()
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidRateLimitRule)
+        ));
+    }
 }
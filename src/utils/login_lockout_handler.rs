@@ -0,0 +1,357 @@
+//! # Login Lockout
+//!
+//! Failed logins are counted per account in `users.failed_login_attempts`, next to the
+//! moment of the latest one. A single failure, most likely a typo, costs nothing; every
+//! further one makes the account wait before the next attempt, twice as long as after the
+//! previous failure, up to `security.login_backoff_max_delay_in_seconds`. Reaching
+//! `security.login_max_failed_attempts` locks the account until `users.locked_until`.
+//!
+//! The lock lifts by itself after `security.login_lockout_duration_in_minutes`, or earlier
+//! when an admin unlocks the account. Failures older than that duration are forgotten, and
+//! a successful login or password reset clears the count.
+//!
+//! Failed logins for emails no account has are counted the same way, in
+//! `unknown_email_login_failures`, so the answers to a login never reveal whether an email
+//! is registered.
+
+use crate::utils::load_config::SecuritySection;
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+
+/// Why a login attempt is refused before the password is even checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoginBlock {
+    /// Too many failed logins in a row; the account is locked.
+    Locked { retry_after_in_seconds: u64 },
+    /// The previous attempt failed too recently.
+    BackOff { retry_after_in_seconds: u64 },
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct LoginAttempts {
+    failed_login_attempts: i32,
+    /// Seconds until `locked_until`, negative once it has passed.
+    locked_for: Option<f64>,
+    /// Seconds since `last_failed_login_at`.
+    since_last_failure: Option<f64>,
+}
+
+/// The wait after `failed_attempts` failed logins in a row, in seconds.
+pub fn backoff_delay_in_seconds(failed_attempts: u32, security: &SecuritySection) -> u64 {
+    if failed_attempts < 2 {
+        return 0;
+    }
+
+    let factor = 2u64.saturating_pow(failed_attempts - 2);
+
+    security
+        .login_backoff_base_delay_in_seconds
+        .saturating_mul(factor)
+        .min(security.login_backoff_max_delay_in_seconds)
+}
+
+/// Seconds to wait, rounded up, for something `remaining` seconds away.
+fn seconds_left(remaining: Option<f64>) -> Option<u64> {
+    remaining
+        .filter(|seconds| *seconds > 0.0)
+        .map(|seconds| seconds.ceil() as u64)
+}
+
+fn block_for(attempts: &LoginAttempts, security: &SecuritySection) -> Option<LoginBlock> {
+    if let Some(retry_after_in_seconds) = seconds_left(attempts.locked_for) {
+        return Some(LoginBlock::Locked {
+            retry_after_in_seconds,
+        });
+    }
+
+    let delay = backoff_delay_in_seconds(
+        u32::try_from(attempts.failed_login_attempts).unwrap_or(0),
+        security,
+    );
+    let remaining = attempts
+        .since_last_failure
+        .map(|since_last_failure| delay as f64 - since_last_failure);
+
+    seconds_left(remaining).map(|retry_after_in_seconds| LoginBlock::BackOff {
+        retry_after_in_seconds,
+    })
+}
+
+/// Whether the user must not attempt a login right now, and for how long.
+pub async fn login_block<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    security: &SecuritySection,
+) -> Result<Option<LoginBlock>, sqlx::Error> {
+    let attempts = sqlx::query_as::<_, LoginAttempts>(
+        r#"
+        SELECT
+            failed_login_attempts,
+            EXTRACT(EPOCH FROM locked_until - NOW())::float8 AS locked_for,
+            EXTRACT(EPOCH FROM NOW() - last_failed_login_at)::float8 AS since_last_failure
+        FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(attempts.and_then(|attempts| block_for(&attempts, security)))
+}
+
+/// Counts a failed login and locks the account once it reaches
+/// `security.login_max_failed_attempts`.
+///
+/// Returns the lock the failure caused, if any.
+pub async fn record_failed_login<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    security: &SecuritySection,
+) -> Result<Option<LoginBlock>, sqlx::Error> {
+    // The count restarts once the previous failure is older than the lockout duration,
+    // which includes every failure after a lock has expired
+    let locked_for: Option<f64> = sqlx::query_scalar(
+        r#"
+        WITH counted AS (
+            SELECT
+                id,
+                CASE
+                    WHEN last_failed_login_at > NOW() - make_interval(mins => $3)
+                        THEN failed_login_attempts + 1
+                    ELSE 1
+                END AS failed_login_attempts
+            FROM users
+            WHERE id = $1
+            FOR UPDATE
+        )
+        UPDATE users
+        SET
+            failed_login_attempts = counted.failed_login_attempts,
+            last_failed_login_at = NOW(),
+            locked_until = CASE
+                WHEN $2 > 0 AND counted.failed_login_attempts >= $2
+                    THEN NOW() + make_interval(mins => $3)
+                ELSE NULL
+            END
+        FROM counted
+        WHERE users.id = counted.id
+        RETURNING EXTRACT(EPOCH FROM users.locked_until - NOW())::float8
+        "#,
+    )
+    .bind(user_id)
+    .bind(i32::try_from(security.login_max_failed_attempts).unwrap_or(i32::MAX))
+    .bind(i32::try_from(security.login_lockout_duration_in_minutes).unwrap_or(i32::MAX))
+    .fetch_optional(executor)
+    .await?
+    .flatten();
+
+    Ok(
+        seconds_left(locked_for).map(|retry_after_in_seconds| LoginBlock::Locked {
+            retry_after_in_seconds,
+        }),
+    )
+}
+
+/// The key failed logins for an email no account has are counted under, so the email
+/// itself is not kept.
+fn unknown_email_hash(email: &str) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(email.trim().to_lowercase().as_bytes())
+    )
+}
+
+/// Like [`login_block`], for an email no account has.
+pub async fn unknown_email_login_block<'e, E: PgExecutor<'e>>(
+    executor: E,
+    email: &str,
+    security: &SecuritySection,
+) -> Result<Option<LoginBlock>, sqlx::Error> {
+    let attempts = sqlx::query_as::<_, LoginAttempts>(
+        r#"
+        SELECT
+            failed_login_attempts,
+            EXTRACT(EPOCH FROM locked_until - NOW())::float8 AS locked_for,
+            EXTRACT(EPOCH FROM NOW() - last_failed_login_at)::float8 AS since_last_failure
+        FROM unknown_email_login_failures
+        WHERE email_hash = $1
+        "#,
+    )
+    .bind(unknown_email_hash(email))
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(attempts.and_then(|attempts| block_for(&attempts, security)))
+}
+
+/// Like [`record_failed_login`], for an email no account has.
+///
+/// Also deletes the failures of other emails that are older than the lockout duration,
+/// which would be forgotten anyway.
+pub async fn record_failed_unknown_email_login<'e, E: PgExecutor<'e>>(
+    executor: E,
+    email: &str,
+    security: &SecuritySection,
+) -> Result<Option<LoginBlock>, sqlx::Error> {
+    let locked_for: Option<f64> = sqlx::query_scalar(
+        r#"
+        WITH swept AS (
+            DELETE FROM unknown_email_login_failures
+            WHERE email_hash <> $1
+                AND last_failed_login_at < NOW() - make_interval(mins => $3)
+        )
+        INSERT INTO unknown_email_login_failures AS failures
+            (email_hash, failed_login_attempts, last_failed_login_at, locked_until)
+        VALUES (
+            $1,
+            1,
+            NOW(),
+            CASE WHEN $2 > 0 AND 1 >= $2 THEN NOW() + make_interval(mins => $3) END
+        )
+        ON CONFLICT (email_hash) DO UPDATE
+        SET
+            failed_login_attempts = CASE
+                WHEN failures.last_failed_login_at > NOW() - make_interval(mins => $3)
+                    THEN failures.failed_login_attempts + 1
+                ELSE 1
+            END,
+            last_failed_login_at = NOW(),
+            locked_until = CASE
+                WHEN $2 > 0
+                    AND CASE
+                        WHEN failures.last_failed_login_at > NOW() - make_interval(mins => $3)
+                            THEN failures.failed_login_attempts + 1
+                        ELSE 1
+                    END >= $2
+                    THEN NOW() + make_interval(mins => $3)
+                ELSE NULL
+            END
+        RETURNING EXTRACT(EPOCH FROM failures.locked_until - NOW())::float8
+        "#,
+    )
+    .bind(unknown_email_hash(email))
+    .bind(i32::try_from(security.login_max_failed_attempts).unwrap_or(i32::MAX))
+    .bind(i32::try_from(security.login_lockout_duration_in_minutes).unwrap_or(i32::MAX))
+    .fetch_one(executor)
+    .await?;
+
+    Ok(
+        seconds_left(locked_for).map(|retry_after_in_seconds| LoginBlock::Locked {
+            retry_after_in_seconds,
+        }),
+    )
+}
+
+/// Forgets the user's failed logins and lifts any lock.
+///
+/// Returns `false` when the user does not exist.
+pub async fn clear_failed_logins<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            failed_login_attempts = 0,
+            last_failed_login_at = NULL,
+            locked_until = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempts(
+        failed_login_attempts: i32,
+        locked_for: Option<f64>,
+        since_last_failure: Option<f64>,
+    ) -> LoginAttempts {
+        LoginAttempts {
+            failed_login_attempts,
+            locked_for,
+            since_last_failure,
+        }
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_up_to_the_maximum() {
+        let security = SecuritySection {
+            login_backoff_base_delay_in_seconds: 2,
+            login_backoff_max_delay_in_seconds: 10,
+            ..SecuritySection::default()
+        };
+
+        let delays: Vec<u64> = (0..6)
+            .map(|failed_attempts| backoff_delay_in_seconds(failed_attempts, &security))
+            .collect();
+        assert_eq!(delays, vec![0, 0, 2, 4, 8, 10]);
+        assert_eq!(backoff_delay_in_seconds(u32::MAX, &security), 10);
+    }
+
+    #[test]
+    fn test_backoff_can_be_disabled() {
+        let security = SecuritySection {
+            login_backoff_base_delay_in_seconds: 0,
+            ..SecuritySection::default()
+        };
+
+        assert_eq!(backoff_delay_in_seconds(4, &security), 0);
+        assert_eq!(block_for(&attempts(4, None, Some(0.0)), &security), None);
+    }
+
+    #[test]
+    fn test_unknown_email_hash_ignores_case_and_whitespace() {
+        assert_eq!(
+            unknown_email_hash(" Someone@Example.com"),
+            unknown_email_hash("someone@example.com")
+        );
+        assert_ne!(
+            unknown_email_hash("someone@example.com"),
+            unknown_email_hash("someone.else@example.com")
+        );
+    }
+
+    #[test]
+    fn test_block_for() {
+        let security = SecuritySection {
+            login_backoff_base_delay_in_seconds: 1,
+            login_backoff_max_delay_in_seconds: 30,
+            ..SecuritySection::default()
+        };
+
+        // No failures yet
+        assert_eq!(block_for(&attempts(0, None, None), &security), None);
+        // A single failure is free
+        assert_eq!(block_for(&attempts(1, None, Some(0.0)), &security), None);
+        // Fourth failure half a second ago: 4 seconds to wait in all
+        assert_eq!(
+            block_for(&attempts(4, None, Some(0.5)), &security),
+            Some(LoginBlock::BackOff {
+                retry_after_in_seconds: 4
+            })
+        );
+        // The back-off has passed
+        assert_eq!(block_for(&attempts(4, None, Some(4.0)), &security), None);
+        // A lock wins over the back-off, and lifts by itself
+        assert_eq!(
+            block_for(&attempts(5, Some(899.2), Some(0.8)), &security),
+            Some(LoginBlock::Locked {
+                retry_after_in_seconds: 900
+            })
+        );
+        assert_eq!(
+            block_for(&attempts(5, Some(-1.0), Some(901.0)), &security),
+            None
+        );
+    }
+}
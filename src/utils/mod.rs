@@ -1,7 +1,19 @@
+pub mod client_metadata;
 pub mod cookie_deploy_handler;
 pub mod current_time_in_milliseconds;
+pub mod email_verification_handler;
 pub mod generate_tokens;
 pub mod hashing_handler;
+pub mod jwt_keys;
 pub mod load_config;
 pub mod load_env;
+pub mod login_lockout_handler;
+pub mod one_time_password_handler;
+pub mod password_blocklist;
+pub mod password_policy;
+pub mod phone_verification_handler;
+pub mod recovery_codes_handler;
+pub mod session_handler;
+pub mod step_up_handler;
+pub mod totp_handler;
 pub mod verification_handler;
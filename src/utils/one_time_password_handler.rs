@@ -0,0 +1,72 @@
+//! # One-Time Password Persistence
+//!
+//! A user has at most one outstanding one-time password. Only its SHA-256 digest is
+//! stored in `users.one_time_password_token`, next to the moment it expires, and it is
+//! cleared the moment it is used, so every one-time password works exactly once.
+
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+
+/// Returns the hex-encoded SHA-256 digest under which a one-time password is stored.
+pub fn hash_one_time_password(one_time_password: &str) -> String {
+    format!("{:x}", Sha256::digest(one_time_password.as_bytes()))
+}
+
+/// Stores a freshly issued one-time password, replacing any outstanding one.
+///
+/// # Arguments
+/// - `executor`: A pool or an open transaction.
+/// - `user_id`: Owner of the one-time password.
+/// - `one_time_password`: The encoded token; only its digest is stored.
+/// - `lifetime_in_minutes`: How long it stays valid, matching its `exp` claim.
+pub async fn store_one_time_password<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    one_time_password: &str,
+    lifetime_in_minutes: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            one_time_password_token = $1,
+            one_time_password_expires_at = NOW() + make_interval(mins => $2),
+            updated_at = NOW()
+        WHERE id = $3
+        "#,
+    )
+    .bind(hash_one_time_password(one_time_password))
+    .bind(i32::try_from(lifetime_in_minutes).unwrap_or(i32::MAX))
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Uses up the outstanding one-time password of a user.
+///
+/// Returns `false` when `one_time_password` is not the user's outstanding one, or when
+/// it has expired. Two concurrent calls can never both succeed.
+pub async fn consume_one_time_password<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    one_time_password: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            one_time_password_token = NULL,
+            one_time_password_expires_at = NULL,
+            updated_at = NOW()
+        WHERE id = $1 AND one_time_password_token = $2 AND one_time_password_expires_at > NOW()
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_one_time_password(one_time_password))
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
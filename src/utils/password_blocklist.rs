@@ -0,0 +1,195 @@
+//! # Password Blocklist
+//!
+//! An offline list of breached or common passwords that users may not choose. The list
+//! is read once at start-up from `security.password_blocklist_path`: a text file, gzip
+//! compressed when its name ends in `.gz`, with one entry per line. An entry is either
+//! a password or the hex SHA-1 digest of one, optionally followed by `:<count>` as in the
+//! Have I Been Pwned downloads. Blank lines and lines starting with `#` are skipped.
+//!
+//! Only SHA-1 digests are kept in memory, sorted, so a lookup is a binary search.
+
+use crate::utils::load_config::SecuritySection;
+use flate2::read::GzDecoder;
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+type Sha1Digest = [u8; 20];
+
+/// Known-breached and common passwords. Empty, blocking nothing, unless loaded.
+#[derive(Debug, Default)]
+pub struct PasswordBlocklist {
+    digests: Vec<Sha1Digest>,
+}
+
+impl PasswordBlocklist {
+    /// Loads the list when `security.enable_password_blocklist` is set, else returns an
+    /// empty one.
+    pub fn from_config(security: Option<&SecuritySection>) -> io::Result<Self> {
+        match security {
+            Some(security) if security.enable_password_blocklist => {
+                let path = security.password_blocklist_path.as_deref().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "security.password_blocklist_path is not configured",
+                    )
+                })?;
+
+                Self::load(path)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the list at `path`, decompressing it when the name ends in `.gz`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+
+        if path.extension().is_some_and(|extension| extension == "gz") {
+            Self::from_reader(GzDecoder::new(file))
+        } else {
+            Self::from_reader(file)
+        }
+    }
+
+    /// Reads a list in the format described in the module documentation.
+    pub fn from_reader(reader: impl Read) -> io::Result<Self> {
+        let mut digests = Vec::new();
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+
+            // Plain-text entries are kept lowercase so `contains` matches them in any case
+            digests.push(parse_digest(entry).unwrap_or_else(|| sha1_digest(&entry.to_lowercase())));
+        }
+
+        digests.sort_unstable();
+        digests.dedup();
+
+        Ok(PasswordBlocklist { digests })
+    }
+
+    /// Whether `password` is listed. Plain-text entries also match regardless of case.
+    pub fn contains(&self, password: &str) -> bool {
+        if self.digests.is_empty() {
+            return false;
+        }
+
+        let lowercase = password.to_lowercase();
+
+        [password, lowercase.as_str()]
+            .iter()
+            .any(|candidate| self.digests.binary_search(&sha1_digest(candidate)).is_ok())
+    }
+
+    /// Number of distinct entries.
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+}
+
+fn sha1_digest(password: &str) -> Sha1Digest {
+    Sha1::digest(password.as_bytes()).into()
+}
+
+/// Parses a `<40 hex digits>` or `<40 hex digits>:<count>` entry.
+fn parse_digest(entry: &str) -> Option<Sha1Digest> {
+    let hex = entry.split_once(':').map_or(entry, |(hex, _count)| hex);
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+
+    Some(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    // SHA-1 of "p@ssw0rd", as listed by Have I Been Pwned
+    const LISTED_DIGEST: &str = "57B2AD99044D337197C0C39FD3823568FF81E48A";
+
+    fn list() -> String {
+        format!(
+            "# common passwords\npassword\nletmein\r\n\n{}:52579\n",
+            LISTED_DIGEST
+        )
+    }
+
+    #[test]
+    fn test_contains_plain_and_hashed_entries() {
+        let blocklist = PasswordBlocklist::from_reader(list().as_bytes()).unwrap();
+
+        assert_eq!(blocklist.len(), 3);
+        assert!(blocklist.contains("password"));
+        assert!(blocklist.contains("LetMeIn"));
+        assert!(blocklist.contains("p@ssw0rd"));
+        assert!(!blocklist.contains("a reasonable passphrase"));
+        assert!(!blocklist.contains("# common passwords"));
+    }
+
+    #[test]
+    fn test_plain_entries_match_regardless_of_case() {
+        let blocklist = PasswordBlocklist::from_reader("Dragon\n".as_bytes()).unwrap();
+
+        assert!(blocklist.contains("dragon"));
+        assert!(blocklist.contains("Dragon"));
+        assert!(blocklist.contains("DRAGON"));
+    }
+
+    #[test]
+    fn test_load_gzip_file() {
+        let path = std::env::temp_dir().join(format!(
+            "password_blocklist_{}.txt.gz",
+            uuid::Uuid::new_v4()
+        ));
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all(list().as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let blocklist = PasswordBlocklist::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(blocklist.len(), 3);
+        assert!(blocklist.contains("letmein"));
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert!(PasswordBlocklist::from_config(None).unwrap().is_empty());
+
+        let disabled = SecuritySection {
+            password_blocklist_path: Some("does/not/exist.txt".to_string()),
+            ..SecuritySection::default()
+        };
+        assert!(
+            PasswordBlocklist::from_config(Some(&disabled))
+                .unwrap()
+                .is_empty()
+        );
+
+        let enabled = SecuritySection {
+            enable_password_blocklist: true,
+            ..disabled
+        };
+        assert!(PasswordBlocklist::from_config(Some(&enabled)).is_err());
+    }
+}
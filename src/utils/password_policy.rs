@@ -0,0 +1,266 @@
+//! # Password Policy
+//!
+//! Every password a user chooses, on registration, password change or reset, is checked
+//! against the rules of the `[security]` config section and the [`PasswordBlocklist`].
+//! All broken rules are reported at once, each with a stable rule name clients can map
+//! to their own wording.
+
+use crate::utils::load_config::SecuritySection;
+use crate::utils::password_blocklist::PasswordBlocklist;
+use serde::Serialize;
+use serde::ser::SerializeStruct;
+use thiserror::Error;
+
+/// Shortest piece of personal information a password is checked for.
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+/// A password policy rule a password breaks.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PasswordPolicyViolation {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(usize),
+    #[error("Password must be at most {0} characters long")]
+    TooLong(usize),
+    #[error("Password must contain a lowercase letter")]
+    MissingLowercase,
+    #[error("Password must contain an uppercase letter")]
+    MissingUppercase,
+    #[error("Password must contain a digit")]
+    MissingDigit,
+    #[error("Password must contain a symbol")]
+    MissingSymbol,
+    #[error("Password must not contain your name or email address")]
+    ContainsPersonalInfo,
+    #[error("Password must not repeat a character more than {0} times in a row")]
+    TooManyRepeatedCharacters(usize),
+    #[error("Password is too common or has appeared in a data breach")]
+    Breached,
+}
+
+impl PasswordPolicyViolation {
+    /// Stable, machine-readable name of the rule.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            PasswordPolicyViolation::TooShort(_) => "min_length",
+            PasswordPolicyViolation::TooLong(_) => "max_length",
+            PasswordPolicyViolation::MissingLowercase => "lowercase",
+            PasswordPolicyViolation::MissingUppercase => "uppercase",
+            PasswordPolicyViolation::MissingDigit => "digit",
+            PasswordPolicyViolation::MissingSymbol => "symbol",
+            PasswordPolicyViolation::ContainsPersonalInfo => "personal_info",
+            PasswordPolicyViolation::TooManyRepeatedCharacters(_) => "max_repeated_characters",
+            PasswordPolicyViolation::Breached => "breached",
+        }
+    }
+}
+
+/// Serialized as `{"rule": ..., "message": ...}`.
+impl Serialize for PasswordPolicyViolation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut violation = serializer.serialize_struct("PasswordPolicyViolation", 2)?;
+        violation.serialize_field("rule", self.rule())?;
+        violation.serialize_field("message", &self.to_string())?;
+        violation.end()
+    }
+}
+
+/// Checks `password` against the policy.
+///
+/// # Arguments
+/// - `password`: The password the user chose.
+/// - `policy`: The `[security]` section holding the rules.
+/// - `personal_info`: The user's email address and names, which the password must not
+///   contain. Only the local part of an email address is considered.
+/// - `blocklist`: Passwords that are refused outright.
+///
+/// Returns every rule the password breaks, in the order above.
+pub fn check_password_policy(
+    password: &str,
+    policy: &SecuritySection,
+    personal_info: &[&str],
+    blocklist: &PasswordBlocklist,
+) -> Result<(), Vec<PasswordPolicyViolation>> {
+    let mut violations = Vec::new();
+    let length = password.chars().count();
+
+    if length < policy.password_min_length {
+        violations.push(PasswordPolicyViolation::TooShort(
+            policy.password_min_length,
+        ));
+    }
+    if length > policy.password_max_length {
+        violations.push(PasswordPolicyViolation::TooLong(policy.password_max_length));
+    }
+    if policy.password_require_lowercase && !password.chars().any(char::is_lowercase) {
+        violations.push(PasswordPolicyViolation::MissingLowercase);
+    }
+    if policy.password_require_uppercase && !password.chars().any(char::is_uppercase) {
+        violations.push(PasswordPolicyViolation::MissingUppercase);
+    }
+    if policy.password_require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        violations.push(PasswordPolicyViolation::MissingDigit);
+    }
+    if policy.password_require_symbol && password.chars().all(char::is_alphanumeric) {
+        violations.push(PasswordPolicyViolation::MissingSymbol);
+    }
+    if policy.password_disallow_personal_info && contains_personal_info(password, personal_info) {
+        violations.push(PasswordPolicyViolation::ContainsPersonalInfo);
+    }
+    if policy.password_max_repeated_characters > 0
+        && longest_run(password) > policy.password_max_repeated_characters
+    {
+        violations.push(PasswordPolicyViolation::TooManyRepeatedCharacters(
+            policy.password_max_repeated_characters,
+        ));
+    }
+    if blocklist.contains(password) {
+        violations.push(PasswordPolicyViolation::Breached);
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// Joins the messages of `violations` into one sentence-per-rule string.
+pub fn describe_violations(violations: &[PasswordPolicyViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn contains_personal_info(password: &str, personal_info: &[&str]) -> bool {
+    let password = password.to_lowercase();
+
+    personal_info
+        .iter()
+        .map(|info| info.split('@').next().unwrap_or_default().trim())
+        .filter(|info| info.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+        .any(|info| password.contains(&info.to_lowercase()))
+}
+
+/// Length of the longest run of one repeated character.
+fn longest_run(password: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous = None;
+
+    for c in password.chars() {
+        current = if previous == Some(c) { current + 1 } else { 1 };
+        longest = longest.max(current);
+        previous = Some(c);
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks `password` with an empty blocklist.
+    fn check(
+        password: &str,
+        policy: &SecuritySection,
+        personal_info: &[&str],
+    ) -> Result<(), Vec<PasswordPolicyViolation>> {
+        check_password_policy(
+            password,
+            policy,
+            personal_info,
+            &PasswordBlocklist::default(),
+        )
+    }
+
+    fn strict_policy() -> SecuritySection {
+        SecuritySection {
+            password_require_lowercase: true,
+            password_require_uppercase: true,
+            password_require_digit: true,
+            password_require_symbol: true,
+            ..SecuritySection::default()
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = SecuritySection::default();
+
+        assert!(check("correct horse battery", &policy, &[]).is_ok());
+        assert_eq!(
+            check("", &policy, &[]),
+            Err(vec![PasswordPolicyViolation::TooShort(8)])
+        );
+        assert_eq!(
+            check(&"ab".repeat(65), &policy, &[]),
+            Err(vec![PasswordPolicyViolation::TooLong(128)])
+        );
+    }
+
+    #[test]
+    fn test_character_classes() {
+        assert_eq!(
+            check("alllowercase", &strict_policy(), &[]),
+            Err(vec![
+                PasswordPolicyViolation::MissingUppercase,
+                PasswordPolicyViolation::MissingDigit,
+                PasswordPolicyViolation::MissingSymbol,
+            ])
+        );
+        assert!(check("Mixed-Case-1", &strict_policy(), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_personal_info() {
+        let policy = SecuritySection::default();
+        let personal_info = ["Ada", "Lovelace", "countess@example.com"];
+
+        assert_eq!(
+            check("i-am-LOVELACE", &policy, &personal_info),
+            Err(vec![PasswordPolicyViolation::ContainsPersonalInfo])
+        );
+        assert_eq!(
+            check("the countess rules", &policy, &personal_info),
+            Err(vec![PasswordPolicyViolation::ContainsPersonalInfo])
+        );
+        // The domain and pieces too short to be telling are not checked
+        let personal_info = ["Al", "Lo", "al@example.com"];
+        assert!(check("example al lo", &policy, &personal_info).is_ok());
+    }
+
+    #[test]
+    fn test_repeated_characters() {
+        let policy = SecuritySection::default();
+
+        assert!(check("aaabbbccc", &policy, &[]).is_ok());
+        assert_eq!(
+            check("aaaabbbccc", &policy, &[]),
+            Err(vec![PasswordPolicyViolation::TooManyRepeatedCharacters(3)])
+        );
+    }
+
+    #[test]
+    fn test_blocklisted_passwords() {
+        let blocklist = PasswordBlocklist::from_reader("password123\n".as_bytes()).unwrap();
+
+        assert_eq!(
+            check_password_policy("Password123", &SecuritySection::default(), &[], &blocklist),
+            Err(vec![PasswordPolicyViolation::Breached])
+        );
+    }
+
+    #[test]
+    fn test_violations_serialize_with_rule_names() {
+        let json = serde_json::to_value(PasswordPolicyViolation::TooShort(8)).unwrap();
+
+        assert_eq!(json["rule"], "min_length");
+        assert_eq!(
+            json["message"],
+            "Password must be at least 8 characters long"
+        );
+    }
+}
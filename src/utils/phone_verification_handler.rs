@@ -0,0 +1,151 @@
+//! # Phone Verification
+//!
+//! A user proves ownership of their phone number by sending back a six-digit code
+//! texted to it. Only the code's SHA-256 digest is stored, next to the moment it
+//! expires, when it was sent and how many wrong guesses it has taken. A code stops
+//! working once it is used, expires, or runs out of attempts.
+
+use crate::utils::one_time_password_handler::hash_one_time_password;
+use sqlx::PgExecutor;
+
+/// Generates a random six-digit phone verification code.
+pub fn generate_phone_verification_code() -> String {
+    format!("{:06}", rand::random_range(0..1_000_000u32))
+}
+
+/// Stores a freshly generated code, replacing any outstanding one.
+///
+/// Returns `false`, storing nothing, while the previous code was sent less than
+/// `resend_cooldown_in_seconds` ago.
+///
+/// # Arguments
+/// - `executor`: A pool or an open transaction.
+/// - `user_id`: Owner of the code.
+/// - `code`: The code texted to the user; only its digest is stored.
+/// - `lifetime_in_minutes`: How long it stays valid.
+/// - `resend_cooldown_in_seconds`: Minimum wait since the previous code.
+pub async fn store_phone_verification_code<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    code: &str,
+    lifetime_in_minutes: u64,
+    resend_cooldown_in_seconds: u64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            phone_verification_code = $1,
+            phone_verification_expires_at = NOW() + make_interval(mins => $2),
+            phone_verification_sent_at = NOW(),
+            phone_verification_attempts = 0,
+            updated_at = NOW()
+        WHERE id = $3
+            AND (
+                phone_verification_sent_at IS NULL
+                OR phone_verification_sent_at <= NOW() - make_interval(secs => $4)
+            )
+        "#,
+    )
+    .bind(hash_one_time_password(code))
+    .bind(i32::try_from(lifetime_in_minutes).unwrap_or(i32::MAX))
+    .bind(user_id)
+    .bind(resend_cooldown_in_seconds as f64)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Seconds left before another code may be sent to the user, rounded up.
+pub async fn phone_verification_cooldown_remaining<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    resend_cooldown_in_seconds: u64,
+) -> Result<u64, sqlx::Error> {
+    let remaining: Option<f64> = sqlx::query_scalar(
+        r#"
+        SELECT EXTRACT(
+            EPOCH FROM phone_verification_sent_at + make_interval(secs => $2) - NOW()
+        )::float8
+        FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(resend_cooldown_in_seconds as f64)
+    .fetch_optional(executor)
+    .await?
+    .flatten();
+
+    Ok(remaining
+        .map(|seconds| seconds.ceil().max(0.0) as u64)
+        .unwrap_or(0))
+}
+
+/// Marks the user's phone number as verified if `code` is their outstanding code.
+///
+/// A wrong guess uses up one of the code's `max_attempts`. Returns `false` when the code
+/// is wrong, expired or out of attempts. Two concurrent calls can never both succeed.
+pub async fn confirm_phone_verification_code<'e, E>(
+    executor: E,
+    user_id: i64,
+    code: &str,
+    max_attempts: u32,
+) -> Result<bool, sqlx::Error>
+where
+    E: PgExecutor<'e> + Copy,
+{
+    let verified = sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            phone_verified_at = NOW(),
+            phone_verification_code = NULL,
+            phone_verification_expires_at = NULL,
+            phone_verification_attempts = 0,
+            updated_at = NOW()
+        WHERE id = $1
+            AND phone_verification_code = $2
+            AND phone_verification_expires_at > NOW()
+            AND phone_verification_attempts < $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_one_time_password(code))
+    .bind(i32::try_from(max_attempts).unwrap_or(i32::MAX))
+    .execute(executor)
+    .await?;
+
+    if verified.rows_affected() > 0 {
+        return Ok(true);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET phone_verification_attempts = phone_verification_attempts + 1
+        WHERE id = $1 AND phone_verification_code IS NOT NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_phone_verification_code() {
+        for _ in 0..100 {
+            let code = generate_phone_verification_code();
+
+            assert_eq!(code.len(), 6);
+            assert!(code.chars().all(|c| c.is_ascii_digit()));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! # MFA Recovery Codes
+//!
+//! Single-use codes that stand in for the authenticator app when it is lost. A set of
+//! [`RECOVERY_CODE_COUNT`] codes is handed out once, when two-factor authentication is
+//! turned on or the set is regenerated; only their Argon2id hashes are kept, in
+//! `mfa_recovery_codes`.
+
+use crate::utils::hashing_handler::{PasswordHashError, PasswordHashing, hashing_handler};
+use crate::utils::verification_handler::verification_handler;
+use data_encoding::BASE32_NOPAD;
+use sqlx::{PgExecutor, PgPool};
+use thiserror::Error;
+
+/// Codes in a set.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Random bytes in a code, 80 bits written as 16 base32 characters.
+const RECOVERY_CODE_BYTES: usize = 10;
+
+/// Characters of a code without separators.
+const RECOVERY_CODE_LENGTH: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeError {
+    #[error(transparent)]
+    PasswordHash(#[from] PasswordHashError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Generates one code, lowercase base32 in groups of four, e.g. `abcd-efgh-ijkl-mnop`.
+fn generate_recovery_code() -> String {
+    let code = BASE32_NOPAD
+        .encode(&rand::random::<[u8; RECOVERY_CODE_BYTES]>())
+        .to_lowercase();
+
+    code.as_bytes()
+        .chunks(4)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The form codes are hashed in, so case, separators and whitespace do not matter.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Replaces the user's recovery codes with a new set and returns it. Codes of the
+/// previous set, used or not, stop working.
+pub async fn replace_recovery_codes(
+    db: &PgPool,
+    hashing: &PasswordHashing,
+    user_id: i64,
+) -> Result<Vec<String>, RecoveryCodeError> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    let mut code_hashes = Vec::with_capacity(codes.len());
+    for code in &codes {
+        code_hashes.push(hashing_handler(&normalize_recovery_code(code), hashing).await?);
+    }
+
+    let mut tx = db.begin().await?;
+    delete_recovery_codes(&mut *tx, user_id).await?;
+    sqlx::query(
+        "INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::VARCHAR[])",
+    )
+    .bind(user_id)
+    .bind(&code_hashes)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+/// Accepts `code` as the user's second factor if it is one of their unused recovery codes,
+/// and marks it used.
+///
+/// Returns `false` for a wrong or used code. Two concurrent calls with the same code can
+/// never both succeed.
+pub async fn consume_recovery_code(
+    db: &PgPool,
+    hashing: &PasswordHashing,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, RecoveryCodeError> {
+    let code = normalize_recovery_code(code);
+    // Not worth an Argon2 verification per stored code
+    if code.len() != RECOVERY_CODE_LENGTH {
+        return Ok(false);
+    }
+
+    let unused = sqlx::query_as::<_, (i64, String)>(
+        "SELECT id, code_hash FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    for (id, code_hash) in unused {
+        if verification_handler(&code, &code_hash, hashing).await? {
+            let consumed = sqlx::query(
+                "UPDATE mfa_recovery_codes SET used_at = NOW() WHERE id = $1 AND used_at IS NULL",
+            )
+            .bind(id)
+            .execute(db)
+            .await?;
+
+            return Ok(consumed.rows_affected() > 0);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Recovery codes the user has left.
+pub async fn remaining_recovery_codes<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Deletes every recovery code of the user.
+pub async fn delete_recovery_codes<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_recovery_code() {
+        let code = generate_recovery_code();
+
+        assert_eq!(code.len(), 19);
+        assert_eq!(code.split('-').count(), 4);
+        assert_eq!(normalize_recovery_code(&code).len(), RECOVERY_CODE_LENGTH);
+        assert_ne!(code, generate_recovery_code());
+    }
+
+    #[test]
+    fn test_normalize_recovery_code() {
+        assert_eq!(
+            normalize_recovery_code(" ABCD-efgh ijkl-MNOP\n"),
+            "abcdefghijklmnop"
+        );
+    }
+}
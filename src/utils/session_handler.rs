@@ -0,0 +1,232 @@
+//! # Session Persistence
+//!
+//! Every login opens a row in the `sessions` table, so each device holds its own
+//! refresh token. Only a SHA-256 digest of the session's latest refresh token is
+//! stored; an older token of the same session presented again reveals token reuse.
+
+use crate::utils::client_metadata::ClientMetadata;
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Returns the hex-encoded SHA-256 digest under which a refresh token is stored.
+pub fn hash_refresh_token(refresh_token: &str) -> String {
+    format!("{:x}", Sha256::digest(refresh_token.as_bytes()))
+}
+
+fn lifetime_hours(lifetime_in_hours: u64) -> i32 {
+    i32::try_from(lifetime_in_hours).unwrap_or(i32::MAX)
+}
+
+/// Opens a new session holding its first refresh token.
+///
+/// # Arguments
+/// - `executor`: A pool or an open transaction.
+/// - `user_id`: Owner of the session.
+/// - `session_id`: The `sid` claim of the session's tokens.
+/// - `refresh_token`: The encoded refresh token; only its digest is stored.
+/// - `client`: The device the session was opened from.
+/// - `lifetime_in_hours`: How long the refresh token stays valid, matching its `exp` claim.
+pub async fn create_session<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    session_id: Uuid,
+    refresh_token: &str,
+    client: &ClientMetadata,
+    lifetime_in_hours: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (id, user_id, refresh_token_hash, user_agent, ip_address, expires_at)
+        VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(hours => $6))
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(hash_refresh_token(refresh_token))
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .bind(lifetime_hours(lifetime_in_hours))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether the user has opened a session from the same user agent before.
+pub async fn is_known_device<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    client: &ClientMetadata,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sessions WHERE user_id = $1 AND user_agent IS NOT DISTINCT FROM $2)",
+    )
+    .bind(user_id)
+    .bind(&client.user_agent)
+    .fetch_one(executor)
+    .await
+}
+
+/// Replaces the refresh token of a session after a successful refresh.
+pub async fn rotate_session_token<'e, E: PgExecutor<'e>>(
+    executor: E,
+    session_id: Uuid,
+    refresh_token: &str,
+    client: &ClientMetadata,
+    lifetime_in_hours: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET
+            refresh_token_hash = $1,
+            ip_address = COALESCE($2, ip_address),
+            last_used_at = NOW(),
+            expires_at = NOW() + make_interval(hours => $3)
+        WHERE id = $4
+        "#,
+    )
+    .bind(hash_refresh_token(refresh_token))
+    .bind(&client.ip_address)
+    .bind(lifetime_hours(lifetime_in_hours))
+    .bind(session_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Records that a session was just used, from the client's current IP address.
+///
+/// Writes at most once a minute per session, so busy clients do not turn every request
+/// into an update.
+pub async fn touch_session<'e, E: PgExecutor<'e>>(
+    executor: E,
+    session_id: Uuid,
+    client: &ClientMetadata,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET
+            last_used_at = NOW(),
+            ip_address = COALESCE($1, ip_address)
+        WHERE id = $2
+            AND revoked_at IS NULL
+            AND last_used_at < NOW() - INTERVAL '1 minute'
+        "#,
+    )
+    .bind(&client.ip_address)
+    .bind(session_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Revokes a single session of a user.
+///
+/// Returns `false` when the user has no such active session.
+pub async fn revoke_session<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    session_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revokes every active session of a user.
+///
+/// Returns the number of sessions revoked.
+pub async fn revoke_user_sessions<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Revokes every active session of a user except `kept_session_id`.
+///
+/// Returns the number of sessions revoked.
+pub async fn revoke_other_sessions<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    kept_session_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND id <> $2 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(kept_session_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Marks the user as logged out exactly when no active session is left.
+pub async fn refresh_logged_out_flag<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            is_logged_out = NOT EXISTS (
+                SELECT 1 FROM sessions
+                WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_refresh_token_is_stable_hex_digest() {
+        let hash = hash_refresh_token("some.refresh.token");
+        assert_eq!(hash.len(), 64);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash, hash_refresh_token("some.refresh.token"));
+    }
+
+    #[test]
+    fn test_hash_refresh_token_differs_per_token() {
+        assert_ne!(
+            hash_refresh_token("first.refresh.token"),
+            hash_refresh_token("second.refresh.token")
+        );
+    }
+
+    #[test]
+    fn test_lifetime_hours_saturates() {
+        assert_eq!(lifetime_hours(24), 24);
+        assert_eq!(lifetime_hours(u64::MAX), i32::MAX);
+    }
+}
@@ -0,0 +1,205 @@
+//! # Step-Up Authentication
+//!
+//! Sensitive account operations make the user prove who they are again, even with a valid
+//! access token: their current password and, once two-factor authentication is on, a code
+//! from their authenticator app or one of their recovery codes.
+//!
+//! A wrong password or code counts as a failed login, so the back-off and lockout of
+//! `login_lockout_handler` cap guessing with a stolen access token too. A successful
+//! step-up clears the count, like a successful login.
+
+use crate::utils::hashing_handler::{PasswordHashError, PasswordHashing};
+use crate::utils::load_config::SecuritySection;
+use crate::utils::login_lockout_handler::{
+    LoginBlock, clear_failed_logins, login_block, record_failed_login,
+};
+use crate::utils::recovery_codes_handler::{RecoveryCodeError, consume_recovery_code};
+use crate::utils::totp_handler::consume_totp_code;
+use crate::utils::verification_handler::verification_handler;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::Response;
+use sqlx::PgPool;
+use thiserror::Error;
+use tracing::{error, info};
+
+#[derive(Debug, Error)]
+pub enum StepUpError {
+    #[error("Current password is incorrect")]
+    WrongPassword,
+    #[error("An authentication code is required")]
+    MissingCode,
+    #[error("Authentication code is invalid")]
+    WrongCode,
+    /// Too many failed logins or step-ups in a row.
+    #[error("{}", describe_block(.0))]
+    Blocked(LoginBlock),
+    #[error(transparent)]
+    PasswordHash(#[from] PasswordHashError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+fn describe_block(block: &LoginBlock) -> String {
+    match block {
+        LoginBlock::Locked {
+            retry_after_in_seconds,
+        } => format!(
+            "Account is locked after too many failed login attempts, try again in {} seconds",
+            retry_after_in_seconds
+        ),
+        LoginBlock::BackOff {
+            retry_after_in_seconds,
+        } => format!(
+            "Too many failed login attempts, try again in {} seconds",
+            retry_after_in_seconds
+        ),
+    }
+}
+
+impl From<RecoveryCodeError> for StepUpError {
+    fn from(e: RecoveryCodeError) -> Self {
+        match e {
+            RecoveryCodeError::PasswordHash(e) => StepUpError::PasswordHash(e),
+            RecoveryCodeError::Database(e) => StepUpError::Database(e),
+        }
+    }
+}
+
+impl StepUpError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            StepUpError::WrongPassword | StepUpError::MissingCode | StepUpError::WrongCode => {
+                StatusCode::FORBIDDEN
+            }
+            StepUpError::Blocked(LoginBlock::Locked { .. }) => StatusCode::LOCKED,
+            StepUpError::Blocked(LoginBlock::BackOff { .. }) => StatusCode::TOO_MANY_REQUESTS,
+            StepUpError::PasswordHash(PasswordHashError::Saturated { .. }) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            StepUpError::PasswordHash(_) | StepUpError::Database(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Seconds to send in `Retry-After`, while the account is blocked or password hashing
+    /// is saturated.
+    pub fn retry_after_in_seconds(&self) -> Option<u64> {
+        match self {
+            StepUpError::Blocked(
+                LoginBlock::Locked {
+                    retry_after_in_seconds,
+                }
+                | LoginBlock::BackOff {
+                    retry_after_in_seconds,
+                },
+            )
+            | StepUpError::PasswordHash(PasswordHashError::Saturated {
+                retry_after_in_seconds,
+            }) => Some(*retry_after_in_seconds),
+            _ => None,
+        }
+    }
+
+    /// The response refusing the operation: `failed` builds the controller's own error
+    /// response from the status code and message, and `Retry-After` is added when known.
+    pub fn into_response_with(
+        self,
+        failed: impl FnOnce(StatusCode, String) -> Response,
+    ) -> Response {
+        let mut response = failed(self.status_code(), self.to_string());
+        if let Some(retry_after_in_seconds) = self.retry_after_in_seconds() {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after_in_seconds));
+        }
+
+        response
+    }
+}
+
+/// Consumes `code` as the user's second factor: a code from their authenticator app, or
+/// else one of their recovery codes.
+///
+/// Returns `false` when it is neither.
+pub async fn verify_second_factor(
+    db: &PgPool,
+    hashing: &PasswordHashing,
+    security: &SecuritySection,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, StepUpError> {
+    if consume_totp_code(db, user_id, code, security).await? {
+        return Ok(true);
+    }
+
+    if consume_recovery_code(db, hashing, user_id, code).await? {
+        info!("RECOVERY CODE USED BY USER {}", user_id);
+
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// Checks `password` against the user's current one and, if they have two-factor
+/// authentication on, consumes `code` as their second factor (see [`verify_second_factor`]).
+///
+/// `code` is ignored for users without two-factor authentication. Refused with
+/// [`StepUpError::Blocked`] while logins are; a wrong password or code is recorded as a
+/// failed login, and success clears the failed logins.
+pub async fn verify_step_up(
+    db: &PgPool,
+    hashing: &PasswordHashing,
+    security: &SecuritySection,
+    user_id: i64,
+    password: &str,
+    code: Option<&str>,
+) -> Result<(), StepUpError> {
+    let (current_hash, mfa_enabled) = sqlx::query_as::<_, (String, bool)>(
+        "SELECT password, totp_enabled_at IS NOT NULL FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+
+    // Checked before the password, so a blocked account cannot be guessed at
+    if let Some(block) = login_block(db, user_id, security).await? {
+        return Err(StepUpError::Blocked(block));
+    }
+
+    if !verification_handler(password, &current_hash, hashing).await? {
+        return Err(failed_step_up(db, user_id, security, StepUpError::WrongPassword).await);
+    }
+
+    if mfa_enabled {
+        let code = code.ok_or(StepUpError::MissingCode)?;
+        if !verify_second_factor(db, hashing, security, user_id, code).await? {
+            return Err(failed_step_up(db, user_id, security, StepUpError::WrongCode).await);
+        }
+    }
+
+    clear_failed_logins(db, user_id).await?;
+
+    Ok(())
+}
+
+/// Records a wrong password or code as a failed login. Returns the block it caused, if
+/// any, or else `error`.
+async fn failed_step_up(
+    db: &PgPool,
+    user_id: i64,
+    security: &SecuritySection,
+    error: StepUpError,
+) -> StepUpError {
+    match record_failed_login(db, user_id, security).await {
+        Ok(Some(block)) => StepUpError::Blocked(block),
+        Ok(None) => error,
+        Err(e) => {
+            error!("FAILED TO RECORD FAILED STEP-UP: {}", e);
+
+            error
+        }
+    }
+}
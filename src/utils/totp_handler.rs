@@ -0,0 +1,406 @@
+//! # TOTP Two-Factor Authentication
+//!
+//! Time-based one-time passwords (RFC 6238) as an optional second factor: six digits
+//! derived with HMAC-SHA1 from a secret shared with the user's authenticator app and the
+//! current 30-second time step.
+//!
+//! Enrollment hands out a new secret as `users.totp_pending_secret`; the first code made
+//! from it confirms the enrollment and moves it to `users.totp_secret`, replacing any
+//! previous one. `users.totp_last_used_step` keeps every code from working twice.
+
+use crate::utils::load_config::SecuritySection;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sqlx::PgExecutor;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds each code is valid for.
+const TOTP_PERIOD_IN_SECONDS: u64 = 30;
+
+/// Digits of each code.
+const TOTP_DIGITS: usize = 6;
+
+/// Length of a secret, the size of an HMAC-SHA1 output as RFC 4226 recommends.
+const TOTP_SECRET_BYTES: usize = 20;
+
+/// Generates a random secret, base32 encoded without padding as authenticator apps expect.
+pub fn generate_totp_secret() -> String {
+    BASE32_NOPAD.encode(&rand::random::<[u8; TOTP_SECRET_BYTES]>())
+}
+
+fn percent_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+/// The `otpauth://` URI an authenticator app is set up with, usually shown as a QR code.
+///
+/// # Arguments
+/// - `issuer`: Service name shown in the app, `security.totp_issuer`.
+/// - `account`: The user's email address.
+/// - `secret`: The base32 secret from [`generate_totp_secret`].
+pub fn totp_provisioning_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        TOTP_DIGITS,
+        TOTP_PERIOD_IN_SECONDS
+    )
+}
+
+/// The time step a moment falls in.
+fn time_step(unix_time_in_seconds: u64) -> u64 {
+    unix_time_in_seconds / TOTP_PERIOD_IN_SECONDS
+}
+
+fn now_in_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The code for `step`, as defined by RFC 4226 (HOTP) with the time step as counter.
+fn totp_code(secret: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = usize::from(digest[digest.len() - 1] & 0x0f);
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS as u32),
+        width = TOTP_DIGITS
+    )
+}
+
+/// The code an authenticator app set up with `secret` shows at `unix_time_in_seconds`.
+///
+/// Returns `None` for an undecodable secret.
+pub fn totp_code_at(secret: &str, unix_time_in_seconds: u64) -> Option<String> {
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+
+    Some(totp_code(&secret, time_step(unix_time_in_seconds)))
+}
+
+/// Compares two codes in time independent of where they first differ.
+fn codes_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// The time step `code` was made for, if it is within `allowed_drift_steps` steps of
+/// `unix_time_in_seconds` and later than `last_used_step`.
+///
+/// Returns `None` for a wrong, stale or already used code, or an undecodable secret.
+pub fn matching_totp_step(
+    secret: &str,
+    code: &str,
+    unix_time_in_seconds: u64,
+    allowed_drift_steps: u64,
+    last_used_step: Option<u64>,
+) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current = time_step(unix_time_in_seconds);
+    let earliest = current
+        .saturating_sub(allowed_drift_steps)
+        .max(last_used_step.map_or(0, |step| step.saturating_add(1)));
+
+    (earliest..=current.saturating_add(allowed_drift_steps))
+        .find(|step| codes_match(&totp_code(&secret, *step), code))
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct TotpState {
+    secret: Option<String>,
+    totp_last_used_step: Option<i64>,
+}
+
+fn step_to_i64(step: u64) -> i64 {
+    i64::try_from(step).unwrap_or(i64::MAX)
+}
+
+/// Whether the user has confirmed a TOTP authenticator.
+pub async fn is_totp_enabled<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let enabled: Option<bool> =
+        sqlx::query_scalar("SELECT totp_enabled_at IS NOT NULL FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(enabled.unwrap_or(false))
+}
+
+/// Hands the user a new secret to set an authenticator app up with, replacing any
+/// enrollment that was never confirmed. A confirmed secret keeps working until the new one
+/// is confirmed.
+pub async fn start_totp_enrollment<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+    secret: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET totp_pending_secret = $1, updated_at = NOW() WHERE id = $2")
+        .bind(secret)
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Turns two-factor authentication on with the pending secret if `code` was made from it.
+///
+/// Returns `false` when there is no pending enrollment or the code is wrong.
+pub async fn confirm_totp_enrollment<'e, E>(
+    executor: E,
+    user_id: i64,
+    code: &str,
+    security: &SecuritySection,
+) -> Result<bool, sqlx::Error>
+where
+    E: PgExecutor<'e> + Copy,
+{
+    let pending = sqlx::query_as::<_, TotpState>(
+        "SELECT totp_pending_secret AS secret, totp_last_used_step FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(TotpState {
+        secret: Some(secret),
+        ..
+    }) = pending
+    else {
+        return Ok(false);
+    };
+
+    let Some(step) = matching_totp_step(
+        &secret,
+        code,
+        now_in_seconds(),
+        security.totp_allowed_drift_steps,
+        None,
+    ) else {
+        return Ok(false);
+    };
+
+    // Only confirm the secret the code was checked against
+    let confirmed = sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            totp_secret = totp_pending_secret,
+            totp_enabled_at = NOW(),
+            totp_pending_secret = NULL,
+            totp_last_used_step = $1,
+            updated_at = NOW()
+        WHERE id = $2 AND totp_pending_secret = $3
+        "#,
+    )
+    .bind(step_to_i64(step))
+    .bind(user_id)
+    .bind(&secret)
+    .execute(executor)
+    .await?;
+
+    Ok(confirmed.rows_affected() > 0)
+}
+
+/// Accepts `code` as the user's second factor if it comes from their confirmed
+/// authenticator and has not been used before.
+///
+/// Returns `false` when the code is wrong, stale or reused, or the user has no
+/// authenticator. Two concurrent calls with the same code can never both succeed.
+pub async fn consume_totp_code<'e, E>(
+    executor: E,
+    user_id: i64,
+    code: &str,
+    security: &SecuritySection,
+) -> Result<bool, sqlx::Error>
+where
+    E: PgExecutor<'e> + Copy,
+{
+    let state = sqlx::query_as::<_, TotpState>(
+        r#"
+        SELECT totp_secret AS secret, totp_last_used_step
+        FROM users
+        WHERE id = $1 AND totp_enabled_at IS NOT NULL
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(TotpState {
+        secret: Some(secret),
+        totp_last_used_step,
+    }) = state
+    else {
+        return Ok(false);
+    };
+
+    let Some(step) = matching_totp_step(
+        &secret,
+        code,
+        now_in_seconds(),
+        security.totp_allowed_drift_steps,
+        totp_last_used_step.and_then(|step| u64::try_from(step).ok()),
+    ) else {
+        return Ok(false);
+    };
+
+    let consumed = sqlx::query(
+        r#"
+        UPDATE users
+        SET totp_last_used_step = $1
+        WHERE id = $2
+            AND totp_secret = $3
+            AND (totp_last_used_step IS NULL OR totp_last_used_step < $1)
+        "#,
+    )
+    .bind(step_to_i64(step))
+    .bind(user_id)
+    .bind(&secret)
+    .execute(executor)
+    .await?;
+
+    Ok(consumed.rows_affected() > 0)
+}
+
+/// Turns two-factor authentication off, dropping the secret and any pending enrollment.
+pub async fn disable_totp<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET
+            totp_secret = NULL,
+            totp_enabled_at = NULL,
+            totp_pending_secret = NULL,
+            totp_last_used_step = NULL,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 secret of the RFC 6238 test vectors, `12345678901234567890`.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_totp_code_matches_rfc_6238_test_vectors() {
+        let secret = BASE32_NOPAD.decode(RFC_SECRET.as_bytes()).unwrap();
+
+        // The RFC lists eight digits; six-digit codes are their last six
+        for (time, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(totp_code(&secret, time_step(time)), code);
+        }
+    }
+
+    #[test]
+    fn test_matching_totp_step_allows_drift() {
+        // 1111111109 is in step 37037036
+        assert_eq!(
+            matching_totp_step(RFC_SECRET, "081804", 1111111109, 1, None),
+            Some(37037036)
+        );
+        // One step later is within the drift, two are not
+        assert_eq!(
+            matching_totp_step(RFC_SECRET, "081804", 1111111109 + 30, 1, None),
+            Some(37037036)
+        );
+        assert_eq!(
+            matching_totp_step(RFC_SECRET, "081804", 1111111109 + 60, 1, None),
+            None
+        );
+        assert_eq!(
+            matching_totp_step(RFC_SECRET, "081804", 1111111109 + 30, 0, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_matching_totp_step_rejects_reused_and_malformed_codes() {
+        assert_eq!(
+            matching_totp_step(RFC_SECRET, "081804", 1111111109, 1, Some(37037036)),
+            None
+        );
+        assert_eq!(
+            matching_totp_step(RFC_SECRET, "081804", 1111111109, 1, Some(37037035)),
+            Some(37037036)
+        );
+        for code in ["", "81804", "0818040", "08180a", "000000"] {
+            assert_eq!(
+                matching_totp_step(RFC_SECRET, code, 1111111109, 1, None),
+                None
+            );
+        }
+        assert_eq!(
+            matching_totp_step("not base32!", "081804", 1111111109, 1, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_generate_totp_secret() {
+        let secret = generate_totp_secret();
+
+        assert_eq!(secret.len(), 32);
+        assert_eq!(
+            BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(),
+            TOTP_SECRET_BYTES
+        );
+        assert_ne!(secret, generate_totp_secret());
+    }
+
+    #[test]
+    fn test_totp_provisioning_uri() {
+        assert_eq!(
+            totp_provisioning_uri("Krabby Chat", "ada@example.com", "JBSWY3DPEHPK3PXP"),
+            "otpauth://totp/Krabby%20Chat:ada%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Krabby%20Chat&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! This module provides functionality for verifying passwords against Argon2 hashes.
 
+use crate::utils::hashing_handler::{PasswordHashError, PasswordHashing};
 use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordVerifier},
@@ -9,33 +10,84 @@ use argon2::{
 
 /// Verifies a plain-text string against a hashed string using Argon2.
 ///
-/// Returns `Ok(true)` when the password matches, `Ok(false)` when it does not (including cases where verification fails and is converted via `.is_ok()`), and only returns `Err` on hash parsing failures.
+/// The cost parameters are read from the hash itself, so hashes made with outdated
+/// parameters keep verifying. Hashes without a `keyid` predate the pepper and are checked
+/// without it; a hash made with another pepper than the current one never matches.
+///
+/// Like hashing, verification runs on the bounded hashing pool.
+///
+/// Returns `Ok(true)` when the password matches, `Ok(false)` when it does not (including cases where verification fails and is converted via `.is_ok()`), and only returns `Err` on hash parsing failures or when the hashing pool is saturated.
 pub async fn verification_handler(
     string_to_compare: &str,
     hashed_string: &str,
-) -> Result<bool, argon2::password_hash::Error> {
-    // Parse the stored hash
-    let parsed_hash = PasswordHash::new(hashed_string)?;
+    hashing: &PasswordHashing,
+) -> Result<bool, PasswordHashError> {
+    let password = string_to_compare.to_string();
+    let hashed_string = hashed_string.to_string();
+
+    hashing
+        .run_blocking(move |hashing| {
+            // Parse the stored hash
+            let parsed_hash = PasswordHash::new(&hashed_string)?;
+            let key_id = parsed_hash
+                .params
+                .get_str("keyid")
+                .and_then(|key_id| key_id.parse::<argon2::KeyId>().ok());
+
+            let argon2 = match key_id {
+                None => Argon2::default(),
+                Some(key_id) if key_id.as_bytes() == hashing.key_id() => hashing.argon2()?,
+                Some(_) => return Ok(false),
+            };
+
+            // Verify the password
+            let is_valid = argon2
+                .verify_password(password.as_bytes(), &parsed_hash)
+                .is_ok(); // returns true if verification succeeded
 
-    // Verify the password
-    let is_valid = Argon2::default()
-        .verify_password(string_to_compare.as_bytes(), &parsed_hash)
-        .is_ok(); // returns true if verification succeeded
+            Ok(is_valid)
+        })
+        .await
+}
 
-    Ok(is_valid)
+/// Spends as long as [`verification_handler`] without a stored hash to check against, so
+/// a request for an unknown account cannot be told apart by its response time.
+///
+/// Returns `Ok(false)` for every password, or `Err` when the hashing pool is saturated,
+/// just like a real verification.
+pub async fn dummy_verification_handler(
+    string_to_compare: &str,
+    hashing: &PasswordHashing,
+) -> Result<bool, PasswordHashError> {
+    let dummy_hash = hashing.dummy_hash().await?;
+
+    verification_handler(string_to_compare, dummy_hash, hashing)
+        .await
+        .map(|_| false)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::utils::hashing_handler::hashing_handler;
+    use crate::utils::load_config::SecuritySection;
+
+    fn peppered(pepper: &str) -> PasswordHashing {
+        PasswordHashing::from_config(Some(&SecuritySection {
+            password_pepper: Some(pepper.to_string()),
+            ..SecuritySection::default()
+        }))
+        .unwrap()
+    }
 
     #[tokio::test]
     async fn test_verification_handler_success() {
         let password = "my_secure_password";
-        let hash = hashing_handler(password).await.unwrap();
+        let hash = hashing_handler(password, &PasswordHashing::default())
+            .await
+            .unwrap();
 
-        let result = verification_handler(password, &hash).await;
+        let result = verification_handler(password, &hash, &PasswordHashing::default()).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
@@ -44,10 +96,56 @@ mod tests {
     async fn test_verification_handler_failure() {
         let password = "my_secure_password";
         let wrong_password = "wrong_password";
-        let hash = hashing_handler(password).await.unwrap();
+        let hash = hashing_handler(password, &PasswordHashing::default())
+            .await
+            .unwrap();
 
-        let result = verification_handler(wrong_password, &hash).await;
+        let result = verification_handler(wrong_password, &hash, &PasswordHashing::default()).await;
         assert!(result.is_ok());
         assert!(!result.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_verification_with_pepper() {
+        let password = "my_secure_password";
+        let peppered_hash = hashing_handler(password, &peppered("pepper"))
+            .await
+            .unwrap();
+        let legacy_hash = hashing_handler(password, &PasswordHashing::default())
+            .await
+            .unwrap();
+
+        assert!(
+            verification_handler(password, &peppered_hash, &peppered("pepper"))
+                .await
+                .unwrap()
+        );
+        // Hashes from before the pepper was introduced keep working
+        assert!(
+            verification_handler(password, &legacy_hash, &peppered("pepper"))
+                .await
+                .unwrap()
+        );
+        // Peppered hashes need that very pepper
+        assert!(
+            !verification_handler(password, &peppered_hash, &peppered("another pepper"))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !verification_handler(password, &peppered_hash, &PasswordHashing::default())
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dummy_verification_never_matches() {
+        let hashing = PasswordHashing::default();
+
+        for password in ["my_secure_password", ""] {
+            let result = dummy_verification_handler(password, &hashing).await;
+            assert!(!result.unwrap());
+        }
+    }
 }
@@ -0,0 +1,101 @@
+mod common;
+
+use axum::routing::get;
+use axum::{Router, middleware};
+use axum_test::TestServer;
+use chat_auth_server::middlewares::access_middleware::{AuthenticatedUser, access_middleware};
+use chat_auth_server::{AppState, create_app};
+use common::{register, setup_test_state};
+
+async fn whoami(user: AuthenticatedUser) -> String {
+    user.email
+}
+
+/// Mounts a protected route next to the real auth routes.
+fn protected_app(state: AppState) -> Router {
+    let protected = Router::new()
+        .route("/whoami", get(whoami))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            access_middleware,
+        ))
+        .with_state(state.clone());
+
+    create_app(state).nest("/protected", protected)
+}
+
+#[tokio::test]
+async fn test_protected_route_with_valid_token() {
+    let server = TestServer::new(protected_app(setup_test_state().await)).unwrap();
+    let (email, access_token) = register(&server, "access").await;
+
+    let response = server
+        .get("/protected/whoami")
+        .authorization_bearer(access_token)
+        .await;
+
+    response.assert_status(axum::http::StatusCode::OK);
+    response.assert_text(email);
+}
+
+#[tokio::test]
+async fn test_protected_route_without_token() {
+    let server = TestServer::new(protected_app(setup_test_state().await)).unwrap();
+
+    server
+        .get("/protected/whoami")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_protected_route_with_invalid_token() {
+    let server = TestServer::new(protected_app(setup_test_state().await)).unwrap();
+
+    server
+        .get("/protected/whoami")
+        .authorization_bearer("not.a.jwt")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_protected_route_after_logout() {
+    let server = TestServer::new(protected_app(setup_test_state().await)).unwrap();
+    let (_email, access_token) = register(&server, "access").await;
+
+    server
+        .post("/api/v1/auth/logout")
+        .authorization_bearer(access_token.clone())
+        .await
+        .assert_status(axum::http::StatusCode::OK);
+
+    server
+        .get("/protected/whoami")
+        .authorization_bearer(access_token)
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_extractor_without_middleware() {
+    let state = setup_test_state().await;
+    let app = create_app(state.clone()).merge(
+        Router::new()
+            .route("/whoami", get(whoami))
+            .with_state(state),
+    );
+    let server = TestServer::new(app).unwrap();
+    let (email, access_token) = register(&server, "access").await;
+
+    server
+        .get("/whoami")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    server
+        .get("/whoami")
+        .authorization_bearer(access_token)
+        .await
+        .assert_text(email);
+}
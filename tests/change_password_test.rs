@@ -0,0 +1,203 @@
+mod common;
+
+use axum_test::TestServer;
+use common::{
+    LoginRequest, RegisterRequest, TestLoginResponse, TestRegisterResponse, TestResponseCore,
+    setup_test_server,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const PASSWORD: &str = "password123";
+const NEW_PASSWORD: &str = "new_password456";
+
+#[derive(Serialize)]
+struct ChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+    revoke_other_sessions: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestChangePasswordResponse {
+    response: Option<TestChangePasswordCore>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestChangePasswordCore {
+    revoked_sessions: u64,
+}
+
+/// Registers from a desktop browser, then logs in from a phone.
+async fn register_on_two_devices(
+    server: &TestServer,
+) -> (String, TestResponseCore, TestResponseCore) {
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("change_password_{}@example.com", unique_id);
+
+    let desktop = server
+        .post("/api/v1/auth/register")
+        .add_header("User-Agent", "Desktop Browser")
+        .json(&RegisterRequest {
+            first_name: "Change".to_string(),
+            last_name: "Changer".to_string(),
+            email: email.clone(),
+            password: PASSWORD.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .json::<TestRegisterResponse>()
+        .response
+        .unwrap();
+
+    let phone = server
+        .post("/api/v1/auth/login")
+        .add_header("User-Agent", "Phone App")
+        .json(&LoginRequest {
+            email: email.clone(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .json::<TestLoginResponse>()
+        .response
+        .unwrap();
+
+    (email, desktop, phone)
+}
+
+fn change_request(current_password: &str, revoke_other_sessions: bool) -> ChangePasswordRequest {
+    ChangePasswordRequest {
+        current_password: current_password.to_string(),
+        new_password: NEW_PASSWORD.to_string(),
+        revoke_other_sessions,
+    }
+}
+
+#[tokio::test]
+async fn test_change_password_success() {
+    let server = setup_test_server().await;
+    let (email, desktop, phone) = register_on_two_devices(&server).await;
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(desktop.access_token.unwrap())
+        .json(&change_request(PASSWORD, false))
+        .await;
+
+    response.assert_status_ok();
+    let core = response
+        .json::<TestChangePasswordResponse>()
+        .response
+        .unwrap();
+    assert_eq!(core.revoked_sessions, 0);
+
+    // Other devices stay logged in by default
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(phone.access_token.unwrap())
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email: email.clone(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email,
+            password: NEW_PASSWORD.to_string(),
+        })
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_change_password_revokes_other_sessions() {
+    let server = setup_test_server().await;
+    let (_, desktop, phone) = register_on_two_devices(&server).await;
+    let desktop_access_token = desktop.access_token.unwrap();
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(&desktop_access_token)
+        .json(&change_request(PASSWORD, true))
+        .await;
+
+    response.assert_status_ok();
+    let core = response
+        .json::<TestChangePasswordResponse>()
+        .response
+        .unwrap();
+    assert_eq!(core.revoked_sessions, 1);
+
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(phone.access_token.unwrap())
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&desktop_access_token)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_change_password_wrong_current_password() {
+    let server = setup_test_server().await;
+    let (_, desktop, _) = register_on_two_devices(&server).await;
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(desktop.access_token.unwrap())
+        .json(&change_request("wrong_password", false))
+        .await;
+
+    response.assert_status_forbidden();
+    assert_eq!(
+        response.json::<TestChangePasswordResponse>().error.unwrap(),
+        "Current password is incorrect"
+    );
+}
+
+#[tokio::test]
+async fn test_change_password_rejects_reuse() {
+    let server = setup_test_server().await;
+    let (_, desktop, _) = register_on_two_devices(&server).await;
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(desktop.access_token.unwrap())
+        .json(&ChangePasswordRequest {
+            current_password: PASSWORD.to_string(),
+            new_password: PASSWORD.to_string(),
+            revoke_other_sessions: false,
+        })
+        .await;
+
+    response.assert_status_bad_request();
+    assert_eq!(
+        response.json::<TestChangePasswordResponse>().error.unwrap(),
+        "New password must differ from the current password"
+    );
+}
+
+#[tokio::test]
+async fn test_change_password_requires_authentication() {
+    let server = setup_test_server().await;
+
+    server
+        .post("/api/v1/auth/password")
+        .json(&change_request(PASSWORD, false))
+        .await
+        .assert_status_unauthorized();
+}
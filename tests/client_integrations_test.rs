@@ -0,0 +1,158 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use chat_auth_server::utils::load_config::ClientIntegrationsSection;
+use common::{login, make_admin, register_user, setup_test_state};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct TestErrorResponse {
+    response_message: String,
+    error: Option<String>,
+}
+
+fn every_flag(enabled: bool) -> ClientIntegrationsSection {
+    ClientIntegrationsSection {
+        allow_access_middleware: enabled,
+        allow_sessions_middleware: enabled,
+        allow_logging_middleware: enabled,
+        allow_request_timeout_middleware: enabled,
+        allow_admin_routes_protector_middleware: enabled,
+        allow_rate_limit_middleware: false,
+    }
+}
+
+/// A server built with the given flags and request timeout.
+async fn setup(
+    client_integrations: ClientIntegrationsSection,
+    request_timeout_secs: u64,
+) -> (TestServer, PgPool) {
+    let mut state = setup_test_state().await;
+    let config = Arc::get_mut(&mut state.config).unwrap();
+    config.client_integrations = client_integrations;
+    config.server.as_mut().unwrap().request_timeout_secs = request_timeout_secs;
+    let db = state.db.clone();
+
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+    (server, db)
+}
+
+/// Registers a user and returns its id and access token.
+async fn register(server: &TestServer) -> (i64, String) {
+    let (_, registered) = register_user(server, "integrations").await;
+
+    (
+        registered.user_profile.unwrap().id,
+        registered.access_token.unwrap(),
+    )
+}
+
+/// Seconds since the user's session was last used.
+async fn seconds_since_last_use(db: &PgPool, user_id: i64) -> f64 {
+    sqlx::query_scalar(
+        "SELECT EXTRACT(EPOCH FROM NOW() - last_used_at)::float8 FROM sessions WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .unwrap()
+}
+
+async fn age_session(db: &PgPool, user_id: i64) {
+    sqlx::query("UPDATE sessions SET last_used_at = NOW() - INTERVAL '1 hour' WHERE user_id = $1")
+        .bind(user_id)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_request_timeout_follows_its_flag() {
+    let email = format!("integrations_{}@example.com", Uuid::new_v4());
+
+    // A zero timeout cuts off every request that has to wait on the database
+    let (server, _) = setup(every_flag(true), 0).await;
+    login(&server, &email)
+        .await
+        .assert_status(StatusCode::REQUEST_TIMEOUT);
+
+    let (server, _) = setup(every_flag(false), 0).await;
+    login(&server, &email).await.assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_sessions_middleware_records_session_activity() {
+    let (server, db) = setup(every_flag(true), 60).await;
+    let (user_id, access_token) = register(&server).await;
+    age_session(&db, user_id).await;
+
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+    assert!(seconds_since_last_use(&db, user_id).await < 60.0);
+
+    // Without the middleware only refreshes count as activity
+    let (server, db) = setup(every_flag(false), 60).await;
+    let (user_id, access_token) = register(&server).await;
+    age_session(&db, user_id).await;
+
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+    assert!(seconds_since_last_use(&db, user_id).await >= 3600.0);
+}
+
+#[tokio::test]
+async fn test_admin_routes_protector_refuses_non_admins() {
+    let (server, db) = setup(every_flag(true), 60).await;
+    let (user_id, access_token) = register(&server).await;
+    let unlock_path = format!("/api/v1/auth/admin/users/{}/unlock", user_id);
+
+    server.post(&unlock_path).await.assert_status_unauthorized();
+
+    let response = server
+        .post(&unlock_path)
+        .authorization_bearer(&access_token)
+        .await;
+    response.assert_status_forbidden();
+    let body = response.json::<TestErrorResponse>();
+    assert_eq!(body.response_message, "Access denied");
+    assert_eq!(body.error.as_deref(), Some("Admin access required"));
+
+    make_admin(&db, user_id).await;
+    server
+        .post(&unlock_path)
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_handlers_check_credentials_without_the_middlewares() {
+    let (server, _) = setup(every_flag(false), 60).await;
+    let (user_id, access_token) = register(&server).await;
+
+    server
+        .get("/api/v1/auth/sessions")
+        .await
+        .assert_status_unauthorized();
+
+    let response = server
+        .post(&format!("/api/v1/auth/admin/users/{}/unlock", user_id))
+        .authorization_bearer(&access_token)
+        .await;
+    response.assert_status_forbidden();
+    assert_eq!(
+        response.json::<TestErrorResponse>().response_message,
+        "Account unlock failed"
+    );
+}
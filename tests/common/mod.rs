@@ -1,10 +1,25 @@
 use axum_test::TestServer;
 use chat_auth_server::db::connect_postgres::connect_pg;
+use chat_auth_server::mailer::{EmailMessage, MemoryMailer};
+use chat_auth_server::rate_limit::MemoryRateLimitStore;
+use chat_auth_server::sms::MemorySmsSender;
+use chat_auth_server::utils::hashing_handler::PasswordHashing;
+use chat_auth_server::utils::jwt_keys::JwtKeys;
 use chat_auth_server::utils::load_config::load_config;
+use chat_auth_server::utils::password_blocklist::PasswordBlocklist;
+use chat_auth_server::utils::totp_handler::totp_code_at;
 use chat_auth_server::{AppState, create_app};
+use sqlx::PgPool;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
-pub async fn setup_test_server() -> TestServer {
+/// Password of the users made by [`register`].
+#[allow(dead_code)]
+pub const TEST_PASSWORD: &str = "secure_password123";
+
+#[allow(dead_code)]
+pub async fn setup_test_state() -> AppState {
     dotenvy::from_filename(".env.development").ok();
 
     let app_config = load_config().expect("Failed to load config");
@@ -36,15 +51,202 @@ pub async fn setup_test_server() -> TestServer {
     )
     .await;
 
-    let state = AppState {
+    let jwt_keys = JwtKeys::from_config(
+        app_config
+            .auth
+            .as_ref()
+            .expect("SERVER START-UP ERROR: AUTH CONFIGURATION IS MISSING!"),
+    )
+    .expect("SERVER START-UP ERROR: FAILED TO LOAD JWT SIGNING KEYS!");
+
+    let password_blocklist = PasswordBlocklist::from_config(app_config.security.as_ref())
+        .expect("SERVER START-UP ERROR: FAILED TO LOAD THE PASSWORD BLOCKLIST!");
+
+    let password_hashing = PasswordHashing::from_config(app_config.security.as_ref())
+        .expect("SERVER START-UP ERROR: FAILED TO SET UP PASSWORD HASHING!");
+
+    AppState {
         config: Arc::new(app_config),
         db: db_pool,
-    };
+        jwt_keys: Arc::new(jwt_keys),
+        mailer: Arc::new(MemoryMailer::default()),
+        sms_sender: Arc::new(MemorySmsSender::default()),
+        password_blocklist: Arc::new(password_blocklist),
+        password_hashing: Arc::new(password_hashing),
+        rate_limit_store: Arc::new(MemoryRateLimitStore::default()),
+    }
+}
 
-    let app = create_app(state);
+#[allow(dead_code)]
+pub async fn setup_test_server() -> TestServer {
+    let app = create_app(setup_test_state().await);
     TestServer::new(app).expect("Failed to create test server")
 }
 
+/// Gives the state a fresh in-memory mailer and returns it, to read sent messages back.
+#[allow(dead_code)]
+pub fn use_memory_mailer(state: &mut AppState) -> Arc<MemoryMailer> {
+    let mailer = Arc::new(MemoryMailer::default());
+    state.mailer = mailer.clone();
+    mailer
+}
+
+/// Gives the state a fresh in-memory SMS sender and returns it, to read sent messages back.
+#[allow(dead_code)]
+pub fn use_memory_sms_sender(state: &mut AppState) -> Arc<MemorySmsSender> {
+    let sms_sender = Arc::new(MemorySmsSender::default());
+    state.sms_sender = sms_sender.clone();
+    sms_sender
+}
+
+/// Waits for a message to `to` with `subject` in its subject, for mail sent after the
+/// response, and returns the latest one.
+#[allow(dead_code)]
+pub async fn wait_for_mail(mailer: &MemoryMailer, to: &str, subject: &str) -> EmailMessage {
+    for _ in 0..100 {
+        let message = mailer
+            .sent()
+            .into_iter()
+            .rev()
+            .find(|message| message.to == to && message.subject.contains(subject));
+        if let Some(message) = message {
+            return message;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    panic!("No \"{}\" message sent to {}", subject, to)
+}
+
+/// Returns the token carried by a message sent without `mail.public_base_url`.
+#[allow(dead_code)]
+pub fn token_from_mail(message: &EmailMessage) -> String {
+    message
+        .text_body
+        .split_whitespace()
+        .find(|word| word.starts_with("eyJ"))
+        .expect("Message carries no token")
+        .to_string()
+}
+
+/// The code an authenticator set up with `secret` shows `offset_in_steps` 30-second steps
+/// from now.
+#[allow(dead_code)]
+pub fn totp_code(secret: &str, offset_in_steps: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    totp_code_at(secret, (now + offset_in_steps * 30) as u64).unwrap()
+}
+
+/// Registers a user with [`TEST_PASSWORD`] and an email starting with `email_prefix`, and
+/// returns the email and the registration's profile and tokens.
+#[allow(dead_code)]
+pub async fn register_user(server: &TestServer, email_prefix: &str) -> (String, TestResponseCore) {
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("{}_{}@example.com", email_prefix, unique_id);
+
+    let response = server
+        .post("/api/v1/auth/register")
+        .json(&RegisterRequest {
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+            email: email.clone(),
+            password: TEST_PASSWORD.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+
+    (
+        email,
+        response.json::<TestRegisterResponse>().response.unwrap(),
+    )
+}
+
+/// Like [`register_user`], returning the email and access token only.
+#[allow(dead_code)]
+pub async fn register(server: &TestServer, email_prefix: &str) -> (String, String) {
+    let (email, registered) = register_user(server, email_prefix).await;
+
+    (email, registered.access_token.unwrap())
+}
+
+/// Logs in with [`TEST_PASSWORD`].
+#[allow(dead_code)]
+pub async fn login(server: &TestServer, email: &str) -> axum_test::TestResponse {
+    login_with_password(server, email, TEST_PASSWORD).await
+}
+
+/// Logs in with `password`.
+#[allow(dead_code)]
+pub async fn login_with_password(
+    server: &TestServer,
+    email: &str,
+    password: &str,
+) -> axum_test::TestResponse {
+    server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email: email.to_string(),
+            password: password.to_string(),
+        })
+        .await
+}
+
+/// Makes the user an admin.
+#[allow(dead_code)]
+pub async fn make_admin(db: &PgPool, user_id: i64) {
+    sqlx::query("UPDATE users SET is_admin = TRUE WHERE id = $1")
+        .bind(user_id)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+/// Enrolls an authenticator, passing `code` as the second factor when re-enrolling, and
+/// confirms it with the previous step's code. Returns the secret and the recovery codes
+/// handed out, if any.
+#[allow(dead_code)]
+pub async fn enroll_and_confirm(
+    server: &TestServer,
+    access_token: &str,
+    code: Option<String>,
+) -> (String, Option<Vec<String>>) {
+    let response = server
+        .post("/api/v1/auth/mfa/totp/enroll")
+        .authorization_bearer(access_token)
+        .json(&EnrollTotpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code,
+        })
+        .await;
+    response.assert_status_ok();
+    let secret = response
+        .json::<TestEnrollTotpResponse>()
+        .response
+        .unwrap()
+        .secret;
+
+    let response = server
+        .post("/api/v1/auth/mfa/totp/confirm")
+        .authorization_bearer(access_token)
+        .json(&TotpCodeRequest {
+            code: totp_code(&secret, -1),
+        })
+        .await;
+    response.assert_status_ok();
+    let recovery_codes = response
+        .json::<TestRecoveryCodesResponse>()
+        .response
+        .map(|core| core.recovery_codes);
+
+    (secret, recovery_codes)
+}
+
 use serde::{Deserialize, Serialize};
 
 #[allow(dead_code)]
@@ -65,6 +267,12 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[allow(dead_code)]
+#[derive(Serialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub struct TestRegisterResponse {
@@ -81,9 +289,18 @@ pub struct TestLoginResponse {
     pub error: Option<String>,
 }
 
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestRefreshResponse {
+    pub response_message: String,
+    pub response: Option<TestResponseCore>,
+    pub error: Option<String>,
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub struct TestResponseCore {
+    #[serde(default)]
     pub user_profile: Option<TestUserProfile>,
     pub access_token: Option<String>,
     pub refresh_token: Option<String>,
@@ -95,4 +312,101 @@ pub struct TestUserProfile {
     pub id: i64,
     pub full_name: String,
     pub email: String,
+    pub phone_number: String,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestSessionsResponse {
+    pub response_message: String,
+    pub response: Option<TestSessionsCore>,
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestSessionsCore {
+    pub sessions: Vec<TestSession>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestSession {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub last_seen: String,
+    pub current: bool,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize)]
+pub struct EnrollTotpRequest {
+    pub password: String,
+    pub code: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize)]
+pub struct TotpCodeRequest {
+    pub code: String,
+}
+
+/// The password and second factor sensitive operations take.
+#[allow(dead_code)]
+#[derive(Serialize)]
+pub struct StepUpRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestEnrollTotpResponse {
+    pub response_message: String,
+    pub response: Option<TestEnrollTotpCore>,
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestEnrollTotpCore {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestRecoveryCodesResponse {
+    pub response_message: String,
+    pub response: Option<TestRecoveryCodesCore>,
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestRecoveryCodesCore {
+    pub recovery_codes: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestMfaRequiredResponse {
+    pub response_message: String,
+    pub response: Option<TestMfaChallenge>,
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct TestMfaChallenge {
+    pub mfa_token: String,
+    pub expires_at: usize,
 }
@@ -0,0 +1,132 @@
+mod common;
+
+use axum_test::TestServer;
+use common::{
+    LoginRequest, RegisterRequest, TestLoginResponse, TestRefreshResponse, setup_test_server,
+};
+use uuid::Uuid;
+
+const AUTH_COOKIE: &str = "rusty_chat_auth_cookie";
+
+async fn register_and_login(server: &TestServer) -> axum_test::TestResponse {
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("cookie_{}@example.com", unique_id);
+
+    server
+        .post("/api/v1/auth/register")
+        .json(&RegisterRequest {
+            first_name: "Cookie".to_string(),
+            last_name: "User".to_string(),
+            email: email.clone(),
+            password: "password123".to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    let response = server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email,
+            password: "password123".to_string(),
+        })
+        .await;
+
+    response.assert_status_ok();
+    response
+}
+
+#[tokio::test]
+async fn test_login_cookie_holds_refresh_token() {
+    let server = setup_test_server().await;
+    let response = register_and_login(&server).await;
+
+    let cookie = response.cookie(AUTH_COOKIE);
+    let body = response.json::<TestLoginResponse>();
+
+    assert_eq!(
+        cookie.value(),
+        body.response.unwrap().refresh_token.unwrap()
+    );
+    assert_eq!(cookie.http_only(), Some(true));
+}
+
+#[tokio::test]
+async fn test_cookie_authenticates_protected_routes() {
+    let server = setup_test_server().await;
+    let cookie = register_and_login(&server).await.cookie(AUTH_COOKIE);
+
+    server
+        .get("/api/v1/auth/sessions")
+        .add_cookie(cookie)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_refresh_with_cookie_only() {
+    let server = setup_test_server().await;
+    let cookie = register_and_login(&server).await.cookie(AUTH_COOKIE);
+
+    let response = server
+        .post("/api/v1/auth/refresh")
+        .add_cookie(cookie.clone())
+        .await;
+
+    response.assert_status_ok();
+    let rotated_cookie = response.cookie(AUTH_COOKIE);
+    let body = response.json::<TestRefreshResponse>();
+    assert_eq!(
+        rotated_cookie.value(),
+        body.response.unwrap().refresh_token.unwrap()
+    );
+    assert_ne!(rotated_cookie.value(), cookie.value());
+
+    // The rotated-out cookie no longer authenticates, the new one does
+    server
+        .get("/api/v1/auth/sessions")
+        .add_cookie(cookie)
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    server
+        .get("/api/v1/auth/sessions")
+        .add_cookie(rotated_cookie)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_forged_cookie_is_rejected() {
+    let server = setup_test_server().await;
+
+    server
+        .get("/api/v1/auth/sessions")
+        .add_header("Cookie", format!("{}=rusty_chat____forged", AUTH_COOKIE))
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    server
+        .post("/api/v1/auth/refresh")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logout_with_cookie_clears_it() {
+    let server = setup_test_server().await;
+    let cookie = register_and_login(&server).await.cookie(AUTH_COOKIE);
+
+    let response = server
+        .post("/api/v1/auth/logout")
+        .add_cookie(cookie.clone())
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.cookie(AUTH_COOKIE).value(), "");
+
+    server
+        .post("/api/v1/auth/refresh")
+        .add_cookie(cookie)
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use axum_test::TestServer;
+use chat_auth_server::mailer::MemoryMailer;
+use chat_auth_server::{AppState, create_app};
+use common::{login, register_user, setup_test_state, token_from_mail, use_memory_mailer};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+struct VerifyEmailRequest {
+    token: String,
+}
+
+#[derive(Serialize)]
+struct ResendVerificationRequest {
+    email: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestVerifyEmailResponse {
+    response: Option<TestVerifyEmailCore>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestVerifyEmailCore {
+    email: String,
+    email_verified_at: String,
+}
+
+async fn setup(require_verified_email: bool) -> (TestServer, AppState, Arc<MemoryMailer>) {
+    let mut state = setup_test_state().await;
+    let mailer = use_memory_mailer(&mut state);
+    Arc::get_mut(&mut state.config)
+        .unwrap()
+        .auth
+        .as_mut()
+        .unwrap()
+        .require_verified_email = require_verified_email;
+
+    let server = TestServer::new(create_app(state.clone())).expect("Failed to create test server");
+    (server, state, mailer)
+}
+
+/// The token of the latest verification message sent to `email`.
+fn verification_token(mailer: &MemoryMailer, email: &str) -> String {
+    let message = mailer.last_sent_to(email).expect("No verification sent");
+    assert!(message.subject.contains("Verify"));
+
+    token_from_mail(&message)
+}
+
+#[tokio::test]
+async fn test_verify_email_success() {
+    let (server, _, mailer) = setup(false).await;
+    let (email, _) = register_user(&server, "verify").await;
+    let token = verification_token(&mailer, &email);
+
+    let first = server
+        .post("/api/v1/auth/verify-email")
+        .json(&VerifyEmailRequest {
+            token: token.clone(),
+        })
+        .await;
+    first.assert_status_ok();
+    let first = first.json::<TestVerifyEmailResponse>().response.unwrap();
+    assert_eq!(first.email, email);
+
+    // Verifying again is harmless and keeps the original time
+    let second = server
+        .post("/api/v1/auth/verify-email")
+        .json(&VerifyEmailRequest { token })
+        .await;
+    second.assert_status_ok();
+    assert_eq!(
+        second
+            .json::<TestVerifyEmailResponse>()
+            .response
+            .unwrap()
+            .email_verified_at,
+        first.email_verified_at
+    );
+}
+
+#[tokio::test]
+async fn test_unverified_users_cannot_log_in_when_required() {
+    let (server, _, mailer) = setup(true).await;
+    let (email, registered) = register_user(&server, "verify").await;
+
+    assert!(registered.access_token.is_none());
+    assert!(registered.refresh_token.is_none());
+
+    login(&server, &email)
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    let token = verification_token(&mailer, &email);
+    server
+        .post("/api/v1/auth/verify-email")
+        .json(&VerifyEmailRequest { token })
+        .await
+        .assert_status_ok();
+
+    login(&server, &email).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_verify_email_rejects_stale_and_wrong_tokens() {
+    let (server, state, mailer) = setup(false).await;
+    let (email, registered) = register_user(&server, "verify").await;
+    let user_id = registered.user_profile.unwrap().id;
+    let token = verification_token(&mailer, &email);
+
+    // An access token is not a verification token
+    server
+        .post("/api/v1/auth/verify-email")
+        .json(&VerifyEmailRequest {
+            token: registered.access_token.unwrap(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    // A token sent to a previous address does not verify the new one
+    sqlx::query("UPDATE users SET email = $1 WHERE id = $2")
+        .bind(format!("changed_{}", email))
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    server
+        .post("/api/v1/auth/verify-email")
+        .json(&VerifyEmailRequest { token })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_resend_verification_does_not_reveal_accounts() {
+    let (server, _, mailer) = setup(false).await;
+    let (email, _) = register_user(&server, "verify").await;
+
+    let known = server
+        .post("/api/v1/auth/resend-verification")
+        .json(&ResendVerificationRequest {
+            email: email.clone(),
+        })
+        .await;
+    let unknown = server
+        .post("/api/v1/auth/resend-verification")
+        .json(&ResendVerificationRequest {
+            email: format!("nobody_{}@example.com", Uuid::new_v4()),
+        })
+        .await;
+
+    known.assert_status_ok();
+    unknown.assert_status_ok();
+    assert_eq!(known.text(), unknown.text());
+
+    // Registration and the resend each sent a verification
+    let sent = mailer.sent();
+    assert_eq!(sent.iter().filter(|message| message.to == email).count(), 2);
+    assert_eq!(sent.len(), 2);
+}
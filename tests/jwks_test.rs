@@ -0,0 +1,106 @@
+mod common;
+
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use chat_auth_server::utils::jwt_keys::JwtKeys;
+use common::{LoginRequest, RegisterRequest, TestLoginResponse, setup_test_state};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct JwksClaims {
+    id: i64,
+    email: String,
+}
+
+async fn rs256_server() -> TestServer {
+    let mut state = setup_test_state().await;
+    state.jwt_keys = Arc::new(
+        JwtKeys::from_pem(
+            Algorithm::RS256,
+            include_bytes!("fixtures/jwt_keys/rs256_private.pem"),
+            include_bytes!("fixtures/jwt_keys/rs256_public.pem"),
+            None,
+        )
+        .expect("Failed to load RS256 fixture keys"),
+    );
+
+    TestServer::new(create_app(state)).expect("Failed to create test server")
+}
+
+#[tokio::test]
+async fn test_jwks_is_empty_for_shared_secret() {
+    let server = TestServer::new(create_app(setup_test_state().await))
+        .expect("Failed to create test server");
+
+    let response = server.get("/.well-known/jwks.json").await;
+
+    response.assert_status(axum::http::StatusCode::OK);
+    assert!(response.json::<JwkSet>().keys.is_empty());
+}
+
+#[tokio::test]
+async fn test_jwks_verifies_issued_tokens() {
+    let server = rs256_server().await;
+
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("jwks_{}@example.com", unique_id);
+    let password = "secure_password123";
+
+    server
+        .post("/api/v1/auth/register")
+        .json(&RegisterRequest {
+            first_name: "Jwks".to_string(),
+            last_name: "User".to_string(),
+            email: email.clone(),
+            password: password.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    let login = server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email: email.clone(),
+            password: password.to_string(),
+        })
+        .await;
+    login.assert_status(axum::http::StatusCode::OK);
+    let access_token = login
+        .json::<TestLoginResponse>()
+        .response
+        .unwrap()
+        .access_token
+        .unwrap();
+
+    // A downstream service only needs the published key to verify the token
+    let jwks = server.get("/.well-known/jwks.json").await.json::<JwkSet>();
+    let header = decode_header(&access_token).unwrap();
+    assert_eq!(header.alg, Algorithm::RS256);
+
+    let jwk = jwks.find(header.kid.as_deref().unwrap()).unwrap();
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&["krabby_chat"]);
+    validation.set_issuer(&["chat_auth_server"]);
+    let claims = decode::<JwksClaims>(
+        &access_token,
+        &DecodingKey::from_jwk(jwk).unwrap(),
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claims.email, email);
+    assert!(claims.id > 0);
+
+    // The server itself accepts the RS256 token on protected routes
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status(axum::http::StatusCode::OK);
+}
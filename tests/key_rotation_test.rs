@@ -0,0 +1,94 @@
+mod common;
+
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use chat_auth_server::utils::jwt_keys::JwtKeys;
+use common::{make_admin, register, register_user, setup_test_state};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[derive(Deserialize, Debug)]
+struct TestRotateResponse {
+    response: Option<TestRotateCore>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestRotateCore {
+    kid: String,
+    retired_kids: Vec<String>,
+}
+
+/// A server whose running key differs from the configured one, as after editing the config.
+async fn server_with_stale_key() -> (TestServer, PgPool, String) {
+    let mut state = setup_test_state().await;
+    let keys = JwtKeys::from_secret(b"pre_rotation_secret", None);
+    let old_kid = keys.kid();
+    state.jwt_keys = Arc::new(keys);
+    let db = state.db.clone();
+
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+    (server, db, old_kid)
+}
+
+#[tokio::test]
+async fn test_rotation_keeps_existing_tokens_valid() {
+    let (server, db, old_kid) = server_with_stale_key().await;
+    let (_, admin) = register_user(&server, "rotation").await;
+    make_admin(&db, admin.user_profile.unwrap().id).await;
+    let old_token = admin.access_token.unwrap();
+
+    let response = server
+        .post("/api/v1/auth/keys/rotate")
+        .authorization_bearer(&old_token)
+        .await;
+
+    response.assert_status(axum::http::StatusCode::OK);
+    let rotated = response.json::<TestRotateResponse>().response.unwrap();
+    assert_ne!(rotated.kid, old_kid);
+    assert_eq!(rotated.retired_kids, vec![old_kid]);
+
+    // Tokens signed with the retired key keep working, new tokens use the new key
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&old_token)
+        .await
+        .assert_status(axum::http::StatusCode::OK);
+
+    let (_, new_token) = register(&server, "rotation").await;
+    let header = jsonwebtoken::decode_header(&new_token).unwrap();
+    assert_eq!(header.kid, Some(rotated.kid));
+
+    // The configured key is now current, so rotating again has nothing to do
+    let response = server
+        .post("/api/v1/auth/keys/rotate")
+        .authorization_bearer(&old_token)
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_rotation_requires_admin() {
+    let (server, _, old_kid) = server_with_stale_key().await;
+    let (_, token) = register(&server, "rotation").await;
+
+    let response = server
+        .post("/api/v1/auth/keys/rotate")
+        .authorization_bearer(&token)
+        .await;
+
+    response.assert_status(axum::http::StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<TestRotateResponse>().error.as_deref(),
+        Some("Admin access required")
+    );
+
+    server
+        .post("/api/v1/auth/keys/rotate")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    let header = jsonwebtoken::decode_header(&token).unwrap();
+    assert_eq!(header.kid, Some(old_kid));
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use common::{
+    TEST_PASSWORD, TestLoginResponse, login, login_with_password, make_admin, register,
+    register_user, setup_test_state,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A server locking accounts after `max_failed_attempts` failed logins in a row.
+async fn setup(
+    max_failed_attempts: u32,
+    backoff_base_delay_in_seconds: u64,
+) -> (TestServer, PgPool) {
+    let mut state = setup_test_state().await;
+    let security = Arc::get_mut(&mut state.config)
+        .unwrap()
+        .security
+        .as_mut()
+        .unwrap();
+    security.login_max_failed_attempts = max_failed_attempts;
+    security.login_backoff_base_delay_in_seconds = backoff_base_delay_in_seconds;
+    let db = state.db.clone();
+
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+    (server, db)
+}
+
+#[tokio::test]
+async fn test_account_locks_after_too_many_failed_logins() {
+    let (server, db) = setup(3, 0).await;
+    let (email, registered) = register_user(&server, "lockout").await;
+    let user_id = registered.user_profile.unwrap().id;
+
+    for _ in 0..2 {
+        login_with_password(&server, &email, "wrong_password")
+            .await
+            .assert_status_unauthorized();
+    }
+
+    // The third failure locks the account
+    let response = login_with_password(&server, &email, "wrong_password").await;
+    response.assert_status(StatusCode::LOCKED);
+    let retry_after: u64 = response
+        .header("retry-after")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+
+    // Even the right password is refused until the lock lifts
+    let response = login(&server, &email).await;
+    response.assert_status(StatusCode::LOCKED);
+    assert!(
+        response
+            .json::<TestLoginResponse>()
+            .error
+            .unwrap()
+            .contains("locked")
+    );
+
+    // Once the lock has expired the account can log in again
+    sqlx::query("UPDATE users SET locked_until = NOW() - INTERVAL '1 second' WHERE id = $1")
+        .bind(user_id)
+        .execute(&db)
+        .await
+        .unwrap();
+    login(&server, &email).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_successful_login_clears_failed_logins() {
+    let (server, _) = setup(3, 0).await;
+    let (email, _) = register(&server, "lockout").await;
+
+    for password in ["wrong_password", "wrong_password", TEST_PASSWORD] {
+        login_with_password(&server, &email, password).await;
+    }
+
+    // The count started over, so two more failures do not lock the account
+    for _ in 0..2 {
+        login_with_password(&server, &email, "wrong_password")
+            .await
+            .assert_status_unauthorized();
+    }
+    login(&server, &email).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_repeated_failures_back_off() {
+    let (server, _) = setup(0, 60).await;
+    let (email, _) = register(&server, "lockout").await;
+
+    // A single typo costs nothing
+    login_with_password(&server, &email, "wrong_password")
+        .await
+        .assert_status_unauthorized();
+    login_with_password(&server, &email, "wrong_password")
+        .await
+        .assert_status_unauthorized();
+
+    let response = login(&server, &email).await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response
+        .header("retry-after")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+}
+
+#[tokio::test]
+async fn test_admin_unlocks_account() {
+    let (server, db) = setup(1, 0).await;
+    let (email, registered) = register_user(&server, "lockout").await;
+    let user_id = registered.user_profile.unwrap().id;
+    let user_token = registered.access_token.unwrap();
+    let (_, admin) = register_user(&server, "lockout").await;
+    make_admin(&db, admin.user_profile.unwrap().id).await;
+    let admin_token = admin.access_token.unwrap();
+
+    login_with_password(&server, &email, "wrong_password")
+        .await
+        .assert_status(StatusCode::LOCKED);
+
+    let unlock_path = format!("/api/v1/auth/admin/users/{}/unlock", user_id);
+
+    server
+        .post(&unlock_path)
+        .authorization_bearer(&user_token)
+        .await
+        .assert_status_forbidden();
+    login(&server, &email)
+        .await
+        .assert_status(StatusCode::LOCKED);
+
+    server
+        .post(&unlock_path)
+        .authorization_bearer(&admin_token)
+        .await
+        .assert_status_ok();
+    login(&server, &email).await.assert_status_ok();
+
+    server
+        .post("/api/v1/auth/admin/users/0/unlock")
+        .authorization_bearer(&admin_token)
+        .await
+        .assert_status_not_found();
+}
+
+/// What a client sees of a refused login, without the seconds left, which may differ by one.
+fn refusal(response: &axum_test::TestResponse) -> (StatusCode, Option<String>, bool) {
+    let error = response
+        .json::<TestLoginResponse>()
+        .error
+        .map(|error| error.replace(|c: char| c.is_ascii_digit(), ""));
+
+    (
+        response.status_code(),
+        error,
+        response.maybe_header("retry-after").is_some(),
+    )
+}
+
+#[tokio::test]
+async fn test_unknown_email_is_answered_like_a_registered_one() {
+    let (server, _) = setup(3, 0).await;
+    let (email, _) = register(&server, "lockout").await;
+    let unknown_email = format!("lockout_{}@example.com", Uuid::new_v4());
+
+    for _ in 0..4 {
+        let registered = login_with_password(&server, &email, "wrong_password").await;
+        let unknown = login_with_password(&server, &unknown_email, "wrong_password").await;
+
+        assert_eq!(refusal(&registered), refusal(&unknown));
+    }
+    login_with_password(&server, &unknown_email, "wrong_password")
+        .await
+        .assert_status(StatusCode::LOCKED);
+
+    // Case does not make a fresh start
+    login_with_password(&server, &unknown_email.to_uppercase(), "wrong_password")
+        .await
+        .assert_status(StatusCode::LOCKED);
+}
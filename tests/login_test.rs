@@ -1,6 +1,14 @@
 mod common;
 
-use common::{LoginRequest, RegisterRequest, TestLoginResponse, setup_test_server};
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use chat_auth_server::utils::hashing_handler::{PasswordHashing, needs_rehash};
+use chat_auth_server::utils::load_config::SecuritySection;
+use common::{
+    LoginRequest, RefreshRequest, RegisterRequest, TestLoginResponse, setup_test_server,
+    setup_test_state, use_memory_mailer,
+};
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[tokio::test]
@@ -95,3 +103,167 @@ async fn test_login_non_existent_user() {
 
     response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn test_login_on_second_device_keeps_first_session() {
+    let server = setup_test_server().await;
+
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("multi_device_{}@example.com", unique_id);
+    let password = "secure_password123";
+
+    server
+        .post("/api/v1/auth/register")
+        .json(&RegisterRequest {
+            first_name: "Multi".to_string(),
+            last_name: "Device".to_string(),
+            email: email.clone(),
+            password: password.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    let mut refresh_tokens = Vec::new();
+    for user_agent in ["Desktop Browser", "Phone App"] {
+        let response = server
+            .post("/api/v1/auth/login")
+            .add_header("User-Agent", user_agent)
+            .json(&LoginRequest {
+                email: email.clone(),
+                password: password.to_string(),
+            })
+            .await;
+
+        response.assert_status(axum::http::StatusCode::OK);
+        let res = response.json::<TestLoginResponse>().response.unwrap();
+        refresh_tokens.push(res.refresh_token.unwrap());
+    }
+
+    // Logging in on the phone must not invalidate the desktop session
+    for refresh_token in refresh_tokens {
+        server
+            .post("/api/v1/auth/refresh")
+            .json(&RefreshRequest { refresh_token })
+            .await
+            .assert_status(axum::http::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn test_login_from_new_device_sends_alert() {
+    let mut state = setup_test_state().await;
+    let mailer = use_memory_mailer(&mut state);
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("new_device_{}@example.com", unique_id);
+    let password = "secure_password123";
+
+    server
+        .post("/api/v1/auth/register")
+        .add_header("User-Agent", "Desktop Browser")
+        .json(&RegisterRequest {
+            first_name: "New".to_string(),
+            last_name: "Device".to_string(),
+            email: email.clone(),
+            password: password.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    let alerts = || {
+        mailer
+            .sent()
+            .into_iter()
+            .filter(|message| message.to == email && message.subject.contains("New sign-in"))
+            .collect::<Vec<_>>()
+    };
+
+    for user_agent in ["Desktop Browser", "Phone App", "Phone App"] {
+        server
+            .post("/api/v1/auth/login")
+            .add_header("User-Agent", user_agent)
+            .json(&LoginRequest {
+                email: email.clone(),
+                password: password.to_string(),
+            })
+            .await
+            .assert_status(axum::http::StatusCode::OK);
+    }
+
+    // Only the first login from the phone was from an unknown device
+    let alerts = alerts();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].text_body.contains("Phone App"));
+    assert!(alerts[0].html_body.contains("Phone App"));
+}
+
+#[tokio::test]
+async fn test_login_rehashes_outdated_password_hash() {
+    let mut outdated_state = setup_test_state().await;
+    outdated_state.password_hashing = Arc::new(
+        PasswordHashing::from_config(Some(&SecuritySection {
+            password_hash_memory_cost_in_kib: 8192,
+            password_hash_iterations: 1,
+            password_hash_parallelism: 1,
+            ..SecuritySection::default()
+        }))
+        .unwrap(),
+    );
+    let outdated_server =
+        TestServer::new(create_app(outdated_state)).expect("Failed to create test server");
+
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("rehash_{}@example.com", unique_id);
+    let password = "secure_password123";
+
+    outdated_server
+        .post("/api/v1/auth/register")
+        .json(&RegisterRequest {
+            first_name: "Rehash".to_string(),
+            last_name: "User".to_string(),
+            email: email.clone(),
+            password: password.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    let state = setup_test_state().await;
+    let db = state.db.clone();
+    let hashing = state.password_hashing.clone();
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+
+    let stored_hash = || async {
+        sqlx::query_scalar::<_, String>("SELECT password FROM users WHERE email = $1")
+            .bind(&email)
+            .fetch_one(&db)
+            .await
+            .unwrap()
+    };
+
+    let outdated_hash = stored_hash().await;
+    assert!(outdated_hash.contains("$m=8192,t=1,p=1$"));
+    assert!(needs_rehash(&outdated_hash, &hashing));
+
+    for _ in 0..2 {
+        server
+            .post("/api/v1/auth/login")
+            .json(&LoginRequest {
+                email: email.clone(),
+                password: password.to_string(),
+            })
+            .await
+            .assert_status(axum::http::StatusCode::OK);
+    }
+
+    // The hash was upgraded on the first login and the new one keeps working
+    let current_hash = stored_hash().await;
+    assert_ne!(current_hash, outdated_hash);
+    assert!(!needs_rehash(&current_hash, &hashing));
+}
@@ -1,52 +1,111 @@
 mod common;
 
-use common::{RegisterRequest, TestLoginResponse, setup_test_server};
-use uuid::Uuid;
+use axum_test::TestServer;
+use common::{
+    RefreshRequest, TestLoginResponse, TestResponseCore, login, register_user, setup_test_server,
+};
 
-#[tokio::test]
-async fn test_logout_user_success() {
-    let server = setup_test_server().await;
+/// Opens a second session for the user.
+async fn open_session(server: &TestServer, email: &str) -> TestResponseCore {
+    let response = login(server, email).await;
 
-    let unique_id = Uuid::new_v4().to_string();
-    let email = format!("logout_{}@example.com", unique_id);
-    let password = "password123";
+    response.assert_status(axum::http::StatusCode::OK);
+    response.json::<TestLoginResponse>().response.unwrap()
+}
 
-    // Register first
+async fn refresh_status(server: &TestServer, session: &TestResponseCore) -> axum::http::StatusCode {
     server
-        .post("/api/v1/auth/register")
-        .json(&RegisterRequest {
-            first_name: "Logout".to_string(),
-            last_name: "User".to_string(),
-            email: email.clone(),
-            password: password.to_string(),
-            country: "TestCountry".to_string(),
-            phone_number: unique_id[0..10].to_string(),
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: session.refresh_token.clone().unwrap(),
         })
         .await
-        .assert_status(axum::http::StatusCode::CREATED);
+        .status_code()
+}
+
+#[tokio::test]
+async fn test_logout_user_success() {
+    let server = setup_test_server().await;
+    let (_email, session) = register_user(&server, "logout").await;
 
-    // Logout
     let response = server
-        .post(&format!("/api/v1/auth/logout?user_email={}", email))
+        .post("/api/v1/auth/logout")
+        .authorization_bearer(session.access_token.clone().unwrap())
         .await;
 
     response.assert_status(axum::http::StatusCode::OK);
     let body = response.json::<TestLoginResponse>();
     assert_eq!(body.response_message, "Logout successful");
 
-    // Auth cookie should be cleared (max-age=0)
-    // Note: TestServer's cookie handling might vary, but we expect the header to be present
+    // The logged-out session can neither be used nor renewed
+    server
+        .post("/api/v1/auth/logout")
+        .authorization_bearer(session.access_token.clone().unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        refresh_status(&server, &session).await,
+        axum::http::StatusCode::UNAUTHORIZED
+    );
 }
 
 #[tokio::test]
-async fn test_logout_non_existent_user() {
+async fn test_logout_only_current_session() {
     let server = setup_test_server().await;
+    let (email, first_session) = register_user(&server, "logout").await;
+    let second_session = open_session(&server, &email).await;
 
-    let response = server
-        .post("/api/v1/auth/logout?user_email=ghost@example.com")
-        .await;
+    server
+        .post("/api/v1/auth/logout")
+        .authorization_bearer(first_session.access_token.clone().unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::OK);
+
+    assert_eq!(
+        refresh_status(&server, &first_session).await,
+        axum::http::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        refresh_status(&server, &second_session).await,
+        axum::http::StatusCode::OK
+    );
+}
 
-    // Based on the controller implementation, it uses fetch_one which panics/errors if not found
-    // Let's see how it behaves. Usually it returns INTERNAL_SERVER_ERROR if fetch_one fails.
-    response.assert_status(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+#[tokio::test]
+async fn test_logout_all_sessions() {
+    let server = setup_test_server().await;
+    let (email, first_session) = register_user(&server, "logout").await;
+    let second_session = open_session(&server, &email).await;
+
+    server
+        .post("/api/v1/auth/logout?all_sessions=true")
+        .authorization_bearer(first_session.access_token.clone().unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::OK);
+
+    assert_eq!(
+        refresh_status(&server, &second_session).await,
+        axum::http::StatusCode::UNAUTHORIZED
+    );
+    server
+        .post("/api/v1/auth/logout")
+        .authorization_bearer(second_session.access_token.clone().unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logout_unauthenticated() {
+    let server = setup_test_server().await;
+
+    server
+        .post("/api/v1/auth/logout")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    // Naming another user no longer logs them out
+    server
+        .post("/api/v1/auth/logout?user_email=ghost@example.com")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
 }
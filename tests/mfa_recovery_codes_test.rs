@@ -0,0 +1,242 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use common::{
+    LoginRequest, MfaLoginRequest, StepUpRequest, TEST_PASSWORD, TestMfaRequiredResponse,
+    TestRecoveryCodesResponse, enroll_and_confirm, login, register, setup_test_state, totp_code,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+struct ChangePasswordRequest {
+    current_password: String,
+    code: Option<String>,
+    new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangePasswordResponse {
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MfaLoginResponse {
+    response: Option<MfaLoginCore>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MfaLoginCore {
+    user_profile: ProfileWithMfa,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileWithMfa {
+    mfa_enabled: bool,
+    recovery_codes_remaining: i64,
+}
+
+async fn setup() -> TestServer {
+    let state = setup_test_state().await;
+
+    TestServer::new(create_app(state)).expect("Failed to create test server")
+}
+
+/// Logs in with the password, then with `code` as the second factor.
+async fn login_with_code(server: &TestServer, email: &str, code: &str) -> axum_test::TestResponse {
+    let response = login(server, email).await;
+    response.assert_status(StatusCode::ACCEPTED);
+    let mfa_token = response
+        .json::<TestMfaRequiredResponse>()
+        .response
+        .unwrap()
+        .mfa_token;
+
+    server
+        .post("/api/v1/auth/login/mfa")
+        .json(&MfaLoginRequest {
+            mfa_token,
+            code: code.to_string(),
+        })
+        .await
+}
+
+#[tokio::test]
+async fn test_recovery_code_logs_in_once() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "recovery").await;
+    let (_, recovery_codes) = enroll_and_confirm(&server, &access_token, None).await;
+    let recovery_codes = recovery_codes.unwrap();
+    assert_eq!(recovery_codes.len(), 10);
+
+    // Case and separators do not matter
+    let typed = recovery_codes[0].replace('-', " ").to_uppercase();
+    let response = login_with_code(&server, &email, &typed).await;
+    response.assert_status_ok();
+    let profile = response
+        .json::<MfaLoginResponse>()
+        .response
+        .unwrap()
+        .user_profile;
+    assert!(profile.mfa_enabled);
+    assert_eq!(profile.recovery_codes_remaining, 9);
+
+    login_with_code(&server, &email, &recovery_codes[0])
+        .await
+        .assert_status_unauthorized();
+    login_with_code(&server, &email, &recovery_codes[1])
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_regenerating_replaces_every_code() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "recovery").await;
+    let (secret, old_codes) = enroll_and_confirm(&server, &access_token, None).await;
+    let old_codes = old_codes.unwrap();
+
+    server
+        .post("/api/v1/auth/mfa/recovery-codes")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: "wrong_password".to_string(),
+            code: totp_code(&secret, 0),
+        })
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // A recovery code is step-up proof as well
+    let response = server
+        .post("/api/v1/auth/mfa/recovery-codes")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: old_codes[0].clone(),
+        })
+        .await;
+    response.assert_status_ok();
+    let new_codes = response
+        .json::<TestRecoveryCodesResponse>()
+        .response
+        .unwrap()
+        .recovery_codes;
+    assert_eq!(new_codes.len(), 10);
+    assert!(new_codes.iter().all(|code| !old_codes.contains(code)));
+
+    login_with_code(&server, &email, &old_codes[1])
+        .await
+        .assert_status_unauthorized();
+    login_with_code(&server, &email, &new_codes[0])
+        .await
+        .assert_status_ok();
+
+    // A re-enrollment keeps the codes
+    let (_, recovery_codes) =
+        enroll_and_confirm(&server, &access_token, Some(new_codes[1].clone())).await;
+    assert!(recovery_codes.is_none());
+    let response = login_with_code(&server, &email, &new_codes[2]).await;
+    response.assert_status_ok();
+    assert_eq!(
+        response
+            .json::<MfaLoginResponse>()
+            .response
+            .unwrap()
+            .user_profile
+            .recovery_codes_remaining,
+        7
+    );
+}
+
+#[tokio::test]
+async fn test_disabling_with_a_recovery_code_deletes_the_codes() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "recovery").await;
+    let (_, recovery_codes) = enroll_and_confirm(&server, &access_token, None).await;
+    let recovery_codes = recovery_codes.unwrap();
+
+    server
+        .post("/api/v1/auth/mfa/totp/disable")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: recovery_codes[0].clone(),
+        })
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/v1/auth/mfa/recovery-codes")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: recovery_codes[1].clone(),
+        })
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    // Turning it back on hands out a new set
+    let (_, new_codes) = enroll_and_confirm(&server, &access_token, None).await;
+    let new_codes = new_codes.unwrap();
+    assert!(new_codes.iter().all(|code| !recovery_codes.contains(code)));
+    login_with_code(&server, &email, &recovery_codes[1])
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_password_change_takes_a_recovery_code() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "recovery").await;
+    let (_, recovery_codes) = enroll_and_confirm(&server, &access_token, None).await;
+    let recovery_codes = recovery_codes.unwrap();
+
+    server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(&access_token)
+        .json(&ChangePasswordRequest {
+            current_password: TEST_PASSWORD.to_string(),
+            code: Some(recovery_codes[0].clone()),
+            new_password: "another_secure_password456".to_string(),
+        })
+        .await
+        .assert_status_ok();
+
+    // The new password is in place, and logging in still takes a second factor
+    let response = server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email: email.clone(),
+            password: "another_secure_password456".to_string(),
+        })
+        .await;
+    response.assert_status(StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn test_password_change_without_a_code_is_refused() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "recovery").await;
+    enroll_and_confirm(&server, &access_token, None).await;
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(&access_token)
+        .json(&ChangePasswordRequest {
+            current_password: TEST_PASSWORD.to_string(),
+            code: None,
+            new_password: "another_secure_password456".to_string(),
+        })
+        .await;
+    response.assert_status(StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<ChangePasswordResponse>().error.as_deref(),
+        Some("An authentication code is required")
+    );
+
+    // The password did not change
+    login(&server, &email)
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+}
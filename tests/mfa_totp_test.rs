@@ -0,0 +1,270 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use common::{
+    EnrollTotpRequest, MfaLoginRequest, StepUpRequest, TEST_PASSWORD, TestEnrollTotpResponse,
+    TestLoginResponse, TestMfaRequiredResponse, TotpCodeRequest, enroll_and_confirm, login,
+    register, setup_test_state, totp_code,
+};
+use std::sync::Arc;
+
+async fn setup() -> TestServer {
+    let state = setup_test_state().await;
+
+    TestServer::new(create_app(state)).expect("Failed to create test server")
+}
+
+#[tokio::test]
+async fn test_enrollment_returns_provisioning_uri_and_needs_confirmation() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "totp").await;
+
+    let response = server
+        .post("/api/v1/auth/mfa/totp/enroll")
+        .authorization_bearer(&access_token)
+        .json(&EnrollTotpRequest {
+            password: "wrong_password".to_string(),
+            code: None,
+        })
+        .await;
+    response.assert_status(StatusCode::FORBIDDEN);
+
+    let response = server
+        .post("/api/v1/auth/mfa/totp/enroll")
+        .authorization_bearer(&access_token)
+        .json(&EnrollTotpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: None,
+        })
+        .await;
+    response.assert_status_ok();
+    let enrollment = response.json::<TestEnrollTotpResponse>().response.unwrap();
+    assert!(
+        enrollment
+            .provisioning_uri
+            .starts_with("otpauth://totp/Krabby%20Chat:totp_")
+    );
+    assert!(
+        enrollment
+            .provisioning_uri
+            .contains(&format!("secret={}", enrollment.secret))
+    );
+
+    // Not on until confirmed
+    login(&server, &email).await.assert_status_ok();
+
+    server
+        .post("/api/v1/auth/mfa/totp/confirm")
+        .authorization_bearer(&access_token)
+        .json(&TotpCodeRequest {
+            code: "000000".to_string(),
+        })
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .post("/api/v1/auth/mfa/totp/confirm")
+        .authorization_bearer(&access_token)
+        .json(&TotpCodeRequest {
+            code: totp_code(&enrollment.secret, 0),
+        })
+        .await
+        .assert_status_ok();
+
+    login(&server, &email)
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn test_login_takes_a_code_after_the_password() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "totp").await;
+    let secret = enroll_and_confirm(&server, &access_token, None).await.0;
+
+    let response = login(&server, &email).await;
+    response.assert_status(StatusCode::ACCEPTED);
+    let mfa_token = response
+        .json::<TestMfaRequiredResponse>()
+        .response
+        .unwrap()
+        .mfa_token;
+
+    // The password step alone is no access token
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&mfa_token)
+        .await
+        .assert_status_unauthorized();
+
+    let wrong_code = if totp_code(&secret, 0) == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+    server
+        .post("/api/v1/auth/login/mfa")
+        .json(&MfaLoginRequest {
+            mfa_token: mfa_token.clone(),
+            code: wrong_code.to_string(),
+        })
+        .await
+        .assert_status_unauthorized();
+
+    let response = server
+        .post("/api/v1/auth/login/mfa")
+        .json(&MfaLoginRequest {
+            mfa_token: mfa_token.clone(),
+            code: totp_code(&secret, 0),
+        })
+        .await;
+    response.assert_status_ok();
+    let access_token = response
+        .json::<TestLoginResponse>()
+        .response
+        .unwrap()
+        .access_token
+        .unwrap();
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+
+    // Every code works once
+    server
+        .post("/api/v1/auth/login/mfa")
+        .json(&MfaLoginRequest {
+            mfa_token,
+            code: totp_code(&secret, 0),
+        })
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/v1/auth/login/mfa")
+        .json(&MfaLoginRequest {
+            mfa_token: access_token,
+            code: totp_code(&secret, 1),
+        })
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_re_enrollment_and_disabling_need_password_and_code() {
+    let server = setup().await;
+    let (email, access_token) = register(&server, "totp").await;
+    let secret = enroll_and_confirm(&server, &access_token, None).await.0;
+
+    let response = server
+        .post("/api/v1/auth/mfa/totp/enroll")
+        .authorization_bearer(&access_token)
+        .json(&EnrollTotpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: None,
+        })
+        .await;
+    response.assert_status(StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<TestEnrollTotpResponse>().error.as_deref(),
+        Some("An authentication code is required")
+    );
+
+    server
+        .post("/api/v1/auth/mfa/totp/enroll")
+        .authorization_bearer(&access_token)
+        .json(&EnrollTotpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: Some(totp_code(&secret, 0)),
+        })
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/v1/auth/mfa/totp/disable")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: "wrong_password".to_string(),
+            code: totp_code(&secret, 1),
+        })
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // The unconfirmed re-enrollment left the old authenticator in place
+    server
+        .post("/api/v1/auth/mfa/totp/disable")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: totp_code(&secret, 1),
+        })
+        .await
+        .assert_status_ok();
+
+    login(&server, &email).await.assert_status_ok();
+    server
+        .post("/api/v1/auth/mfa/totp/disable")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: totp_code(&secret, 1),
+        })
+        .await
+        .assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_wrong_step_ups_count_as_failed_logins() {
+    let mut state = setup_test_state().await;
+    let security = Arc::get_mut(&mut state.config)
+        .unwrap()
+        .security
+        .as_mut()
+        .unwrap();
+    security.login_max_failed_attempts = 2;
+    security.login_backoff_base_delay_in_seconds = 0;
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+    let (email, access_token) = register(&server, "totp").await;
+    let secret = enroll_and_confirm(&server, &access_token, None).await.0;
+
+    let wrong_code = if totp_code(&secret, 0) == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+    server
+        .post("/api/v1/auth/mfa/totp/disable")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: wrong_code.to_string(),
+        })
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .post("/api/v1/auth/mfa/totp/enroll")
+        .authorization_bearer(&access_token)
+        .json(&EnrollTotpRequest {
+            password: "wrong_password".to_string(),
+            code: None,
+        })
+        .await
+        .assert_status(StatusCode::LOCKED);
+
+    // Locked for step-ups and logins alike, even with the right password and code
+    let response = server
+        .post("/api/v1/auth/mfa/totp/disable")
+        .authorization_bearer(&access_token)
+        .json(&StepUpRequest {
+            password: TEST_PASSWORD.to_string(),
+            code: totp_code(&secret, 0),
+        })
+        .await;
+    response.assert_status(StatusCode::LOCKED);
+    assert!(response.maybe_header("retry-after").is_some());
+    login(&server, &email)
+        .await
+        .assert_status(StatusCode::LOCKED);
+}
@@ -0,0 +1,249 @@
+mod common;
+
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use chat_auth_server::utils::load_config::SecuritySection;
+use chat_auth_server::utils::password_blocklist::PasswordBlocklist;
+use common::{
+    RegisterRequest, TestRegisterResponse, setup_test_server, setup_test_state, token_from_mail,
+    use_memory_mailer, wait_for_mail,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+struct ChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+#[derive(Serialize)]
+struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Serialize)]
+struct ResetPasswordRequest {
+    one_time_password: String,
+    new_password: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestPolicyRejection {
+    error: Option<String>,
+    password_violations: Option<Vec<TestViolation>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestViolation {
+    rule: String,
+    message: String,
+}
+
+fn register_request(password: &str) -> (String, RegisterRequest) {
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("policy_{}@example.com", unique_id);
+
+    (
+        email.clone(),
+        RegisterRequest {
+            first_name: "Grace".to_string(),
+            last_name: "Hopper".to_string(),
+            email,
+            password: password.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        },
+    )
+}
+
+/// The rule names of a `400` password policy rejection.
+fn rejected_rules(response: axum_test::TestResponse) -> Vec<String> {
+    response.assert_status_bad_request();
+    let rejection = response.json::<TestPolicyRejection>();
+    let violations = rejection.password_violations.unwrap();
+
+    assert_eq!(
+        rejection.error.unwrap(),
+        violations
+            .iter()
+            .map(|violation| violation.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    );
+    violations
+        .into_iter()
+        .map(|violation| violation.rule)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_register_rejects_weak_passwords() {
+    let server = setup_test_server().await;
+
+    let (_, request) = register_request("");
+    let response = server.post("/api/v1/auth/register").json(&request).await;
+    assert_eq!(rejected_rules(response), vec!["min_length"]);
+
+    let (_, request) = register_request("gracehopper1906");
+    let response = server.post("/api/v1/auth/register").json(&request).await;
+    assert_eq!(rejected_rules(response), vec!["personal_info"]);
+
+    let (_, request) = register_request("zzzzzzzz");
+    let response = server.post("/api/v1/auth/register").json(&request).await;
+    assert_eq!(rejected_rules(response), vec!["max_repeated_characters"]);
+
+    let (_, request) = register_request("a reasonable passphrase");
+    server
+        .post("/api/v1/auth/register")
+        .json(&request)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_configured_character_classes_are_enforced() {
+    let mut state = setup_test_state().await;
+    Arc::get_mut(&mut state.config).unwrap().security = Some(SecuritySection {
+        password_require_uppercase: true,
+        password_require_digit: true,
+        password_require_symbol: true,
+        ..SecuritySection::default()
+    });
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+
+    let (_, request) = register_request("lowercase only");
+    let response = server.post("/api/v1/auth/register").json(&request).await;
+    assert_eq!(rejected_rules(response), vec!["uppercase", "digit"]);
+
+    let (_, request) = register_request("Upper-and-1");
+    server
+        .post("/api/v1/auth/register")
+        .json(&request)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_change_password_enforces_policy() {
+    let server = setup_test_server().await;
+    let (_, request) = register_request("password123");
+    let access_token = server
+        .post("/api/v1/auth/register")
+        .json(&request)
+        .await
+        .json::<TestRegisterResponse>()
+        .response
+        .unwrap()
+        .access_token
+        .unwrap();
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(access_token)
+        .json(&ChangePasswordRequest {
+            current_password: "password123".to_string(),
+            new_password: "short".to_string(),
+        })
+        .await;
+
+    assert_eq!(rejected_rules(response), vec!["min_length"]);
+}
+
+#[tokio::test]
+async fn test_reset_password_enforces_policy() {
+    let mut state = setup_test_state().await;
+    let mailer = use_memory_mailer(&mut state);
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+
+    let (email, request) = register_request("password123");
+    server
+        .post("/api/v1/auth/register")
+        .json(&request)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    server
+        .post("/api/v1/auth/forgot-password")
+        .json(&ForgotPasswordRequest {
+            email: email.clone(),
+        })
+        .await
+        .assert_status_ok();
+    let code = token_from_mail(&wait_for_mail(&mailer, &email, "Reset").await);
+
+    let response = server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: code.clone(),
+            new_password: "I am Grace".to_string(),
+        })
+        .await;
+    assert_eq!(rejected_rules(response), vec!["personal_info"]);
+
+    // A rejected password leaves the code usable
+    server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: code,
+            new_password: "a reasonable passphrase".to_string(),
+        })
+        .await
+        .assert_status_ok();
+}
+
+/// A server refusing the common passwords shipped in `config/password_blocklist.txt`.
+async fn setup_with_blocklist() -> TestServer {
+    let mut state = setup_test_state().await;
+    state.password_blocklist = Arc::new(
+        PasswordBlocklist::load("config/password_blocklist.txt")
+            .expect("Failed to load the password blocklist"),
+    );
+
+    TestServer::new(create_app(state)).expect("Failed to create test server")
+}
+
+#[tokio::test]
+async fn test_register_rejects_blocklisted_passwords() {
+    let server = setup_with_blocklist().await;
+
+    for password in ["password123", "Qwerty123"] {
+        let (_, request) = register_request(password);
+        let response = server.post("/api/v1/auth/register").json(&request).await;
+        assert_eq!(rejected_rules(response), vec!["breached"]);
+    }
+
+    let (_, request) = register_request("a reasonable passphrase");
+    server
+        .post("/api/v1/auth/register")
+        .json(&request)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_change_password_rejects_blocklisted_passwords() {
+    let server = setup_with_blocklist().await;
+    let (_, request) = register_request("a reasonable passphrase");
+    let access_token = server
+        .post("/api/v1/auth/register")
+        .json(&request)
+        .await
+        .json::<TestRegisterResponse>()
+        .response
+        .unwrap()
+        .access_token
+        .unwrap();
+
+    let response = server
+        .post("/api/v1/auth/password")
+        .authorization_bearer(access_token)
+        .json(&ChangePasswordRequest {
+            current_password: "a reasonable passphrase".to_string(),
+            new_password: "letmein1".to_string(),
+        })
+        .await;
+
+    assert_eq!(rejected_rules(response), vec!["breached"]);
+}
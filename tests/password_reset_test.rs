@@ -0,0 +1,219 @@
+mod common;
+
+use axum_test::TestServer;
+use chat_auth_server::mailer::MemoryMailer;
+use chat_auth_server::utils::generate_tokens::{User, generate_one_time_password_token};
+use chat_auth_server::utils::one_time_password_handler::store_one_time_password;
+use chat_auth_server::{AppState, create_app};
+use common::{
+    LoginRequest, RefreshRequest, TEST_PASSWORD, register_user, setup_test_state, token_from_mail,
+    use_memory_mailer, wait_for_mail,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Serialize)]
+struct ResetPasswordRequest {
+    one_time_password: String,
+    new_password: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestResetPasswordResponse {
+    response_message: String,
+    response: Option<TestResetPasswordCore>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestResetPasswordCore {
+    revoked_sessions: u64,
+}
+
+async fn setup() -> (TestServer, AppState, Arc<MemoryMailer>) {
+    let mut state = setup_test_state().await;
+    let mailer = use_memory_mailer(&mut state);
+    let server = TestServer::new(create_app(state.clone())).expect("Failed to create test server");
+    (server, state, mailer)
+}
+
+/// Issues and stores a code directly, to control its lifetime.
+async fn issue_code(
+    state: &AppState,
+    user_id: i64,
+    email: &str,
+    lifetime_in_minutes: u64,
+) -> String {
+    let user = User {
+        id: user_id,
+        email: email.to_string(),
+        session_id: None,
+    };
+    let code = generate_one_time_password_token(&user, &state.config, &state.jwt_keys)
+        .unwrap()
+        .token;
+
+    store_one_time_password(&state.db, user_id, &code, lifetime_in_minutes)
+        .await
+        .unwrap();
+    code
+}
+
+#[tokio::test]
+async fn test_forgot_password_does_not_reveal_accounts() {
+    let (server, state, mailer) = setup().await;
+    let (email, registered) = register_user(&server, "reset").await;
+
+    let known = server
+        .post("/api/v1/auth/forgot-password")
+        .json(&ForgotPasswordRequest {
+            email: email.clone(),
+        })
+        .await;
+    let unknown = server
+        .post("/api/v1/auth/forgot-password")
+        .json(&ForgotPasswordRequest {
+            email: format!("nobody_{}@example.com", Uuid::new_v4()),
+        })
+        .await;
+
+    known.assert_status_ok();
+    unknown.assert_status_ok();
+    assert_eq!(known.text(), unknown.text());
+    wait_for_mail(&mailer, &email, "Reset").await;
+
+    // Only a digest of the code is stored, together with its expiry
+    let (stored, expires): (Option<String>, Option<chrono::NaiveDateTime>) = sqlx::query_as(
+        "SELECT one_time_password_token, one_time_password_expires_at FROM users WHERE id = $1",
+    )
+    .bind(registered.user_profile.unwrap().id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap();
+    assert_eq!(stored.unwrap().len(), 64);
+    assert!(expires.is_some());
+
+    // Only the known address was sent a code
+    let codes: Vec<_> = mailer
+        .sent()
+        .into_iter()
+        .filter(|message| message.subject.contains("Reset"))
+        .collect();
+    assert_eq!(codes.len(), 1);
+    assert_eq!(codes[0].to, email);
+}
+
+#[tokio::test]
+async fn test_reset_password_success() {
+    let (server, _, mailer) = setup().await;
+    let (email, registered) = register_user(&server, "reset").await;
+
+    server
+        .post("/api/v1/auth/forgot-password")
+        .json(&ForgotPasswordRequest {
+            email: email.clone(),
+        })
+        .await
+        .assert_status_ok();
+    let code = token_from_mail(&wait_for_mail(&mailer, &email, "Reset").await);
+
+    let response = server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: code.clone(),
+            new_password: "new_password456".to_string(),
+        })
+        .await;
+
+    response.assert_status_ok();
+    let body = response.json::<TestResetPasswordResponse>();
+    assert_eq!(body.response_message, "Password reset successfully");
+    assert_eq!(body.response.unwrap().revoked_sessions, 1);
+
+    // Every existing session is gone
+    server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: registered.refresh_token.unwrap(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    // Only the new password works
+    server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email: email.clone(),
+            password: TEST_PASSWORD.to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    server
+        .post("/api/v1/auth/login")
+        .json(&LoginRequest {
+            email,
+            password: "new_password456".to_string(),
+        })
+        .await
+        .assert_status_ok();
+
+    // The code is single-use
+    server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: code,
+            new_password: "another_password789".to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_reset_password_rejects_superseded_and_expired_codes() {
+    let (server, state, _) = setup().await;
+    let (email, registered) = register_user(&server, "reset").await;
+    let user_id = registered.user_profile.unwrap().id;
+
+    let superseded = issue_code(&state, user_id, &email, 5).await;
+    issue_code(&state, user_id, &email, 5).await;
+
+    server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: superseded,
+            new_password: "new_password456".to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    let expired = issue_code(&state, user_id, &email, 0).await;
+
+    server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: expired,
+            new_password: "new_password456".to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_reset_password_rejects_other_token_kinds() {
+    let (server, _, _) = setup().await;
+    let (_, registered) = register_user(&server, "reset").await;
+
+    server
+        .post("/api/v1/auth/reset-password")
+        .json(&ResetPasswordRequest {
+            one_time_password: registered.access_token.unwrap(),
+            new_password: "new_password456".to_string(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use chat_auth_server::sms::{DisabledSmsSender, MemorySmsSender};
+use chat_auth_server::utils::load_config::SmsSection;
+use common::{register_user, setup_test_state, use_memory_sms_sender};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct VerifyPhoneRequest {
+    code: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestVerifyPhoneResponse {
+    response: Option<TestVerifyPhoneCore>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestVerifyPhoneCore {
+    phone_number: String,
+    phone_verified_at: Option<String>,
+}
+
+async fn setup(sms: SmsSection) -> (TestServer, Arc<MemorySmsSender>) {
+    let mut state = setup_test_state().await;
+    let sms_sender = use_memory_sms_sender(&mut state);
+    Arc::get_mut(&mut state.config).unwrap().sms = Some(sms);
+
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+    (server, sms_sender)
+}
+
+/// Registers a user and returns its phone number and access token.
+async fn register(server: &TestServer) -> (String, String) {
+    let (_, registered) = register_user(server, "phone").await;
+
+    (
+        registered.user_profile.unwrap().phone_number,
+        registered.access_token.unwrap(),
+    )
+}
+
+/// The code of the latest message sent to `phone_number`.
+fn sent_code(sms_sender: &MemorySmsSender, phone_number: &str) -> String {
+    let message = sms_sender.last_sent_to(phone_number).expect("No code sent");
+
+    message.body[0..6].to_string()
+}
+
+#[tokio::test]
+async fn test_verify_phone_number_success() {
+    let (server, sms_sender) = setup(SmsSection::default()).await;
+    let (phone_number, access_token) = register(&server).await;
+
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/api/v1/auth/phone/verify")
+        .authorization_bearer(&access_token)
+        .json(&VerifyPhoneRequest {
+            code: sent_code(&sms_sender, &phone_number),
+        })
+        .await;
+
+    response.assert_status_ok();
+    let core = response.json::<TestVerifyPhoneResponse>().response.unwrap();
+    assert_eq!(core.phone_number, phone_number);
+    assert!(core.phone_verified_at.is_some());
+
+    // A verified number needs no further codes
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_resend_is_refused_during_cooldown() {
+    let (server, sms_sender) = setup(SmsSection::default()).await;
+    let (_, access_token) = register(&server).await;
+
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await;
+
+    response.assert_status(axum::http::StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response
+        .header("retry-after")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+    assert_eq!(sms_sender.sent().len(), 1);
+}
+
+#[tokio::test]
+async fn test_resend_replaces_previous_code() {
+    let (server, sms_sender) = setup(SmsSection {
+        phone_code_resend_cooldown_in_seconds: 0,
+        ..SmsSection::default()
+    })
+    .await;
+    let (phone_number, access_token) = register(&server).await;
+
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+    let first_code = sent_code(&sms_sender, &phone_number);
+
+    // Retry until the new code differs, since two random codes can collide
+    let mut second_code = first_code.clone();
+    while second_code == first_code {
+        server
+            .post("/api/v1/auth/phone/send-code")
+            .authorization_bearer(&access_token)
+            .await
+            .assert_status_ok();
+        second_code = sent_code(&sms_sender, &phone_number);
+    }
+
+    server
+        .post("/api/v1/auth/phone/verify")
+        .authorization_bearer(&access_token)
+        .json(&VerifyPhoneRequest { code: first_code })
+        .await
+        .assert_status_bad_request();
+
+    server
+        .post("/api/v1/auth/phone/verify")
+        .authorization_bearer(&access_token)
+        .json(&VerifyPhoneRequest { code: second_code })
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_code_stops_working_after_max_attempts() {
+    let (server, sms_sender) = setup(SmsSection {
+        phone_code_max_attempts: 2,
+        ..SmsSection::default()
+    })
+    .await;
+    let (phone_number, access_token) = register(&server).await;
+
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+    let code = sent_code(&sms_sender, &phone_number);
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..2 {
+        let response = server
+            .post("/api/v1/auth/phone/verify")
+            .authorization_bearer(&access_token)
+            .json(&VerifyPhoneRequest {
+                code: wrong_code.to_string(),
+            })
+            .await;
+
+        response.assert_status_bad_request();
+        assert_eq!(
+            response.json::<TestVerifyPhoneResponse>().error.unwrap(),
+            "Invalid or expired verification code"
+        );
+    }
+
+    server
+        .post("/api/v1/auth/phone/verify")
+        .authorization_bearer(&access_token)
+        .json(&VerifyPhoneRequest { code })
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_phone_verification_requires_authentication() {
+    let (server, _) = setup(SmsSection::default()).await;
+
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/v1/auth/phone/verify")
+        .json(&VerifyPhoneRequest {
+            code: "123456".to_string(),
+        })
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_disabled_sms_transport_refuses_to_send_codes() {
+    let mut state = setup_test_state().await;
+    state.sms_sender = Arc::new(DisabledSmsSender);
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+    let (_, access_token) = register(&server).await;
+
+    server
+        .post("/api/v1/auth/phone/send-code")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+}
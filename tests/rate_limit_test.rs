@@ -0,0 +1,195 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chat_auth_server::rate_limit::{MemoryRateLimitStore, PostgresRateLimitStore, RateLimitStore};
+use chat_auth_server::utils::load_config::{RateLimitRule, RateLimitSection};
+use chat_auth_server::{AppState, create_app};
+use common::{LoginRequest, setup_test_state};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// State metering logins at a burst of two, reading client IPs from `X-Forwarded-For`.
+async fn rate_limited_state(enabled: bool) -> AppState {
+    let mut state = setup_test_state().await;
+    let config = Arc::get_mut(&mut state.config).unwrap();
+    config.client_integrations.allow_rate_limit_middleware = enabled;
+    config.server.as_mut().unwrap().trust_proxy_headers = true;
+    config.rate_limit = Some(RateLimitSection {
+        login: RateLimitRule {
+            burst: 2,
+            per_minute: 1,
+        },
+        ..RateLimitSection::default()
+    });
+    state
+}
+
+fn server_with_store(mut state: AppState, store: Arc<dyn RateLimitStore>) -> TestServer {
+    state.rate_limit_store = store;
+    TestServer::new(create_app(state)).expect("Failed to create test server")
+}
+
+fn unique_ip() -> String {
+    let bytes = Uuid::new_v4().into_bytes();
+    format!("10.{}.{}.{}", bytes[0], bytes[1], bytes[2])
+}
+
+fn unique_email() -> String {
+    format!("rate_limit_{}@example.com", Uuid::new_v4())
+}
+
+async fn login(server: &TestServer, ip: &str, email: &str) -> axum_test::TestResponse {
+    server
+        .post("/api/v1/auth/login")
+        .add_header("x-forwarded-for", ip)
+        .json(&LoginRequest {
+            email: email.to_string(),
+            password: "wrong_password".to_string(),
+        })
+        .await
+}
+
+fn header_value(response: &axum_test::TestResponse, name: &str) -> u64 {
+    response.header(name).to_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_login_is_limited_per_client_ip() {
+    let server = server_with_store(
+        rate_limited_state(true).await,
+        Arc::new(MemoryRateLimitStore::default()),
+    );
+    let ip = unique_ip();
+
+    let response = login(&server, &ip, &unique_email()).await;
+    response.assert_status_unauthorized();
+    assert_eq!(header_value(&response, "ratelimit-limit"), 2);
+    assert_eq!(header_value(&response, "ratelimit-remaining"), 1);
+
+    login(&server, &ip, &unique_email())
+        .await
+        .assert_status_unauthorized();
+
+    // Another email does not help once the IP has used up its burst
+    let response = login(&server, &ip, &unique_email()).await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    let retry_after = header_value(&response, "retry-after");
+    assert!(retry_after > 0 && retry_after <= 60);
+    assert_eq!(header_value(&response, "ratelimit-remaining"), 0);
+
+    // Other clients are unaffected
+    login(&server, &unique_ip(), &unique_email())
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_forged_forwarded_for_entries_do_not_reset_the_bucket() {
+    let server = server_with_store(
+        rate_limited_state(true).await,
+        Arc::new(MemoryRateLimitStore::default()),
+    );
+    let ip = unique_ip();
+
+    // Only the entry appended by the proxy counts, whatever the client puts before it
+    for _ in 0..2 {
+        login(
+            &server,
+            &format!("{}, {}", unique_ip(), ip),
+            &unique_email(),
+        )
+        .await
+        .assert_status_unauthorized();
+    }
+    login(
+        &server,
+        &format!("{}, {}", unique_ip(), ip),
+        &unique_email(),
+    )
+    .await
+    .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_login_is_limited_per_account() {
+    let server = server_with_store(
+        rate_limited_state(true).await,
+        Arc::new(MemoryRateLimitStore::default()),
+    );
+    let email = unique_email();
+
+    for _ in 0..2 {
+        login(&server, &unique_ip(), &email)
+            .await
+            .assert_status_unauthorized();
+    }
+
+    // Spreading the attempts over IPs does not help either, whatever the email's case
+    login(&server, &unique_ip(), &email.to_uppercase())
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_other_routes_use_their_own_rule() {
+    let server = server_with_store(
+        rate_limited_state(true).await,
+        Arc::new(MemoryRateLimitStore::default()),
+    );
+    let ip = unique_ip();
+
+    for _ in 0..2 {
+        login(&server, &ip, &unique_email()).await;
+    }
+
+    let response = server
+        .get("/.well-known/jwks.json")
+        .add_header("x-forwarded-for", &ip)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        header_value(&response, "ratelimit-limit"),
+        u64::from(RateLimitSection::default().default.burst)
+    );
+}
+
+#[tokio::test]
+async fn test_rate_limiting_is_off_unless_enabled() {
+    let server = server_with_store(
+        rate_limited_state(false).await,
+        Arc::new(MemoryRateLimitStore::default()),
+    );
+    let ip = unique_ip();
+
+    for _ in 0..3 {
+        let response = login(&server, &ip, &unique_email()).await;
+        response.assert_status_unauthorized();
+        assert!(response.maybe_header("ratelimit-limit").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_postgres_store_is_shared_between_instances() {
+    let state = rate_limited_state(true).await;
+    let db = state.db.clone();
+
+    // Two instances, each with its own store over the same database
+    let first = server_with_store(
+        state.clone(),
+        Arc::new(PostgresRateLimitStore::new(db.clone())),
+    );
+    let second = server_with_store(state, Arc::new(PostgresRateLimitStore::new(db)));
+    let ip = unique_ip();
+
+    login(&first, &ip, &unique_email())
+        .await
+        .assert_status_unauthorized();
+    login(&second, &ip, &unique_email())
+        .await
+        .assert_status_unauthorized();
+
+    let response = login(&first, &ip, &unique_email()).await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(header_value(&response, "retry-after") > 0);
+}
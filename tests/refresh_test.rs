@@ -0,0 +1,111 @@
+mod common;
+
+use axum_test::TestServer;
+use common::{RefreshRequest, TestRefreshResponse, register_user, setup_test_server};
+
+async fn register_and_get_refresh_token(server: &TestServer) -> String {
+    register_user(server, "refresh")
+        .await
+        .1
+        .refresh_token
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_refresh_tokens_success() {
+    let server = setup_test_server().await;
+    let refresh_token = register_and_get_refresh_token(&server).await;
+
+    let response = server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: refresh_token.clone(),
+        })
+        .await;
+
+    response.assert_status(axum::http::StatusCode::OK);
+    let body = response.json::<TestRefreshResponse>();
+    assert_eq!(body.response_message, "Tokens refreshed successfully");
+    let res = body.response.unwrap();
+    assert!(res.access_token.is_some());
+    assert_ne!(res.refresh_token.unwrap(), refresh_token);
+}
+
+#[tokio::test]
+async fn test_refresh_token_reuse_revokes_session() {
+    let server = setup_test_server().await;
+    let original_token = register_and_get_refresh_token(&server).await;
+
+    // Legitimate rotation
+    let rotated_token = server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: original_token.clone(),
+        })
+        .await
+        .json::<TestRefreshResponse>()
+        .response
+        .unwrap()
+        .refresh_token
+        .unwrap();
+
+    // Replaying the rotated-out token is treated as theft
+    let response = server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: original_token,
+        })
+        .await;
+
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    let body = response.json::<TestRefreshResponse>();
+    assert_eq!(
+        body.error.unwrap(),
+        "Refresh token reuse detected; session has been revoked"
+    );
+
+    // ...and the newest token of the same session is revoked with it
+    server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: rotated_token,
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_refresh_with_invalid_token() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: "not.a.jwt".to_string(),
+        })
+        .await;
+
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_token_kinds_cannot_be_swapped() {
+    let server = setup_test_server().await;
+    let (_, tokens) = register_user(&server, "refresh").await;
+
+    // An access token is not a refresh token...
+    let response = server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: tokens.access_token.unwrap(),
+        })
+        .await;
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    // ...and a refresh token is not an access token
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(tokens.refresh_token.unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
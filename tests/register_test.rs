@@ -1,6 +1,12 @@
 mod common;
 
-use common::{RegisterRequest, TestRegisterResponse, setup_test_server};
+use axum_test::TestServer;
+use chat_auth_server::create_app;
+use common::{
+    RegisterRequest, TestRegisterResponse, setup_test_server, setup_test_state, use_memory_mailer,
+    use_memory_sms_sender, wait_for_mail,
+};
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[tokio::test]
@@ -116,3 +122,82 @@ async fn test_register_user_duplicate_phone_number() {
     let body = response.json::<TestRegisterResponse>();
     assert_eq!(body.error.unwrap(), "Phone number already exists");
 }
+
+#[tokio::test]
+async fn test_enumeration_resistant_registration() {
+    let mut state = setup_test_state().await;
+    let mailer = use_memory_mailer(&mut state);
+    let sms_sender = use_memory_sms_sender(&mut state);
+    Arc::get_mut(&mut state.config)
+        .unwrap()
+        .auth
+        .as_mut()
+        .unwrap()
+        .enumeration_resistant_registration = true;
+    let server = TestServer::new(create_app(state)).expect("Failed to create test server");
+
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("resistant_{}@example.com", unique_id);
+    let other_email = format!("resistant_other_{}@example.com", unique_id);
+    let phone = unique_id[0..10].to_string();
+    let other_phone = unique_id[11..21].to_string();
+
+    let register = |email: &str, phone: &str| {
+        server.post("/api/v1/auth/register").json(&RegisterRequest {
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+            email: email.to_string(),
+            password: "password123".to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: phone.to_string(),
+        })
+    };
+
+    // A new account, a taken email and a taken phone number all look the same
+    let mut bodies = Vec::new();
+    for (email, phone) in [
+        (&email, &phone),
+        (&email, &other_phone),
+        (&other_email, &phone),
+    ] {
+        let response = register(email, phone).await;
+        response.assert_status(axum::http::StatusCode::ACCEPTED);
+        let body = response.json::<TestRegisterResponse>();
+        assert!(body.response.is_none());
+        assert!(body.error.is_none());
+        bodies.push(body.response_message);
+    }
+    assert!(bodies.iter().all(|body| body == &bodies[0]));
+
+    // The owner of the taken email hears about the second attempt by mail, and the owner
+    // of the taken phone number about the third by text message, once answered
+    wait_for_mail(&mailer, &email, "Verify").await;
+    wait_for_mail(&mailer, &email, "already exists").await;
+    assert_eq!(
+        mailer
+            .sent()
+            .into_iter()
+            .filter(|message| message.to == email)
+            .count(),
+        2
+    );
+    for _ in 0..100 {
+        if sms_sender.last_sent_to(&phone).is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(
+        sms_sender
+            .last_sent_to(&phone)
+            .expect("No notice texted")
+            .body
+            .contains("already has one")
+    );
+    assert!(
+        mailer
+            .sent()
+            .into_iter()
+            .all(|message| message.to != other_email)
+    );
+}
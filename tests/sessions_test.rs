@@ -0,0 +1,166 @@
+mod common;
+
+use axum_test::TestServer;
+use common::{
+    LoginRequest, RefreshRequest, RegisterRequest, TestLoginResponse, TestRegisterResponse,
+    TestResponseCore, TestSessionsResponse, setup_test_server,
+};
+use uuid::Uuid;
+
+const PASSWORD: &str = "password123";
+
+/// Registers from a desktop browser, then logs in from a phone.
+async fn register_on_two_devices(server: &TestServer) -> (TestResponseCore, TestResponseCore) {
+    let unique_id = Uuid::new_v4().to_string();
+    let email = format!("sessions_{}@example.com", unique_id);
+
+    let desktop = server
+        .post("/api/v1/auth/register")
+        .add_header("User-Agent", "Desktop Browser")
+        .json(&RegisterRequest {
+            first_name: "Sessions".to_string(),
+            last_name: "User".to_string(),
+            email: email.clone(),
+            password: PASSWORD.to_string(),
+            country: "TestCountry".to_string(),
+            phone_number: unique_id[0..10].to_string(),
+        })
+        .await
+        .json::<TestRegisterResponse>()
+        .response
+        .unwrap();
+
+    let phone = server
+        .post("/api/v1/auth/login")
+        .add_header("User-Agent", "Phone App")
+        .json(&LoginRequest {
+            email,
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .json::<TestLoginResponse>()
+        .response
+        .unwrap();
+
+    (desktop, phone)
+}
+
+#[tokio::test]
+async fn test_list_sessions() {
+    let server = setup_test_server().await;
+    let (_desktop, phone) = register_on_two_devices(&server).await;
+
+    let response = server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(phone.access_token.unwrap())
+        .await;
+
+    response.assert_status(axum::http::StatusCode::OK);
+    let sessions = response
+        .json::<TestSessionsResponse>()
+        .response
+        .unwrap()
+        .sessions;
+
+    assert_eq!(sessions.len(), 2);
+    let current: Vec<_> = sessions.iter().filter(|session| session.current).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].user_agent.as_deref(), Some("Phone App"));
+    assert!(
+        sessions
+            .iter()
+            .any(|session| session.user_agent.as_deref() == Some("Desktop Browser"))
+    );
+}
+
+#[tokio::test]
+async fn test_revoke_other_session() {
+    let server = setup_test_server().await;
+    let (desktop, phone) = register_on_two_devices(&server).await;
+    let phone_access_token = phone.access_token.unwrap();
+
+    let sessions = server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(phone_access_token.clone())
+        .await
+        .json::<TestSessionsResponse>()
+        .response
+        .unwrap()
+        .sessions;
+    let desktop_session = sessions.iter().find(|session| !session.current).unwrap();
+
+    server
+        .delete(&format!("/api/v1/auth/sessions/{}", desktop_session.id))
+        .authorization_bearer(phone_access_token.clone())
+        .await
+        .assert_status(axum::http::StatusCode::OK);
+
+    // The desktop can neither use its access token nor renew it
+    server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(desktop.access_token.unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    server
+        .post("/api/v1/auth/refresh")
+        .json(&RefreshRequest {
+            refresh_token: desktop.refresh_token.unwrap(),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    // ...while the phone keeps working
+    let remaining = server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(phone_access_token)
+        .await
+        .json::<TestSessionsResponse>()
+        .response
+        .unwrap()
+        .sessions;
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0].current);
+}
+
+#[tokio::test]
+async fn test_revoke_unknown_session() {
+    let server = setup_test_server().await;
+    let (desktop, phone) = register_on_two_devices(&server).await;
+
+    server
+        .delete(&format!("/api/v1/auth/sessions/{}", Uuid::new_v4()))
+        .authorization_bearer(phone.access_token.unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    // Another user's session cannot be revoked either
+    let (_other_desktop, other_phone) = register_on_two_devices(&server).await;
+    let desktop_sessions = server
+        .get("/api/v1/auth/sessions")
+        .authorization_bearer(desktop.access_token.unwrap())
+        .await
+        .json::<TestSessionsResponse>()
+        .response
+        .unwrap()
+        .sessions;
+
+    server
+        .delete(&format!("/api/v1/auth/sessions/{}", desktop_sessions[0].id))
+        .authorization_bearer(other_phone.access_token.unwrap())
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_sessions_require_authentication() {
+    let server = setup_test_server().await;
+
+    server
+        .get("/api/v1/auth/sessions")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    server
+        .delete(&format!("/api/v1/auth/sessions/{}", Uuid::new_v4()))
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}